@@ -0,0 +1,177 @@
+#![macro_use]
+
+use crate::internal_prelude::*;
+use crate::mutation_observer::{queue_mutation_record, MutationRecord};
+use crate::range;
+
+use std::sync::RwLock;
+
+/// CharacterDataBehavior trait for internal use only. Offsets and counts are in UTF-16 code
+/// units, as in browsers.
+pub(crate) trait CharacterDataBehavior {
+    fn data(&self) -> String;
+    fn set_data(&self, data: String);
+    fn length(&self) -> usize;
+    fn substring_data(&self, offset: usize, count: usize) -> Result<String, DomError>;
+    fn append_data(&self, data: String);
+    fn insert_data(&self, offset: usize, data: String) -> Result<(), DomError>;
+    fn delete_data(&self, offset: usize, count: usize) -> Result<(), DomError>;
+    fn replace_data(&self, offset: usize, count: usize, data: String) -> Result<(), DomError>;
+}
+
+/// The text of a [CharacterData](https://developer.mozilla.org/en-US/docs/Web/API/CharacterData)
+/// node. Strings cannot hold unpaired surrogates, so an offset which splits a surrogate pair
+/// leaves replacement characters in the data.
+#[derive(Default)]
+pub struct CharacterDataBehaviorStorage {
+    data: RwLock<String>,
+}
+
+/// Cloning copies the data, so the clone does not share it
+impl Clone for CharacterDataBehaviorStorage {
+    fn clone(&self) -> CharacterDataBehaviorStorage {
+        CharacterDataBehaviorStorage::new(self.data())
+    }
+}
+
+impl CharacterDataBehaviorStorage {
+    pub fn new(data: String) -> CharacterDataBehaviorStorage {
+        CharacterDataBehaviorStorage {
+            data: RwLock::new(data),
+        }
+    }
+
+    pub fn data(&self) -> String {
+        self.data.read().unwrap().clone()
+    }
+
+    pub fn length(&self) -> usize {
+        self.data.read().unwrap().encode_utf16().count()
+    }
+
+    /// Gives the (offset, count) of the code units in the data which an operation affects,
+    /// clamping the count to the end of the data
+    fn clamp(units: &[u16], offset: usize, count: usize) -> Result<(usize, usize), DomError> {
+        if offset > units.len() {
+            return Err(DomError::Range);
+        }
+        Ok((offset, count.min(units.len() - offset)))
+    }
+
+    pub fn substring_data(&self, offset: usize, count: usize) -> Result<String, DomError> {
+        let units: Vec<u16> = self.data.read().unwrap().encode_utf16().collect();
+        let (offset, count) = Self::clamp(&units, offset, count)?;
+        Ok(String::from_utf16_lossy(&units[offset..offset + count]))
+    }
+
+    /// [Replaces data](https://dom.spec.whatwg.org/#concept-cd-replace) of `node`, queueing a
+    /// "characterData" mutation record and updating live ranges
+    pub fn replace_data(
+        &self,
+        node: &AnyNodeArc,
+        offset: usize,
+        count: usize,
+        data: String,
+    ) -> Result<(), DomError> {
+        let (old_value, offset, count) = {
+            let mut lock = self.data.write().unwrap();
+            let mut units: Vec<u16> = lock.encode_utf16().collect();
+            let (offset, count) = Self::clamp(&units, offset, count)?;
+            units.splice(offset..offset + count, data.encode_utf16());
+            let old_value = std::mem::replace(&mut *lock, String::from_utf16_lossy(&units));
+            (old_value, offset, count)
+        };
+        queue_mutation_record(MutationRecord::character_data(node, old_value));
+        range::data_replaced(node, offset, count, data.encode_utf16().count());
+        Ok(())
+    }
+}
+
+/// Implements CharacterDataBehavior
+#[macro_export]
+macro_rules! impl_character_data {
+    ($structname: ty, $($fieldname: ident).+) => {
+        paste::paste! {
+            impl CharacterDataBehavior for $structname {
+                fn data(&self) -> String {
+                    self.$($fieldname).+.data()
+                }
+
+                fn set_data(&self, data: String) {
+                    let length = self.$($fieldname).+.length();
+                    // Replacing from offset 0 cannot be out of range
+                    let _ = self.replace_data(0, length, data);
+                }
+
+                fn length(&self) -> usize {
+                    self.$($fieldname).+.length()
+                }
+
+                fn substring_data(&self, offset: usize, count: usize) -> Result<String, DomError> {
+                    self.$($fieldname).+.substring_data(offset, count)
+                }
+
+                fn append_data(&self, data: String) {
+                    let length = self.$($fieldname).+.length();
+                    // Replacing at the end cannot be out of range
+                    let _ = self.replace_data(length, 0, data);
+                }
+
+                fn insert_data(&self, offset: usize, data: String) -> Result<(), DomError> {
+                    self.replace_data(offset, 0, data)
+                }
+
+                fn delete_data(&self, offset: usize, count: usize) -> Result<(), DomError> {
+                    self.replace_data(offset, count, String::new())
+                }
+
+                fn replace_data(&self, offset: usize, count: usize, data: String) -> Result<(), DomError> {
+                    self.$($fieldname).+.replace_data(&self.clone().into(), offset, count, data)
+                }
+            }
+        }
+    };
+}
+
+/// Passes methods through to CharacterDataBehavior, for public use.
+#[macro_export]
+macro_rules! proxy_character_data_behavior {
+    () => {
+        paste::paste! {
+            /// [CharacterData.data](https://developer.mozilla.org/en-US/docs/Web/API/CharacterData/data)
+            pub fn data(&self) -> String {
+                <Self as crate::behavior::CharacterDataBehavior>::data(self)
+            }
+            /// Sets [CharacterData.data](https://developer.mozilla.org/en-US/docs/Web/API/CharacterData/data),
+            /// queueing a "characterData" mutation record
+            pub fn set_data(&self, data: String) {
+                <Self as crate::behavior::CharacterDataBehavior>::set_data(self, data)
+            }
+            /// [CharacterData.length](https://developer.mozilla.org/en-US/docs/Web/API/CharacterData/length),
+            /// in UTF-16 code units
+            pub fn length(&self) -> usize {
+                <Self as crate::behavior::CharacterDataBehavior>::length(self)
+            }
+            /// [CharacterData.substringData](https://developer.mozilla.org/en-US/docs/Web/API/CharacterData/substringData)
+            pub fn substring_data(&self, offset: usize, count: usize) -> Result<String, DomError> {
+                <Self as crate::behavior::CharacterDataBehavior>::substring_data(self, offset, count)
+            }
+            /// [CharacterData.appendData](https://developer.mozilla.org/en-US/docs/Web/API/CharacterData/appendData)
+            pub fn append_data(&self, data: String) {
+                <Self as crate::behavior::CharacterDataBehavior>::append_data(self, data)
+            }
+            /// [CharacterData.insertData](https://developer.mozilla.org/en-US/docs/Web/API/CharacterData/insertData)
+            pub fn insert_data(&self, offset: usize, data: String) -> Result<(), DomError> {
+                <Self as crate::behavior::CharacterDataBehavior>::insert_data(self, offset, data)
+            }
+            /// [CharacterData.deleteData](https://developer.mozilla.org/en-US/docs/Web/API/CharacterData/deleteData)
+            pub fn delete_data(&self, offset: usize, count: usize) -> Result<(), DomError> {
+                <Self as crate::behavior::CharacterDataBehavior>::delete_data(self, offset, count)
+            }
+            /// [CharacterData.replaceData](https://developer.mozilla.org/en-US/docs/Web/API/CharacterData/replaceData)
+            pub fn replace_data(&self, offset: usize, count: usize, data: String) -> Result<(), DomError> {
+                <Self as crate::behavior::CharacterDataBehavior>::replace_data(self, offset, count, data)
+            }
+        }
+    };
+}
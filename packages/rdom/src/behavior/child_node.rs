@@ -0,0 +1,374 @@
+#![macro_use]
+
+use crate::internal_prelude::*;
+use crate::node::concrete::{DocumentNodeArc, ElementNodeArc, TextNodeArc};
+use crate::node::contents::{NodeContentsArc, TextStore};
+use crate::node::{NodeCommon, NodeOrString};
+use crate::range::is_inclusive_ancestor;
+
+use std::convert::TryFrom;
+
+/// ChildNodeBehavior trait for internal use only.
+pub(crate) trait ChildNodeBehavior {
+    fn before(&self, nodes: Vec<NodeOrString>) -> Result<(), DomError>;
+    fn after(&self, nodes: Vec<NodeOrString>) -> Result<(), DomError>;
+    fn replace_with(&self, nodes: Vec<NodeOrString>) -> Result<(), DomError>;
+    fn remove(&self) -> Result<(), DomError>;
+    fn previous_element_sibling(&self) -> Result<Option<ElementNodeArc>, DomError>;
+    fn next_element_sibling(&self) -> Result<Option<ElementNodeArc>, DomError>;
+}
+
+/// Where nodes are inserted among the children of their parent which stay in place, i.e. those
+/// which are neither being inserted nor replaced
+pub(crate) enum Position<'a> {
+    /// Directly after the given child, or first if there is none
+    After(Option<&'a AnyNodeArc>),
+    /// Directly before the given child, or last if there is none
+    Before(Option<&'a AnyNodeArc>),
+    /// In place of all the children
+    All,
+}
+
+fn is_text(node: &AnyNodeArc) -> bool {
+    matches!(
+        node.contents,
+        NodeContentsArc::Text(_) | NodeContentsArc::CDataSection(_)
+    )
+}
+
+/// [Ensures](https://dom.spec.whatwg.org/#concept-node-ensure-pre-insertion-validity) that
+/// inserting `nodes`, converted into a single node, into `parent` at `position` gives a valid
+/// tree. When `replaced` is given, the nodes [replace](https://dom.spec.whatwg.org/#concept-node-replace)
+/// it instead.
+fn ensure_insertion_validity(
+    parent: &AnyNodeArc,
+    nodes: &[NodeOrString],
+    replaced: Option<&AnyNodeArc>,
+    position: Position,
+) -> Result<(), DomError> {
+    let parent_is_document = matches!(parent.contents, NodeContentsArc::Document(_));
+    let mut inserted = Vec::new();
+    for item in nodes {
+        let node = match item {
+            NodeOrString::Node(node) => node,
+            NodeOrString::String(_) if parent_is_document => {
+                return Err(DomError::HierarchyRequest)
+            }
+            NodeOrString::String(_) => continue,
+        };
+        let is_valid = match node.contents {
+            NodeContentsArc::Document(_) | NodeContentsArc::Attribute(_) => false,
+            NodeContentsArc::DocumentType(_) => parent_is_document,
+            _ => !(parent_is_document && is_text(node)) && !is_inclusive_ancestor(node, parent),
+        };
+        if !is_valid {
+            return Err(DomError::HierarchyRequest);
+        }
+        match node.contents {
+            NodeContentsArc::DocumentFragment(_) => {
+                inserted.extend(node.common.node_graph.static_child_nodes())
+            }
+            _ => inserted.push(node.clone()),
+        }
+    }
+    if !parent_is_document {
+        return Ok(());
+    }
+
+    // A document has at most one element and one doctype, with the doctype first
+    let is_argument = |child: &AnyNodeArc| {
+        nodes.iter().any(|item| match item {
+            NodeOrString::Node(node) => node.ptr_eq(child),
+            NodeOrString::String(_) => false,
+        })
+    };
+    let siblings: Vec<AnyNodeArc> = match position {
+        Position::All => Vec::new(),
+        _ => parent
+            .common
+            .node_graph
+            .static_child_nodes()
+            .into_iter()
+            .filter(|child| !is_argument(child) && replaced.is_none_or(|r| !r.ptr_eq(child)))
+            .collect(),
+    };
+    let index_of = |child: &AnyNodeArc| siblings.iter().position(|sibling| sibling.ptr_eq(child));
+    let index = match position {
+        Position::After(child) => child.and_then(index_of).map_or(0, |index| index + 1),
+        Position::Before(child) => child.and_then(index_of).unwrap_or(siblings.len()),
+        Position::All => 0,
+    };
+    let is_element = |node: &AnyNodeArc| matches!(node.contents, NodeContentsArc::Element(_));
+    let is_doctype = |node: &AnyNodeArc| matches!(node.contents, NodeContentsArc::DocumentType(_));
+
+    let elements = inserted.iter().filter(|node| is_element(node)).count();
+    let is_valid = match elements {
+        0 => true,
+        1 => !siblings.iter().any(is_element) && !siblings[index..].iter().any(is_doctype),
+        _ => false,
+    };
+    let doctypes = inserted.iter().filter(|node| is_doctype(node)).count();
+    let is_valid = is_valid
+        && match doctypes {
+            0 => true,
+            1 => {
+                !siblings.iter().any(is_doctype)
+                    && !siblings[..index].iter().any(is_element)
+                    && !inserted
+                        .iter()
+                        .take_while(|node| !is_doctype(node))
+                        .any(is_element)
+            }
+            _ => false,
+        };
+    if !is_valid || inserted.iter().any(is_text) {
+        return Err(DomError::HierarchyRequest);
+    }
+    Ok(())
+}
+
+/// [Converts](https://dom.spec.whatwg.org/#converting-nodes-into-a-node) the arguments of a
+/// ChildNode or ParentNode method into the nodes to insert into `parent`, once it is certain
+/// that inserting them at `position`, or in place of `replaced`, gives a valid tree. Strings
+/// become text nodes in the document of `node`, and document fragments are replaced by their
+/// children.
+pub(crate) fn convert_nodes(
+    node: &AnyNodeArc,
+    parent: &AnyNodeArc,
+    nodes: Vec<NodeOrString>,
+    replaced: Option<&AnyNodeArc>,
+    position: Position,
+) -> Result<Vec<AnyNodeArc>, DomError> {
+    ensure_insertion_validity(parent, &nodes, replaced, position)?;
+
+    let document = DocumentNodeArc::try_from(node.clone())
+        .ok()
+        .or_else(|| node.owner_document());
+    let mut converted = Vec::new();
+    for item in nodes {
+        match item {
+            NodeOrString::Node(node) => {
+                if let NodeContentsArc::DocumentFragment(_) = node.contents {
+                    converted.extend(node.common.node_graph.static_child_nodes());
+                    node.common.node_graph.replace_all(Vec::new());
+                } else {
+                    converted.push(node);
+                }
+            }
+            NodeOrString::String(text) => {
+                let text = TextNodeArc::new(node.get_context(), Arc::new(TextStore::new(text)));
+                if let Some(document) = &document {
+                    text.common.set_owner_document(document);
+                }
+                converted.push(text.into());
+            }
+        }
+    }
+    Ok(converted)
+}
+
+/// The first sibling of `node` in the given direction which is not one of `nodes`
+fn viable_sibling(node: &AnyNodeArc, nodes: &[NodeOrString], next: bool) -> Option<AnyNodeArc> {
+    let step = |node: &AnyNodeArc| {
+        if next {
+            node.common.node_graph.next_sibling()
+        } else {
+            node.common.node_graph.previous_sibling()
+        }
+    };
+    let mut sibling = step(node);
+    while let Some(candidate) = sibling {
+        let is_argument = nodes.iter().any(|item| match item {
+            NodeOrString::Node(node) => node.ptr_eq(&candidate),
+            NodeOrString::String(_) => false,
+        });
+        if !is_argument {
+            return Some(candidate);
+        }
+        sibling = step(&candidate);
+    }
+    None
+}
+
+pub struct ChildNodeBehaviorStorage {
+    pub(crate) node: Weak<NodeCommon>,
+}
+
+impl ChildNodeBehaviorStorage {
+    pub fn new(node: Weak<NodeCommon>) -> ChildNodeBehaviorStorage {
+        ChildNodeBehaviorStorage { node }
+    }
+
+    fn node(&self) -> Result<AnyNodeArc, DomError> {
+        let node = self.node.upgrade().ok_or(DomError::SandboxDropped)?;
+        node.node_graph.self_arc()
+    }
+
+    pub fn before(&self, nodes: Vec<NodeOrString>) -> Result<(), DomError> {
+        let node = self.node()?;
+        let parent = match node.common.node_graph.parent_node() {
+            Some(parent) => parent,
+            None => return Ok(()),
+        };
+        let viable_previous_sibling = viable_sibling(&node, &nodes, false);
+        let position = Position::After(viable_previous_sibling.as_ref());
+        let nodes = convert_nodes(&node, &parent, nodes, None, position)?;
+        let reference = match viable_previous_sibling {
+            Some(sibling) => sibling.common.node_graph.next_sibling(),
+            None => parent.common.node_graph.first_child(),
+        };
+        parent
+            .common
+            .node_graph
+            .splice(None, nodes, reference.as_ref());
+        Ok(())
+    }
+
+    pub fn after(&self, nodes: Vec<NodeOrString>) -> Result<(), DomError> {
+        let node = self.node()?;
+        let parent = match node.common.node_graph.parent_node() {
+            Some(parent) => parent,
+            None => return Ok(()),
+        };
+        let viable_next_sibling = viable_sibling(&node, &nodes, true);
+        let position = Position::Before(viable_next_sibling.as_ref());
+        let nodes = convert_nodes(&node, &parent, nodes, None, position)?;
+        parent
+            .common
+            .node_graph
+            .splice(None, nodes, viable_next_sibling.as_ref());
+        Ok(())
+    }
+
+    pub fn replace_with(&self, nodes: Vec<NodeOrString>) -> Result<(), DomError> {
+        let node = self.node()?;
+        let parent = match node.common.node_graph.parent_node() {
+            Some(parent) => parent,
+            None => return Ok(()),
+        };
+        let viable_next_sibling = viable_sibling(&node, &nodes, true);
+        let position = Position::Before(viable_next_sibling.as_ref());
+        let nodes = convert_nodes(&node, &parent, nodes, Some(&node), position)?;
+        parent
+            .common
+            .node_graph
+            .splice(Some(&node), nodes, viable_next_sibling.as_ref());
+        Ok(())
+    }
+
+    pub fn remove(&self) -> Result<(), DomError> {
+        let node = self.node()?;
+        if let Some(parent) = node.common.node_graph.parent_node() {
+            parent.common.node_graph.remove_child(&node);
+        }
+        Ok(())
+    }
+
+    /// The first element sibling of the node in the given direction
+    fn element_sibling(&self, next: bool) -> Result<Option<ElementNodeArc>, DomError> {
+        let step = |node: &AnyNodeArc| {
+            if next {
+                node.common.node_graph.next_sibling()
+            } else {
+                node.common.node_graph.previous_sibling()
+            }
+        };
+        let mut sibling = step(&self.node()?);
+        while let Some(candidate) = sibling {
+            if let Ok(element) = ElementNodeArc::try_from(candidate.clone()) {
+                return Ok(Some(element));
+            }
+            sibling = step(&candidate);
+        }
+        Ok(None)
+    }
+
+    pub fn previous_element_sibling(&self) -> Result<Option<ElementNodeArc>, DomError> {
+        self.element_sibling(false)
+    }
+
+    pub fn next_element_sibling(&self) -> Result<Option<ElementNodeArc>, DomError> {
+        self.element_sibling(true)
+    }
+}
+
+/// Implements ChildNodeBehavior
+#[macro_export]
+macro_rules! impl_child_node {
+    ($structname: ty, $($fieldname: ident).+) => {
+        paste::paste! {
+            impl ChildNodeBehavior for $structname {
+                fn before(&self, nodes: Vec<crate::node::NodeOrString>) -> Result<(), DomError> {
+                    self.$($fieldname).+.before(nodes)
+                }
+
+                fn after(&self, nodes: Vec<crate::node::NodeOrString>) -> Result<(), DomError> {
+                    self.$($fieldname).+.after(nodes)
+                }
+
+                fn replace_with(&self, nodes: Vec<crate::node::NodeOrString>) -> Result<(), DomError> {
+                    self.$($fieldname).+.replace_with(nodes)
+                }
+
+                fn remove(&self) -> Result<(), DomError> {
+                    self.$($fieldname).+.remove()
+                }
+
+                fn previous_element_sibling(&self) -> Result<Option<crate::node::concrete::ElementNodeArc>, DomError> {
+                    self.$($fieldname).+.previous_element_sibling()
+                }
+
+                fn next_element_sibling(&self) -> Result<Option<crate::node::concrete::ElementNodeArc>, DomError> {
+                    self.$($fieldname).+.next_element_sibling()
+                }
+            }
+        }
+    };
+}
+
+/// Passes methods through to ChildNodeBehavior, for public use.
+#[macro_export]
+macro_rules! proxy_child_node_behavior {
+    () => {
+        paste::paste! {
+            /// [Element.before](https://developer.mozilla.org/en-US/docs/Web/API/Element/before):
+            /// inserts nodes or text before this node
+            pub fn before(&self, nodes: Vec<crate::node::NodeOrString>) -> Result<(), DomError> {
+                <Self as crate::behavior::ChildNodeBehavior>::before(self, nodes)
+            }
+            /// [Element.after](https://developer.mozilla.org/en-US/docs/Web/API/Element/after):
+            /// inserts nodes or text after this node
+            pub fn after(&self, nodes: Vec<crate::node::NodeOrString>) -> Result<(), DomError> {
+                <Self as crate::behavior::ChildNodeBehavior>::after(self, nodes)
+            }
+            /// [Element.replaceWith](https://developer.mozilla.org/en-US/docs/Web/API/Element/replaceWith):
+            /// replaces this node with nodes or text
+            pub fn replace_with(&self, nodes: Vec<crate::node::NodeOrString>) -> Result<(), DomError> {
+                <Self as crate::behavior::ChildNodeBehavior>::replace_with(self, nodes)
+            }
+            /// [Element.remove](https://developer.mozilla.org/en-US/docs/Web/API/Element/remove):
+            /// removes this node from its parent
+            pub fn remove(&self) -> Result<(), DomError> {
+                <Self as crate::behavior::ChildNodeBehavior>::remove(self)
+            }
+        }
+    };
+}
+
+/// Passes the [NonDocumentTypeChildNode](https://dom.spec.whatwg.org/#interface-nondocumenttypechildnode)
+/// methods of ChildNodeBehavior through, for public use. Document types do not have them.
+#[macro_export]
+macro_rules! proxy_non_document_type_child_node_behavior {
+    () => {
+        paste::paste! {
+            /// [Element.previousElementSibling](https://developer.mozilla.org/en-US/docs/Web/API/Element/previousElementSibling)
+            pub fn previous_element_sibling(&self) -> Result<Option<crate::node::concrete::ElementNodeArc>, DomError> {
+                <Self as crate::behavior::ChildNodeBehavior>::previous_element_sibling(self)
+            }
+            /// [Element.nextElementSibling](https://developer.mozilla.org/en-US/docs/Web/API/Element/nextElementSibling)
+            pub fn next_element_sibling(&self) -> Result<Option<crate::node::concrete::ElementNodeArc>, DomError> {
+                <Self as crate::behavior::ChildNodeBehavior>::next_element_sibling(self)
+            }
+        }
+    };
+}
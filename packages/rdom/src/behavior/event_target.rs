@@ -0,0 +1,191 @@
+#![macro_use]
+
+use crate::event::{AddEventListenerOptions, Event, EventListener};
+use crate::internal_prelude::*;
+use std::sync::RwLock;
+
+/// EventTargetBehavior trait for internal use only.
+pub(crate) trait EventTargetBehavior {
+    fn add_event_listener(
+        &self,
+        event_type: String,
+        listener: EventListener,
+        options: AddEventListenerOptions,
+    );
+    fn remove_event_listener(&self, event_type: String, listener: &EventListener, capture: bool);
+    fn dispatch_event(&self, event: &Event) -> Result<bool, DomError>;
+}
+
+#[derive(Clone)]
+struct ListenerEntry {
+    event_type: String,
+    listener: EventListener,
+    capture: bool,
+    once: bool,
+}
+
+impl ListenerEntry {
+    fn is_same(&self, other: &ListenerEntry) -> bool {
+        self.event_type == other.event_type
+            && self.capture == other.capture
+            && Arc::ptr_eq(&self.listener, &other.listener)
+    }
+}
+
+/// The event listeners of a target. Cloning it gives another handle to the same listeners.
+#[derive(Clone)]
+pub struct EventTargetBehaviorStorage {
+    listeners: Arc<RwLock<Vec<ListenerEntry>>>,
+}
+
+impl EventTargetBehaviorStorage {
+    pub fn new() -> EventTargetBehaviorStorage {
+        EventTargetBehaviorStorage {
+            listeners: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    pub fn add_event_listener(
+        &self,
+        event_type: String,
+        listener: EventListener,
+        options: AddEventListenerOptions,
+    ) {
+        let entry = ListenerEntry {
+            event_type,
+            listener,
+            capture: options.capture,
+            once: options.once,
+        };
+        let mut listeners = self.listeners.write().unwrap();
+        if !listeners.iter().any(|existing| existing.is_same(&entry)) {
+            listeners.push(entry);
+        }
+    }
+
+    pub fn remove_event_listener(
+        &self,
+        event_type: String,
+        listener: &EventListener,
+        capture: bool,
+    ) {
+        self.listeners.write().unwrap().retain(|entry| {
+            !(entry.event_type == event_type
+                && entry.capture == capture
+                && Arc::ptr_eq(&entry.listener, listener))
+        });
+    }
+
+    /// Invokes the listeners registered for the event's type. `capture` selects only capturing
+    /// (`Some(true)`) or only bubbling (`Some(false)`) listeners; `None` selects all of them, as
+    /// is done at the target.
+    pub(crate) fn invoke(&self, event: &Event, capture: Option<bool>) {
+        let event_type = event.event_type();
+        let matching: Vec<ListenerEntry> = self
+            .listeners
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|entry| {
+                entry.event_type == event_type && capture.is_none_or(|c| c == entry.capture)
+            })
+            .cloned()
+            .collect();
+
+        for entry in matching {
+            {
+                let mut listeners = self.listeners.write().unwrap();
+                let position = listeners
+                    .iter()
+                    .position(|existing| existing.is_same(&entry));
+                match position {
+                    // The listener was removed by an earlier listener
+                    None => continue,
+                    Some(position) if entry.once => {
+                        listeners.remove(position);
+                    }
+                    Some(_) => {}
+                }
+            }
+
+            (entry.listener)(event);
+
+            if event.immediate_propagation_stopped() {
+                break;
+            }
+        }
+    }
+}
+
+impl Default for EventTargetBehaviorStorage {
+    fn default() -> EventTargetBehaviorStorage {
+        EventTargetBehaviorStorage::new()
+    }
+}
+
+/// Implements EventTargetBehavior
+#[macro_export]
+macro_rules! impl_event_target {
+    ($structname: ty, $($fieldname: ident).+) => {
+        paste::paste! {
+            impl EventTargetBehavior for $structname {
+                fn add_event_listener(
+                    &self,
+                    event_type: String,
+                    listener: $crate::event::EventListener,
+                    options: $crate::event::AddEventListenerOptions,
+                ) {
+                    self.$($fieldname).+.add_event_listener(event_type, listener, options)
+                }
+
+                fn remove_event_listener(
+                    &self,
+                    event_type: String,
+                    listener: &$crate::event::EventListener,
+                    capture: bool,
+                ) {
+                    self.$($fieldname).+.remove_event_listener(event_type, listener, capture)
+                }
+
+                fn dispatch_event(&self, event: &$crate::event::Event) -> Result<bool, DomError> {
+                    use $crate::event::AsEventTarget;
+                    $crate::event::dispatch(self.as_event_target()?, event)
+                }
+            }
+        }
+    };
+}
+
+/// Passes methods through to EventTargetBehavior, for public use.
+#[macro_export]
+macro_rules! proxy_event_target_behavior {
+    () => {
+        paste::paste! {
+            /// [EventTarget.addEventListener](https://developer.mozilla.org/en-US/docs/Web/API/EventTarget/addEventListener)
+            pub fn add_event_listener(
+                &self,
+                event_type: String,
+                listener: $crate::event::EventListener,
+                options: $crate::event::AddEventListenerOptions,
+            ) {
+                <Self as $crate::behavior::EventTargetBehavior>::add_event_listener(self, event_type, listener, options)
+            }
+
+            /// [EventTarget.removeEventListener](https://developer.mozilla.org/en-US/docs/Web/API/EventTarget/removeEventListener)
+            pub fn remove_event_listener(
+                &self,
+                event_type: String,
+                listener: &$crate::event::EventListener,
+                capture: bool,
+            ) {
+                <Self as $crate::behavior::EventTargetBehavior>::remove_event_listener(self, event_type, listener, capture)
+            }
+
+            /// [EventTarget.dispatchEvent](https://developer.mozilla.org/en-US/docs/Web/API/EventTarget/dispatchEvent).
+            /// Returns false if the event was canceled.
+            pub fn dispatch_event(&self, event: &$crate::event::Event) -> Result<bool, DomError> {
+                <Self as $crate::behavior::EventTargetBehavior>::dispatch_event(self, event)
+            }
+        }
+    };
+}
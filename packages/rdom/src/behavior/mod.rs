@@ -1,3 +1,6 @@
+pub(crate) use self::character_data::CharacterDataBehavior;
+pub(crate) use self::child_node::ChildNodeBehavior;
+pub(crate) use self::event_target::EventTargetBehavior;
 pub(crate) use self::parent_node::ParentNodeBehavior;
 pub(crate) use super::node::NodeBehavior;
 
@@ -31,12 +34,18 @@ macro_rules! use_behaviors {
     }
 }
 
+pub mod character_data;
+pub mod child_node;
 pub mod element;
+pub mod event_target;
 pub mod parent_node;
 pub mod sandbox_member;
 
 generate_preludes! {
-    parent_node ParentNode
+    parent_node ParentNode,
+    event_target EventTarget,
+    character_data CharacterData,
+    child_node ChildNode
 }
 
 pub mod node;
@@ -4,7 +4,7 @@
 //! so it is not defined in NodeBehavior
 
 use crate::internal_prelude::*;
-use crate::node::concrete::ElementNodeArc;
+use crate::node::concrete::{DocumentNodeArc, ElementNodeArc};
 use crate::node::AnyNodeArc;
 use crate::node_list::NodeList;
 use crate::selector::Selector;
@@ -24,8 +24,28 @@ pub(crate) trait NodeBehavior {
     fn clone_node(&self) -> AnyNodeArc;
     /// [Node.getType](https://developer.mozilla.org/en-US/docs/Web/API/Node/nodeType)
     fn node_type(&self) -> isize;
+    /// [Node.ownerDocument](https://developer.mozilla.org/en-US/docs/Web/API/Node/ownerDocument)
+    fn owner_document(&self) -> Option<DocumentNodeArc>;
     /// [.querySelector](https://developer.mozilla.org/en-US/docs/Web/API/Document/querySelector)
     fn query_selector(&self, selector: &Selector) -> Result<Option<ElementNodeArc>, DomError>;
+    /// [Node.normalize](https://developer.mozilla.org/en-US/docs/Web/API/Node/normalize)
+    fn normalize(&self);
+    /// [Node.isSameNode](https://developer.mozilla.org/en-US/docs/Web/API/Node/isSameNode)
+    fn is_same_node(&self, other: &AnyNodeArc) -> bool;
+    /// [Node.isEqualNode](https://developer.mozilla.org/en-US/docs/Web/API/Node/isEqualNode)
+    fn is_equal_node(&self, other: &AnyNodeArc) -> bool;
+    /// [Node.compareDocumentPosition](https://developer.mozilla.org/en-US/docs/Web/API/Node/compareDocumentPosition)
+    fn compare_document_position(&self, other: &AnyNodeArc) -> u16;
+    /// [Node.nodeName](https://developer.mozilla.org/en-US/docs/Web/API/Node/nodeName)
+    fn node_name(&self) -> String;
+    /// [Node.nodeValue](https://developer.mozilla.org/en-US/docs/Web/API/Node/nodeValue)
+    fn node_value(&self) -> Option<String>;
+    /// Sets [Node.nodeValue](https://developer.mozilla.org/en-US/docs/Web/API/Node/nodeValue)
+    fn set_node_value(&self, value: String);
+    /// [Node.textContent](https://developer.mozilla.org/en-US/docs/Web/API/Node/textContent)
+    fn text_content(&self) -> Option<String>;
+    /// Sets [Node.textContent](https://developer.mozilla.org/en-US/docs/Web/API/Node/textContent)
+    fn set_text_content(&self, text: String);
 }
 
 /// Passes methods through to NodeBehavior, for public use.
@@ -57,10 +77,65 @@ macro_rules! proxy_node_behavior {
             pub fn node_type(&self) -> isize {
                 <Self as crate::behavior::NodeBehavior>::node_type(self)
             }
+            /// [Node.ownerDocument](https://developer.mozilla.org/en-US/docs/Web/API/Node/ownerDocument):
+            /// the document the node belongs to, or none for a document
+            pub fn owner_document(&self) -> Option<DocumentNodeArc> {
+                <Self as crate::behavior::NodeBehavior>::owner_document(self)
+            }
             /// [.querySelector](https://developer.mozilla.org/en-US/docs/Web/API/Document/querySelector)
             pub fn query_selector(&self, selector: &Selector) -> Result<Option<ElementNodeArc>, DomError> {
                 <Self as crate::behavior::NodeBehavior>::query_selector(self, selector)
             }
+            /// [Node.normalize](https://developer.mozilla.org/en-US/docs/Web/API/Node/normalize):
+            /// merges adjacent text nodes in the subtree and removes empty ones, updating live
+            /// ranges
+            pub fn normalize(&self) {
+                <Self as crate::behavior::NodeBehavior>::normalize(self)
+            }
+            /// [Node.isSameNode](https://developer.mozilla.org/en-US/docs/Web/API/Node/isSameNode):
+            /// whether `other` is a reference to this node
+            pub fn is_same_node(&self, other: &AnyNodeArc) -> bool {
+                <Self as crate::behavior::NodeBehavior>::is_same_node(self, other)
+            }
+            /// [Node.isEqualNode](https://developer.mozilla.org/en-US/docs/Web/API/Node/isEqualNode):
+            /// whether `other` has the same type, names, attributes, data and children
+            pub fn is_equal_node(&self, other: &AnyNodeArc) -> bool {
+                <Self as crate::behavior::NodeBehavior>::is_equal_node(self, other)
+            }
+            /// [Node.compareDocumentPosition](https://developer.mozilla.org/en-US/docs/Web/API/Node/compareDocumentPosition):
+            /// the position of `other` relative to this node, as a bitmask of the
+            /// `DOCUMENT_POSITION_` constants
+            pub fn compare_document_position(&self, other: &AnyNodeArc) -> u16 {
+                <Self as crate::behavior::NodeBehavior>::compare_document_position(self, other)
+            }
+            /// [Node.nodeName](https://developer.mozilla.org/en-US/docs/Web/API/Node/nodeName):
+            /// the uppercase tag name of an HTML element, the name of an attribute or doctype,
+            /// the target of a processing instruction, or a name like "#text" for other nodes
+            pub fn node_name(&self) -> String {
+                <Self as crate::behavior::NodeBehavior>::node_name(self)
+            }
+            /// [Node.nodeValue](https://developer.mozilla.org/en-US/docs/Web/API/Node/nodeValue):
+            /// the value of an attribute or the data of a text-like node, otherwise none
+            pub fn node_value(&self) -> Option<String> {
+                <Self as crate::behavior::NodeBehavior>::node_value(self)
+            }
+            /// Sets [Node.nodeValue](https://developer.mozilla.org/en-US/docs/Web/API/Node/nodeValue).
+            /// Does nothing for nodes without a value.
+            pub fn set_node_value(&self, value: String) {
+                <Self as crate::behavior::NodeBehavior>::set_node_value(self, value)
+            }
+            /// [Node.textContent](https://developer.mozilla.org/en-US/docs/Web/API/Node/textContent):
+            /// the text of the descendant text nodes of an element or fragment, the value of
+            /// other nodes, or none for documents and doctypes
+            pub fn text_content(&self) -> Option<String> {
+                <Self as crate::behavior::NodeBehavior>::text_content(self)
+            }
+            /// Sets [Node.textContent](https://developer.mozilla.org/en-US/docs/Web/API/Node/textContent):
+            /// replaces the children of an element or fragment with a text node, or sets the
+            /// value of other nodes
+            pub fn set_text_content(&self, text: String) {
+                <Self as crate::behavior::NodeBehavior>::set_text_content(self, text)
+            }
         }
     };
 }
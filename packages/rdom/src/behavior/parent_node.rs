@@ -1,14 +1,24 @@
 #![macro_use]
 
+use crate::behavior::child_node::{convert_nodes, Position};
 use crate::internal_prelude::*;
 use crate::node::concrete::ElementNodeArc;
 use crate::node::contents::NodeType;
-use crate::node::NodeCommon;
+use crate::node::{NodeCommon, NodeOrString};
+use crate::node_list::{NodeList, NodeListStorage, Query};
 use crate::selector::Selector;
 
+use std::convert::TryFrom;
+
 /// ParentNodeBehavior trait for internal use only.
 pub(crate) trait ParentNodeBehavior {
+    fn children(&self) -> Result<Arc<NodeList>, DomError>;
+    fn first_element_child(&self) -> Result<Option<ElementNodeArc>, DomError>;
+    fn last_element_child(&self) -> Result<Option<ElementNodeArc>, DomError>;
     fn child_element_count(&self) -> Result<usize, DomError>;
+    fn prepend(&self, nodes: Vec<NodeOrString>) -> Result<(), DomError>;
+    fn append(&self, nodes: Vec<NodeOrString>) -> Result<(), DomError>;
+    fn replace_children(&self, nodes: Vec<NodeOrString>) -> Result<(), DomError>;
 }
 
 pub struct ParentNodeBehaviorStorage {
@@ -20,6 +30,37 @@ impl ParentNodeBehaviorStorage {
         ParentNodeBehaviorStorage { node }
     }
 
+    fn node(&self) -> Result<AnyNodeArc, DomError> {
+        let node = self.node.upgrade().ok_or(DomError::SandboxDropped)?;
+        node.node_graph.self_arc()
+    }
+
+    pub fn children(&self) -> Result<Arc<NodeList>, DomError> {
+        let node = self.node()?;
+        Ok(NodeList::new(
+            node.get_context(),
+            NodeListStorage::Live(Query::Children { children_of: node }),
+        ))
+    }
+
+    fn child_elements(&self) -> Result<Vec<ElementNodeArc>, DomError> {
+        let node = self.node.upgrade().ok_or(DomError::SandboxDropped)?;
+        Ok(node
+            .node_graph
+            .static_child_nodes()
+            .into_iter()
+            .filter_map(|node| ElementNodeArc::try_from(node).ok())
+            .collect())
+    }
+
+    pub fn first_element_child(&self) -> Result<Option<ElementNodeArc>, DomError> {
+        Ok(self.child_elements()?.into_iter().next())
+    }
+
+    pub fn last_element_child(&self) -> Result<Option<ElementNodeArc>, DomError> {
+        Ok(self.child_elements()?.pop())
+    }
+
     pub fn child_element_count(&self) -> Result<usize, DomError> {
         let node = self.node.upgrade().ok_or(DomError::SandboxDropped)?;
         Ok(node
@@ -30,6 +71,30 @@ impl ParentNodeBehaviorStorage {
             .count())
     }
 
+    pub fn prepend(&self, nodes: Vec<NodeOrString>) -> Result<(), DomError> {
+        let node = self.node()?;
+        let nodes = convert_nodes(&node, &node, nodes, None, Position::After(None))?;
+        let first_child = node.common.node_graph.first_child();
+        node.common
+            .node_graph
+            .splice(None, nodes, first_child.as_ref());
+        Ok(())
+    }
+
+    pub fn append(&self, nodes: Vec<NodeOrString>) -> Result<(), DomError> {
+        let node = self.node()?;
+        let nodes = convert_nodes(&node, &node, nodes, None, Position::Before(None))?;
+        node.common.node_graph.splice(None, nodes, None);
+        Ok(())
+    }
+
+    pub fn replace_children(&self, nodes: Vec<NodeOrString>) -> Result<(), DomError> {
+        let node = self.node()?;
+        let nodes = convert_nodes(&node, &node, nodes, None, Position::All)?;
+        node.common.node_graph.replace_all(nodes);
+        Ok(())
+    }
+
     pub fn query_selector(&self, selector: &Selector) -> Result<Option<ElementNodeArc>, DomError> {
         let node = self.node.upgrade().ok_or(DomError::SandboxDropped)?;
         let node_arc = node.node_graph.self_arc()?;
@@ -54,9 +119,33 @@ macro_rules! impl_parent_node {
     ($structname: ty, $($fieldname: ident).+) => {
         paste::paste! {
             impl ParentNodeBehavior for $structname {
+                fn children(&self) -> Result<Arc<crate::node_list::NodeList>, DomError> {
+                    self.$($fieldname).+.children()
+                }
+
+                fn first_element_child(&self) -> Result<Option<crate::node::concrete::ElementNodeArc>, DomError> {
+                    self.$($fieldname).+.first_element_child()
+                }
+
+                fn last_element_child(&self) -> Result<Option<crate::node::concrete::ElementNodeArc>, DomError> {
+                    self.$($fieldname).+.last_element_child()
+                }
+
                 fn child_element_count(&self) -> Result<usize, DomError> {
                     self.$($fieldname).+.child_element_count()
                 }
+
+                fn prepend(&self, nodes: Vec<crate::node::NodeOrString>) -> Result<(), DomError> {
+                    self.$($fieldname).+.prepend(nodes)
+                }
+
+                fn append(&self, nodes: Vec<crate::node::NodeOrString>) -> Result<(), DomError> {
+                    self.$($fieldname).+.append(nodes)
+                }
+
+                fn replace_children(&self, nodes: Vec<crate::node::NodeOrString>) -> Result<(), DomError> {
+                    self.$($fieldname).+.replace_children(nodes)
+                }
             }
         }
     };
@@ -67,10 +156,38 @@ macro_rules! impl_parent_node {
 macro_rules! proxy_parent_node_behavior {
     () => {
         paste::paste! {
+            /// [Element.children](https://developer.mozilla.org/en-US/docs/Web/API/Element/children):
+            /// a live list of the child elements
+            pub fn children(&self) -> Result<Arc<crate::node_list::NodeList>, DomError> {
+                <Self as crate::behavior::ParentNodeBehavior>::children(self)
+            }
+            /// [Element.firstElementChild](https://developer.mozilla.org/en-US/docs/Web/API/Element/firstElementChild)
+            pub fn first_element_child(&self) -> Result<Option<crate::node::concrete::ElementNodeArc>, DomError> {
+                <Self as crate::behavior::ParentNodeBehavior>::first_element_child(self)
+            }
+            /// [Element.lastElementChild](https://developer.mozilla.org/en-US/docs/Web/API/Element/lastElementChild)
+            pub fn last_element_child(&self) -> Result<Option<crate::node::concrete::ElementNodeArc>, DomError> {
+                <Self as crate::behavior::ParentNodeBehavior>::last_element_child(self)
+            }
             /// Number of child elements
             pub fn child_element_count(&self) -> Result<usize, DomError> {
                 <Self as crate::behavior::ParentNodeBehavior>::child_element_count(self)
             }
+            /// [Element.prepend](https://developer.mozilla.org/en-US/docs/Web/API/Element/prepend):
+            /// inserts nodes or text before the first child
+            pub fn prepend(&self, nodes: Vec<crate::node::NodeOrString>) -> Result<(), DomError> {
+                <Self as crate::behavior::ParentNodeBehavior>::prepend(self, nodes)
+            }
+            /// [Element.append](https://developer.mozilla.org/en-US/docs/Web/API/Element/append):
+            /// inserts nodes or text after the last child
+            pub fn append(&self, nodes: Vec<crate::node::NodeOrString>) -> Result<(), DomError> {
+                <Self as crate::behavior::ParentNodeBehavior>::append(self, nodes)
+            }
+            /// [Element.replaceChildren](https://developer.mozilla.org/en-US/docs/Web/API/Element/replaceChildren):
+            /// replaces the children with nodes or text
+            pub fn replace_children(&self, nodes: Vec<crate::node::NodeOrString>) -> Result<(), DomError> {
+                <Self as crate::behavior::ParentNodeBehavior>::replace_children(self, nodes)
+            }
         }
     };
 }
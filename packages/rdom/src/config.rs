@@ -2,11 +2,31 @@
 //! in order to allow some values (e.g. window.innerWidth), we need a configuration
 //! for the tab, which is what the structures in this module represent.
 
-/// Screen metrics configuration (width and height)
-#[derive(Clone, Debug)]
+use crate::cookie::CookieJar;
+use crate::error::DomError;
+use crate::storage::SharedStorage;
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use url::Url;
+
+/// Screen metrics configuration: the sizes of the viewport, the browser window and the screen,
+/// in CSS pixels
+#[derive(Clone, Debug, PartialEq)]
 pub struct ScreenMetrics {
-    inner_width: u16,
-    inner_height: u16,
+    /// Width of the viewport ([Window.innerWidth](https://developer.mozilla.org/en-US/docs/Web/API/Window/innerWidth))
+    pub inner_width: u16,
+    /// Height of the viewport ([Window.innerHeight](https://developer.mozilla.org/en-US/docs/Web/API/Window/innerHeight))
+    pub inner_height: u16,
+    /// Width of the whole browser window ([Window.outerWidth](https://developer.mozilla.org/en-US/docs/Web/API/Window/outerWidth))
+    pub outer_width: u16,
+    /// Height of the whole browser window, including toolbars ([Window.outerHeight](https://developer.mozilla.org/en-US/docs/Web/API/Window/outerHeight))
+    pub outer_height: u16,
+    /// Width of the screen ([Screen.width](https://developer.mozilla.org/en-US/docs/Web/API/Screen/width))
+    pub screen_width: u16,
+    /// Height of the screen ([Screen.height](https://developer.mozilla.org/en-US/docs/Web/API/Screen/height))
+    pub screen_height: u16,
+    /// Device pixels per CSS pixel ([Window.devicePixelRatio](https://developer.mozilla.org/en-US/docs/Web/API/Window/devicePixelRatio))
+    pub device_pixel_ratio: f64,
 }
 
 impl Default for ScreenMetrics {
@@ -14,6 +34,277 @@ impl Default for ScreenMetrics {
         ScreenMetrics {
             inner_width: 1500,
             inner_height: 1000,
+            outer_width: 1500,
+            outer_height: 1080,
+            screen_width: 1920,
+            screen_height: 1080,
+            device_pixel_ratio: 1.0,
+        }
+    }
+}
+
+/// A color scheme the user can prefer ([prefers-color-scheme](https://developer.mozilla.org/en-US/docs/Web/CSS/@media/prefers-color-scheme))
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ColorScheme {
+    /// Dark text on a light background
+    #[default]
+    Light,
+    /// Light text on a dark background
+    Dark,
+}
+
+/// Preferences of the simulated user, which media queries can test
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct UserPreferences {
+    /// The preferred color scheme
+    pub color_scheme: ColorScheme,
+    /// Whether the user asked for less motion ([prefers-reduced-motion](https://developer.mozilla.org/en-US/docs/Web/CSS/@media/prefers-reduced-motion))
+    pub reduced_motion: bool,
+}
+
+/// The complete configuration of a sandbox: the document's URL and everything the window
+/// exposes about the simulated browser and user. Create one with [SandboxConfig::builder].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SandboxConfig {
+    url: String,
+    referrer: String,
+    user_agent: String,
+    languages: Vec<String>,
+    timezone: String,
+    cookie_enabled: bool,
+    online: bool,
+    forward_console: bool,
+    seed: u64,
+    start_time: SystemTime,
+    local_storage: SharedStorage,
+    cookie_jar: CookieJar,
+    pub(crate) screen_metrics: ScreenMetrics,
+    pub(crate) user_preferences: UserPreferences,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> SandboxConfig {
+        SandboxConfig {
+            url: "about:blank".to_owned(),
+            referrer: "".to_owned(),
+            user_agent: format!(
+                "Mozilla/5.0 (X11; Linux x86_64) rdom/{}",
+                env!("CARGO_PKG_VERSION")
+            ),
+            languages: vec!["en-US".to_owned()],
+            timezone: "UTC".to_owned(),
+            cookie_enabled: true,
+            online: true,
+            forward_console: false,
+            seed: 0,
+            // 2021-01-01T00:00:00Z
+            start_time: UNIX_EPOCH + Duration::from_secs(1_609_459_200),
+            local_storage: Default::default(),
+            cookie_jar: Default::default(),
+            screen_metrics: Default::default(),
+            user_preferences: Default::default(),
+        }
+    }
+}
+
+impl From<ScreenMetrics> for SandboxConfig {
+    fn from(screen_metrics: ScreenMetrics) -> SandboxConfig {
+        SandboxConfig {
+            screen_metrics,
+            ..Default::default()
         }
     }
 }
+
+impl SandboxConfig {
+    /// Starts building a configuration from the defaults
+    pub fn builder() -> SandboxConfigBuilder {
+        SandboxConfigBuilder {
+            config: Default::default(),
+        }
+    }
+
+    /// The URL of the document ([Document.URL](https://developer.mozilla.org/en-US/docs/Web/API/Document/URL))
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    /// The URL of the page which linked to the document ([Document.referrer](https://developer.mozilla.org/en-US/docs/Web/API/Document/referrer))
+    pub fn referrer(&self) -> String {
+        self.referrer.clone()
+    }
+
+    /// [Navigator.userAgent](https://developer.mozilla.org/en-US/docs/Web/API/Navigator/userAgent)
+    pub fn user_agent(&self) -> String {
+        self.user_agent.clone()
+    }
+
+    /// [Navigator.languages](https://developer.mozilla.org/en-US/docs/Web/API/Navigator/languages),
+    /// most preferred first
+    pub fn languages(&self) -> Vec<String> {
+        self.languages.clone()
+    }
+
+    /// The IANA time zone of the simulated user, e.g. "Europe/Berlin"
+    pub fn timezone(&self) -> String {
+        self.timezone.clone()
+    }
+
+    /// [Navigator.cookieEnabled](https://developer.mozilla.org/en-US/docs/Web/API/Navigator/cookieEnabled)
+    pub fn cookie_enabled(&self) -> bool {
+        self.cookie_enabled
+    }
+
+    /// [Navigator.onLine](https://developer.mozilla.org/en-US/docs/Web/API/Navigator/onLine)
+    pub fn online(&self) -> bool {
+        self.online
+    }
+
+    /// Whether console messages are forwarded to the `log` crate
+    pub fn forward_console(&self) -> bool {
+        self.forward_console
+    }
+
+    /// The seed of the sandbox's random number generator
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The wall-clock time at which the sandbox's virtual clock starts
+    pub fn start_time(&self) -> SystemTime {
+        self.start_time
+    }
+
+    /// The cookies of [Document.cookie](https://developer.mozilla.org/en-US/docs/Web/API/Document/cookie)
+    pub fn cookie_jar(&self) -> CookieJar {
+        self.cookie_jar.clone()
+    }
+
+    /// The storage behind [Window.localStorage](https://developer.mozilla.org/en-US/docs/Web/API/Window/localStorage)
+    pub fn local_storage(&self) -> SharedStorage {
+        self.local_storage.clone()
+    }
+
+    /// The sizes of the viewport, window and screen
+    pub fn screen_metrics(&self) -> ScreenMetrics {
+        self.screen_metrics.clone()
+    }
+
+    /// The preferences of the simulated user
+    pub fn user_preferences(&self) -> UserPreferences {
+        self.user_preferences.clone()
+    }
+
+    pub(crate) fn set_online(&mut self, online: bool) {
+        self.online = online;
+    }
+}
+
+/// Builds a [SandboxConfig]. Anything which is not set keeps its default value.
+#[derive(Clone, Debug)]
+pub struct SandboxConfigBuilder {
+    config: SandboxConfig,
+}
+
+impl SandboxConfigBuilder {
+    /// Sets the URL of the document, which must be an absolute URL. Defaults to "about:blank".
+    pub fn url(mut self, url: String) -> SandboxConfigBuilder {
+        self.config.url = url;
+        self
+    }
+
+    /// Sets the referrer of the document. Defaults to none (the empty string).
+    pub fn referrer(mut self, referrer: String) -> SandboxConfigBuilder {
+        self.config.referrer = referrer;
+        self
+    }
+
+    /// Sets the user agent string
+    pub fn user_agent(mut self, user_agent: String) -> SandboxConfigBuilder {
+        self.config.user_agent = user_agent;
+        self
+    }
+
+    /// Sets the preferred languages, most preferred first. Defaults to "en-US".
+    pub fn languages(mut self, languages: Vec<String>) -> SandboxConfigBuilder {
+        self.config.languages = languages;
+        self
+    }
+
+    /// Sets the time zone. Defaults to "UTC".
+    pub fn timezone(mut self, timezone: String) -> SandboxConfigBuilder {
+        self.config.timezone = timezone;
+        self
+    }
+
+    /// Sets the preferred color scheme. Defaults to light.
+    pub fn color_scheme(mut self, color_scheme: ColorScheme) -> SandboxConfigBuilder {
+        self.config.user_preferences.color_scheme = color_scheme;
+        self
+    }
+
+    /// Sets whether the user prefers reduced motion. Defaults to false.
+    pub fn reduced_motion(mut self, reduced_motion: bool) -> SandboxConfigBuilder {
+        self.config.user_preferences.reduced_motion = reduced_motion;
+        self
+    }
+
+    /// Sets whether cookies are enabled. Defaults to true.
+    pub fn cookie_enabled(mut self, cookie_enabled: bool) -> SandboxConfigBuilder {
+        self.config.cookie_enabled = cookie_enabled;
+        self
+    }
+
+    /// Sets whether the browser starts out online. Defaults to true.
+    pub fn online(mut self, online: bool) -> SandboxConfigBuilder {
+        self.config.online = online;
+        self
+    }
+
+    /// Sets whether console messages are forwarded to the `log` crate, with the target
+    /// "rdom::console". They are recorded in the sandbox either way. Defaults to false.
+    pub fn forward_console(mut self, forward_console: bool) -> SandboxConfigBuilder {
+        self.config.forward_console = forward_console;
+        self
+    }
+
+    /// Sets the seed of the random number generator. Defaults to 0.
+    pub fn seed(mut self, seed: u64) -> SandboxConfigBuilder {
+        self.config.seed = seed;
+        self
+    }
+
+    /// Sets the wall-clock time at which the virtual clock starts, which decides e.g. when
+    /// cookies expire. Defaults to 2021-01-01T00:00:00Z, so that runs are reproducible.
+    pub fn start_time(mut self, start_time: SystemTime) -> SandboxConfigBuilder {
+        self.config.start_time = start_time;
+        self
+    }
+
+    /// Sets the jar which holds the document's cookies. Sandboxes configured with the same jar
+    /// share their cookies. Defaults to a new, empty jar.
+    pub fn cookie_jar(mut self, cookie_jar: CookieJar) -> SandboxConfigBuilder {
+        self.config.cookie_jar = cookie_jar;
+        self
+    }
+
+    /// Sets the storage behind `localStorage`. Sandboxes configured with the same storage
+    /// share the items of each origin. Defaults to new, empty in-memory storage.
+    pub fn local_storage(mut self, local_storage: SharedStorage) -> SandboxConfigBuilder {
+        self.config.local_storage = local_storage;
+        self
+    }
+
+    /// Sets the sizes of the viewport, window and screen
+    pub fn screen_metrics(mut self, screen_metrics: ScreenMetrics) -> SandboxConfigBuilder {
+        self.config.screen_metrics = screen_metrics;
+        self
+    }
+
+    /// Finishes building the configuration. Fails with `DomError::Syntax` if the URL cannot be
+    /// parsed.
+    pub fn build(self) -> Result<SandboxConfig, DomError> {
+        Url::parse(&self.config.url).map_err(|_| DomError::Syntax)?;
+        Ok(self.config)
+    }
+}
@@ -0,0 +1,209 @@
+//! The [console](https://developer.mozilla.org/en-US/docs/Web/API/console) of a window. Messages
+//! are recorded as [ConsoleEntry] values in the sandbox (see `Sandbox::console_entries`), and
+//! forwarded to the `log` crate if the sandbox is configured to.
+
+use crate::internal_prelude::*;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// The severity of a console message
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConsoleLevel {
+    /// [console.debug](https://developer.mozilla.org/en-US/docs/Web/API/console/debug)
+    Debug,
+    /// [console.log](https://developer.mozilla.org/en-US/docs/Web/API/console/log), and the
+    /// labels of groups and tables
+    Log,
+    /// [console.info](https://developer.mozilla.org/en-US/docs/Web/API/console/info)
+    Info,
+    /// [console.warn](https://developer.mozilla.org/en-US/docs/Web/API/console/warn)
+    Warn,
+    /// [console.error](https://developer.mozilla.org/en-US/docs/Web/API/console/error)
+    Error,
+}
+
+/// Tabular data, as logged by [console.table](https://developer.mozilla.org/en-US/docs/Web/API/console/table)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConsoleTable {
+    /// The column headings
+    pub columns: Vec<String>,
+    /// The rows, each with a cell for each column
+    pub rows: Vec<Vec<String>>,
+}
+
+/// A message written to the console
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConsoleEntry {
+    /// The severity of the message
+    pub level: ConsoleLevel,
+    /// The message, or the label of a group. Tables are rendered as text.
+    pub message: String,
+    /// The number of groups the message is nested in
+    pub group_depth: usize,
+    /// Whether the entry starts a group, which the following entries are nested in
+    pub group_start: bool,
+    /// The data of a table
+    pub table: Option<ConsoleTable>,
+    /// When the message was written, on the sandbox's virtual clock
+    pub time: Duration,
+}
+
+/// A [console](https://developer.mozilla.org/en-US/docs/Web/API/console) object
+#[sourcegen::sourcegen(generator = "behave", script = "SandboxMember context")]
+// Generated. All manual edits to the block annotated with #[sourcegen...] will be discarded.
+pub struct Console {
+    /// SandboxMember implementation
+    context: Weak<Sandbox>,
+    /// The number of groups which have been started and not ended
+    group_depth: AtomicUsize,
+}
+
+#[sourcegen::generated]
+impl Console {
+    /// gets `Weak<Sandbox>` to the `Sandbox` that it is in
+    pub fn get_context(&self) -> Weak<Sandbox> {
+        self.context.clone()
+    }
+}
+
+#[sourcegen::generated]
+impl SandboxMemberBehavior for Console {
+    fn get_context(&self) -> Weak<Sandbox> {
+        self.get_context()
+    }
+}
+
+impl Console {
+    pub(crate) fn new(context: Weak<Sandbox>) -> Arc<Console> {
+        Arc::new(Console {
+            context,
+            group_depth: AtomicUsize::new(0),
+        })
+    }
+
+    /// [console.log](https://developer.mozilla.org/en-US/docs/Web/API/console/log)
+    pub fn log(&self, message: String) {
+        self.write(ConsoleLevel::Log, message, false, None);
+    }
+
+    /// [console.debug](https://developer.mozilla.org/en-US/docs/Web/API/console/debug)
+    pub fn debug(&self, message: String) {
+        self.write(ConsoleLevel::Debug, message, false, None);
+    }
+
+    /// [console.info](https://developer.mozilla.org/en-US/docs/Web/API/console/info)
+    pub fn info(&self, message: String) {
+        self.write(ConsoleLevel::Info, message, false, None);
+    }
+
+    /// [console.warn](https://developer.mozilla.org/en-US/docs/Web/API/console/warn)
+    pub fn warn(&self, message: String) {
+        self.write(ConsoleLevel::Warn, message, false, None);
+    }
+
+    /// [console.error](https://developer.mozilla.org/en-US/docs/Web/API/console/error)
+    pub fn error(&self, message: String) {
+        self.write(ConsoleLevel::Error, message, false, None);
+    }
+
+    /// [console.group](https://developer.mozilla.org/en-US/docs/Web/API/console/group): writes
+    /// `label`, and nests the following messages in a group until `group_end`
+    pub fn group(&self, label: String) {
+        self.write(ConsoleLevel::Log, label, true, None);
+        self.group_depth.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// [console.groupEnd](https://developer.mozilla.org/en-US/docs/Web/API/console/groupEnd):
+    /// ends the innermost group, if any
+    pub fn group_end(&self) {
+        let _ = self
+            .group_depth
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |depth| {
+                depth.checked_sub(1)
+            });
+    }
+
+    /// [console.table](https://developer.mozilla.org/en-US/docs/Web/API/console/table): writes
+    /// `rows` as a table with the given column headings. Missing cells are left empty.
+    pub fn table(&self, columns: Vec<String>, rows: Vec<Vec<String>>) {
+        let rows: Vec<Vec<String>> = rows
+            .into_iter()
+            .map(|mut row| {
+                row.resize(columns.len(), String::new());
+                row
+            })
+            .collect();
+        let table = ConsoleTable { columns, rows };
+        self.write(ConsoleLevel::Log, render_table(&table), false, Some(table));
+    }
+
+    fn write(
+        &self,
+        level: ConsoleLevel,
+        message: String,
+        group_start: bool,
+        table: Option<ConsoleTable>,
+    ) {
+        let context = match self.context.upgrade() {
+            Some(context) => context,
+            None => return,
+        };
+        let group_depth = self.group_depth.load(Ordering::SeqCst);
+
+        if context.config().forward_console() {
+            let indent = "  ".repeat(group_depth);
+            let log_level = match level {
+                ConsoleLevel::Debug => log::Level::Debug,
+                ConsoleLevel::Log | ConsoleLevel::Info => log::Level::Info,
+                ConsoleLevel::Warn => log::Level::Warn,
+                ConsoleLevel::Error => log::Level::Error,
+            };
+            for line in message.lines() {
+                log::log!(target: "rdom::console", log_level, "{}{}", indent, line);
+            }
+        }
+
+        context.record_console_entry(ConsoleEntry {
+            level,
+            message,
+            group_depth,
+            group_start,
+            table,
+            time: context.now(),
+        });
+    }
+}
+
+/// Renders a table as text, with a line per row and the columns padded to line up
+fn render_table(table: &ConsoleTable) -> String {
+    let widths: Vec<usize> = table
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            table
+                .rows
+                .iter()
+                .map(|row| row[index].chars().count())
+                .chain(std::iter::once(column.chars().count()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let render_row = |cells: &[String]| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join(" | ")
+            .trim_end()
+            .to_owned()
+    };
+
+    std::iter::once(render_row(&table.columns))
+        .chain(table.rows.iter().map(|row| render_row(row)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
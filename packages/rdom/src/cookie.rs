@@ -0,0 +1,422 @@
+//! Cookies, as stored by a [CookieJar] and read and written through
+//! [Document.cookie](https://developer.mozilla.org/en-US/docs/Web/API/Document/cookie).
+//! Parsing and matching follow [RFC 6265](https://datatracker.ietf.org/doc/html/rfc6265#section-5),
+//! with the SameSite attribute of its successor draft. Expiry is measured on the virtual clock of
+//! the sandbox which uses the jar.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use url::{Host, Url};
+
+/// The longest a cookie may last, per [RFC 6265bis](https://httpwg.org/http-extensions/draft-ietf-httpbis-rfc6265bis.html#section-5.6.2)
+const MAX_AGE_LIMIT: Duration = Duration::from_secs(400 * 24 * 60 * 60);
+
+/// The [SameSite](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Set-Cookie#samesitesamesite-value)
+/// attribute of a cookie
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SameSite {
+    /// Only sent with same-site requests
+    Strict,
+    /// Also sent when navigating to the site from another one. Cookies which do not say
+    /// otherwise are treated as lax.
+    #[default]
+    Lax,
+    /// Sent with all requests. Such cookies must be secure.
+    None,
+}
+
+/// A cookie in a [CookieJar]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Cookie {
+    /// The name of the cookie, which may be empty
+    pub name: String,
+    /// The value of the cookie
+    pub value: String,
+    /// The host the cookie was set for, or the domain it was set for with its Domain attribute
+    pub domain: String,
+    /// Whether the cookie is only sent to `domain` itself, and not its subdomains
+    pub host_only: bool,
+    /// Only requests for this path, or paths below it, get the cookie
+    pub path: String,
+    /// When the cookie expires, or none if it lasts for the session
+    pub expires: Option<SystemTime>,
+    /// Whether the cookie is only sent over secure connections
+    pub secure: bool,
+    /// Whether the cookie is hidden from scripts
+    pub http_only: bool,
+    /// When the cookie is sent with cross-site requests
+    pub same_site: SameSite,
+}
+
+impl Cookie {
+    /// Parses a cookie string, as given to `document.cookie` or in a Set-Cookie header, which
+    /// was received from `url` at `now`. Returns none if the cookie must be ignored.
+    fn parse(cookie_string: &str, url: &Url, now: SystemTime) -> Option<Cookie> {
+        let (name_value_pair, attributes) = match cookie_string.find(';') {
+            Some(index) => cookie_string.split_at(index),
+            None => (cookie_string, ""),
+        };
+        let (name, value) = match name_value_pair.find('=') {
+            Some(index) => (&name_value_pair[..index], &name_value_pair[index + 1..]),
+            None => ("", name_value_pair),
+        };
+        let (name, value) = (name.trim(), value.trim());
+        if name.is_empty() && value.is_empty() {
+            return None;
+        }
+
+        let host = url.host_str()?.to_ascii_lowercase();
+        let mut cookie = Cookie {
+            name: name.to_owned(),
+            value: value.to_owned(),
+            domain: host.clone(),
+            host_only: true,
+            path: default_path(url),
+            expires: None,
+            secure: false,
+            http_only: false,
+            same_site: SameSite::default(),
+        };
+        let mut max_age = None;
+        let mut domain = None;
+
+        for attribute in attributes.split(';').skip(1) {
+            let (attribute_name, attribute_value) = match attribute.find('=') {
+                Some(index) => (&attribute[..index], &attribute[index + 1..]),
+                None => (attribute, ""),
+            };
+            let attribute_value = attribute_value.trim();
+            match attribute_name.trim().to_ascii_lowercase().as_ref() {
+                "expires" => {
+                    if let Some(expires) = parse_cookie_date(attribute_value) {
+                        cookie.expires = Some(expires.min(now + MAX_AGE_LIMIT));
+                    }
+                }
+                "max-age" => {
+                    let digits = attribute_value.strip_prefix('-').unwrap_or(attribute_value);
+                    if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+                        // Values too large to parse saturate, keeping their sign
+                        let seconds = attribute_value.parse::<i64>().unwrap_or(
+                            if attribute_value.starts_with('-') {
+                                i64::MIN
+                            } else {
+                                i64::MAX
+                            },
+                        );
+                        max_age = Some(if seconds <= 0 {
+                            UNIX_EPOCH
+                        } else {
+                            now + Duration::from_secs(seconds as u64).min(MAX_AGE_LIMIT)
+                        });
+                    }
+                }
+                "domain" => {
+                    let value = attribute_value.strip_prefix('.').unwrap_or(attribute_value);
+                    if !value.is_empty() {
+                        domain = Some(value.to_ascii_lowercase());
+                    }
+                }
+                "path" => {
+                    cookie.path = if attribute_value.starts_with('/') {
+                        attribute_value.to_owned()
+                    } else {
+                        default_path(url)
+                    };
+                }
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                "samesite" => match attribute_value.to_ascii_lowercase().as_ref() {
+                    "strict" => cookie.same_site = SameSite::Strict,
+                    "lax" => cookie.same_site = SameSite::Lax,
+                    "none" => cookie.same_site = SameSite::None,
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+
+        // Max-Age takes precedence over Expires
+        if max_age.is_some() {
+            cookie.expires = max_age;
+        }
+        if let Some(domain) = domain {
+            if !domain_matches(&host, &domain) {
+                return None;
+            }
+            cookie.domain = domain;
+            cookie.host_only = false;
+        }
+        if cookie.secure && url.scheme() != "https" {
+            return None;
+        }
+        if cookie.same_site == SameSite::None && !cookie.secure {
+            return None;
+        }
+        Some(cookie)
+    }
+
+    fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    /// Whether the cookie would be sent with a request to `url`
+    fn matches(&self, url: &Url) -> bool {
+        let host = match url.host_str() {
+            Some(host) => host.to_ascii_lowercase(),
+            None => return false,
+        };
+        let host_matches = if self.host_only {
+            host == self.domain
+        } else {
+            domain_matches(&host, &self.domain)
+        };
+        host_matches
+            && path_matches(url.path(), &self.path)
+            && (!self.secure || url.scheme() == "https")
+    }
+}
+
+/// Cookies which any number of sandboxes can share. Cloning it gives another handle to the
+/// same jar.
+#[derive(Clone, Default)]
+pub struct CookieJar {
+    /// The cookies in the order they were created
+    cookies: Arc<Mutex<Vec<Cookie>>>,
+}
+
+impl CookieJar {
+    /// Creates an empty jar
+    pub fn new() -> CookieJar {
+        Default::default()
+    }
+
+    /// Gives every cookie in the jar, including expired ones which have not been removed yet,
+    /// oldest first
+    pub fn cookies(&self) -> Vec<Cookie> {
+        self.cookies.lock().unwrap().clone()
+    }
+
+    /// Adds a cookie, replacing any with the same name, domain and path
+    pub fn insert(&self, cookie: Cookie) {
+        let mut cookies = self.cookies.lock().unwrap();
+        match cookies.iter_mut().find(|existing| {
+            existing.name == cookie.name
+                && existing.domain == cookie.domain
+                && existing.path == cookie.path
+        }) {
+            // The replaced cookie keeps its place, as it keeps its creation time
+            Some(existing) => *existing = cookie,
+            None => cookies.push(cookie),
+        }
+    }
+
+    /// Removes every cookie
+    pub fn clear(&self) {
+        self.cookies.lock().unwrap().clear();
+    }
+
+    /// Stores a cookie from a Set-Cookie header of a response from `url`, received at `now`
+    pub fn set_from_header(&self, url: &Url, set_cookie: &str, now: SystemTime) {
+        self.store(url, set_cookie, now, true);
+    }
+
+    /// Gives the value of the Cookie header of a request to `url` made at `now`
+    pub fn header_for(&self, url: &Url, now: SystemTime) -> String {
+        self.cookie_string(url, now, true)
+    }
+
+    /// [Stores](https://datatracker.ietf.org/doc/html/rfc6265#section-5.3) a cookie. Scripts,
+    /// which are not `http`, may neither set nor overwrite HttpOnly cookies.
+    pub(crate) fn store(&self, url: &Url, cookie_string: &str, now: SystemTime, http: bool) {
+        let cookie = match Cookie::parse(cookie_string, url, now) {
+            Some(cookie) => cookie,
+            None => return,
+        };
+
+        let mut cookies = self.cookies.lock().unwrap();
+        let existing = cookies.iter().position(|existing| {
+            existing.name == cookie.name
+                && existing.domain == cookie.domain
+                && existing.path == cookie.path
+        });
+        if !http && (cookie.http_only || existing.is_some_and(|i| cookies[i].http_only)) {
+            return;
+        }
+        match (existing, cookie.is_expired(now)) {
+            (Some(index), true) => {
+                cookies.remove(index);
+            }
+            (Some(index), false) => cookies[index] = cookie,
+            (None, true) => {}
+            (None, false) => cookies.push(cookie),
+        }
+    }
+
+    /// [Gives](https://datatracker.ietf.org/doc/html/rfc6265#section-5.4) the cookies for `url`
+    /// as "name=value" pairs separated by "; ", longest paths first
+    pub(crate) fn cookie_string(&self, url: &Url, now: SystemTime, http: bool) -> String {
+        let mut cookies = self.cookies.lock().unwrap();
+        cookies.retain(|cookie| !cookie.is_expired(now));
+
+        let mut matching: Vec<&Cookie> = cookies
+            .iter()
+            .filter(|cookie| cookie.matches(url) && (http || !cookie.http_only))
+            .collect();
+        // The sort is stable, so cookies with equally long paths stay oldest first
+        matching.sort_by_key(|cookie| std::cmp::Reverse(cookie.path.len()));
+        matching
+            .iter()
+            .map(|cookie| {
+                if cookie.name.is_empty() {
+                    cookie.value.clone()
+                } else {
+                    format!("{}={}", cookie.name, cookie.value)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+impl PartialEq for CookieJar {
+    fn eq(&self, other: &CookieJar) -> bool {
+        Arc::ptr_eq(&self.cookies, &other.cookies)
+    }
+}
+
+impl fmt::Debug for CookieJar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CookieJar")
+            .field("cookies", &self.cookies.lock().unwrap())
+            .finish()
+    }
+}
+
+/// The [default path](https://datatracker.ietf.org/doc/html/rfc6265#section-5.1.4) of cookies
+/// set for `url`: its path up to, but not including, the last "/"
+fn default_path(url: &Url) -> String {
+    let path = url.path();
+    match path.rfind('/') {
+        Some(index) if index > 0 && path.starts_with('/') => path[..index].to_owned(),
+        _ => "/".to_owned(),
+    }
+}
+
+/// Whether `host` [domain-matches](https://datatracker.ietf.org/doc/html/rfc6265#section-5.1.3)
+/// `domain`
+fn domain_matches(host: &str, domain: &str) -> bool {
+    if host == domain {
+        return true;
+    }
+    let is_ip_address = matches!(Host::parse(host), Ok(Host::Ipv4(_)) | Ok(Host::Ipv6(_)));
+    !is_ip_address
+        && host.len() > domain.len()
+        && host.ends_with(domain)
+        && host.as_bytes()[host.len() - domain.len() - 1] == b'.'
+}
+
+/// Whether `request_path` [path-matches](https://datatracker.ietf.org/doc/html/rfc6265#section-5.1.4)
+/// `cookie_path`
+fn path_matches(request_path: &str, cookie_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path.as_bytes()[cookie_path.len()] == b'/'))
+}
+
+/// Parses a date in the [cookie-date](https://datatracker.ietf.org/doc/html/rfc6265#section-5.1.1)
+/// format, which accepts most ways of writing a date, e.g. "Wed, 21 Oct 2015 07:28:00 GMT"
+fn parse_cookie_date(date: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    let is_delimiter = |c: char| matches!(c, '\t' | ' '..='/' | ';'..='@' | '['..='`' | '{'..='~');
+    /// The number made of the digits at the start of `token`, if there are `min` to `max`
+    fn leading_number(token: &str, min: usize, max: usize) -> Option<u32> {
+        let digits = token.bytes().take_while(u8::is_ascii_digit).count();
+        if digits < min || digits > max {
+            return None;
+        }
+        token[..digits].parse().ok()
+    }
+
+    let (mut time, mut day, mut month, mut year) = (None, None, None, None);
+    for token in date.split(is_delimiter).filter(|token| !token.is_empty()) {
+        if time.is_none() {
+            let parts: Vec<&str> = token.splitn(3, ':').collect();
+            if let [hours, minutes, seconds] = parts[..] {
+                let all_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+                if all_digits(hours) && all_digits(minutes) {
+                    let hms = (
+                        leading_number(hours, 1, 2),
+                        leading_number(minutes, 1, 2),
+                        leading_number(seconds, 1, 2),
+                    );
+                    if let (Some(h), Some(m), Some(s)) = hms {
+                        time = Some((h, m, s));
+                        continue;
+                    }
+                }
+            }
+        }
+        if day.is_none() {
+            if let Some(d) = leading_number(token, 1, 2) {
+                day = Some(d);
+                continue;
+            }
+        }
+        if month.is_none() {
+            // The token may start with a multi-byte character, which has no 3-byte prefix
+            if let Some(prefix) = token.get(..3) {
+                let prefix = prefix.to_ascii_lowercase();
+                if let Some(index) = MONTHS.iter().position(|name| *name == prefix) {
+                    month = Some(index as u32 + 1);
+                    continue;
+                }
+            }
+        }
+        if year.is_none() {
+            if let Some(y) = leading_number(token, 2, 4) {
+                year = Some(y);
+                continue;
+            }
+        }
+    }
+
+    let ((hours, minutes, seconds), day, month, mut year) = (time?, day?, month?, year?);
+    if (70..=99).contains(&year) {
+        year += 1900;
+    } else if year <= 69 {
+        year += 2000;
+    }
+    let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    let days_in_month = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+    if day < 1 || day > days_in_month || year < 1601 || hours > 23 || minutes > 59 || seconds > 59 {
+        return None;
+    }
+
+    let seconds = days_from_civil(year.into(), month, day) * 86400
+        + i64::from(hours * 3600 + minutes * 60 + seconds);
+    if seconds >= 0 {
+        UNIX_EPOCH.checked_add(Duration::from_secs(seconds as u64))
+    } else {
+        UNIX_EPOCH.checked_sub(Duration::from_secs(seconds.unsigned_abs()))
+    }
+}
+
+/// The number of days from 1970-01-01 to the given date of the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
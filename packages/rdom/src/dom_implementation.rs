@@ -0,0 +1,134 @@
+//! [DOMImplementation](https://developer.mozilla.org/en-US/docs/Web/API/DOMImplementation),
+//! which creates documents that are not displayed in a window.
+
+use crate::internal_prelude::*;
+use crate::node::concrete::{
+    validate_namespace, validate_qualified_name, DocumentNodeArc, DocumentTypeNodeArc,
+};
+use crate::node::contents::{DocumentStore, DocumentTypeStore};
+use crate::node::element::HTML_NAMESPACE;
+
+use std::sync::RwLock;
+
+/// The [SVG namespace](https://infra.spec.whatwg.org/#svg-namespace)
+const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+
+/// A [DOMImplementation](https://developer.mozilla.org/en-US/docs/Web/API/DOMImplementation),
+/// as given by `Document::implementation`. The documents it creates are in the same sandbox,
+/// but have no window.
+#[sourcegen::sourcegen(generator = "behave", script = "SandboxMember context")]
+// Generated. All manual edits to the block annotated with #[sourcegen...] will be discarded.
+#[derive(Clone)]
+pub struct DomImplementation {
+    /// SandboxMember implementation
+    context: Weak<Sandbox>,
+}
+
+#[sourcegen::generated]
+impl DomImplementation {
+    /// gets `Weak<Sandbox>` to the `Sandbox` that it is in
+    pub fn get_context(&self) -> Weak<Sandbox> {
+        self.context.clone()
+    }
+}
+
+#[sourcegen::generated]
+impl SandboxMemberBehavior for DomImplementation {
+    fn get_context(&self) -> Weak<Sandbox> {
+        self.get_context()
+    }
+}
+
+impl DomImplementation {
+    pub(crate) fn new(context: Weak<Sandbox>) -> DomImplementation {
+        DomImplementation { context }
+    }
+
+    /// Creates an empty document with the given content type, at about:blank
+    fn create_blank_document(&self, content_type: &str) -> DocumentNodeArc {
+        DocumentNodeArc::new(
+            self.context.clone(),
+            Arc::new(DocumentStore {
+                url: Arc::new(RwLock::new("about:blank".to_owned())),
+                content_type: content_type.to_owned(),
+                ..Default::default()
+            }),
+        )
+    }
+
+    /// [DOMImplementation.createDocumentType](https://developer.mozilla.org/en-US/docs/Web/API/DOMImplementation/createDocumentType):
+    /// creates a doctype which can be inserted into a document. An HTML document takes its mode
+    /// from a doctype inserted before its document element.
+    pub fn create_document_type(
+        &self,
+        qualified_name: String,
+        public_id: String,
+        system_id: String,
+    ) -> Result<DocumentTypeNodeArc, DomError> {
+        validate_qualified_name(&qualified_name)?;
+        let context = self.context.upgrade().ok_or(DomError::SandboxDropped)?;
+        Ok(context
+            .builder::<DocumentTypeNodeArc>()
+            .build(DocumentTypeStore::new(qualified_name, public_id, system_id)))
+    }
+
+    /// [DOMImplementation.createHTMLDocument](https://developer.mozilla.org/en-US/docs/Web/API/DOMImplementation/createHTMLDocument):
+    /// creates an HTML document with a doctype, and html, head and body elements. The head has
+    /// a title element if `title` is given.
+    pub fn create_html_document(&self, title: Option<String>) -> Result<DocumentNodeArc, DomError> {
+        self.context.upgrade().ok_or(DomError::SandboxDropped)?;
+        let document = self.create_blank_document("text/html");
+
+        let doctype = self.create_document_type("html".to_owned(), String::new(), String::new())?;
+        doctype.common.set_owner_document(&document);
+        document.append_child(doctype.into());
+
+        let html = document.create_element("html".to_owned())?;
+        document.append_child(html.clone().into());
+        let head = document.create_element("head".to_owned())?;
+        html.append_child(head.clone().into());
+        if let Some(title) = title {
+            let title_element = document.create_element("title".to_owned())?;
+            title_element.append_child(document.create_text_node(title)?.into());
+            head.append_child(title_element.into());
+        }
+        html.append_child(document.create_element("body".to_owned())?.into());
+
+        Ok(document)
+    }
+
+    /// [DOMImplementation.createDocument](https://developer.mozilla.org/en-US/docs/Web/API/DOMImplementation/createDocument):
+    /// creates an XML document, with a document element named `qualified_name` unless it is
+    /// empty. The document element is in `namespace`, which also decides the content type.
+    pub fn create_document(
+        &self,
+        namespace: Option<String>,
+        qualified_name: String,
+    ) -> Result<DocumentNodeArc, DomError> {
+        self.context.upgrade().ok_or(DomError::SandboxDropped)?;
+        let namespace = if qualified_name.is_empty() {
+            namespace.filter(|namespace| !namespace.is_empty())
+        } else {
+            validate_namespace(namespace, &qualified_name)?
+        };
+        let content_type = match namespace.as_deref() {
+            Some(HTML_NAMESPACE) => "application/xhtml+xml",
+            Some(SVG_NAMESPACE) => "image/svg+xml",
+            _ => "application/xml",
+        };
+        let document = self.create_blank_document(content_type);
+
+        if !qualified_name.is_empty() {
+            let element = document.create_element_ns(namespace, qualified_name)?;
+            document.append_child(element.into());
+        }
+
+        Ok(document)
+    }
+
+    /// [DOMImplementation.hasFeature](https://developer.mozilla.org/en-US/docs/Web/API/DOMImplementation/hasFeature):
+    /// always true, as in browsers
+    pub fn has_feature(&self) -> bool {
+        true
+    }
+}
@@ -34,4 +34,50 @@ pub enum DomError {
     /// for another element.
     #[error("attribute is already in use in another element")]
     NotFound,
+
+    /// An operation was attempted on an object which does not support it in its current state,
+    /// e.g. reading the value of an element which is not a form control.
+    #[error("the object is in an invalid state")]
+    InvalidState,
+
+    /// An argument had the right type but an invalid value, e.g. mutation observer options
+    /// which do not observe anything.
+    #[error("type error (invalid argument)")]
+    Type,
+
+    /// A string did not match the expected pattern, e.g. a URL which could not be parsed.
+    #[error("syntax error")]
+    Syntax,
+
+    /// An operation was not allowed, e.g. changing the document's URL to another origin
+    /// through the history.
+    #[error("security error")]
+    Security,
+
+    /// An operation would have exceeded a storage quota.
+    #[error("quota exceeded")]
+    QuotaExceeded,
+
+    /// An operation would have made an invalid node tree, e.g. setting the body of a document
+    /// to an element which is not a body.
+    #[error("hierarchy request error")]
+    HierarchyRequest,
+
+    /// A node was of a type which the operation does not accept, e.g. a doctype as the
+    /// boundary of a range.
+    #[error("invalid node type")]
+    InvalidNodeType,
+
+    /// A string contained characters which are not allowed, e.g. a name with spaces.
+    #[error("invalid character")]
+    InvalidCharacter,
+
+    /// A name was not valid for its namespace, e.g. a prefixed attribute name without a
+    /// namespace.
+    #[error("namespace error")]
+    Namespace,
+
+    /// An operation is not supported for the object, e.g. importing a document into another.
+    #[error("operation not supported")]
+    NotSupported,
 }
@@ -0,0 +1,517 @@
+//! Representation of an [Event](https://developer.mozilla.org/en-US/docs/Web/API/Event) and
+//! the algorithm which dispatches it through the node tree.
+
+use crate::behavior::event_target_prelude::EventTargetBehaviorStorage;
+use crate::internal_prelude::*;
+use crate::media_query::MediaQueryList;
+use crate::node::activation;
+use crate::node::concrete::{ConcreteNodeArc, DocumentNodeArc};
+use crate::node::AnyNodeStore;
+use crate::storage::Storage;
+use crate::window::Window;
+
+use std::convert::TryInto;
+use std::fmt;
+use std::sync::RwLock;
+
+/// A callback which is invoked when an event is dispatched to a target it was added to.
+pub type EventListener = Arc<dyn Fn(&Event) + Send + Sync>;
+
+/// Options given to [EventTarget.addEventListener](https://developer.mozilla.org/en-US/docs/Web/API/EventTarget/addEventListener)
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AddEventListenerOptions {
+    /// Whether the listener is invoked during the capturing phase instead of the bubbling phase
+    pub capture: bool,
+    /// Whether the listener is removed after it is invoked once
+    pub once: bool,
+}
+
+/// [Event.eventPhase](https://developer.mozilla.org/en-US/docs/Web/API/Event/eventPhase)
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EventPhase {
+    /// The event is not being dispatched
+    None,
+    /// The event is travelling from the window down towards the target
+    Capturing,
+    /// The event is being handled by the target itself
+    AtTarget,
+    /// The event is travelling from the target back up towards the window
+    Bubbling,
+}
+
+/// Something which an event can be dispatched to
+#[derive(Clone)]
+pub enum EventTargetArc {
+    /// A node in a document tree
+    Node(AnyNodeArc),
+    /// A window
+    Window(Arc<Window>),
+    /// A media query list
+    MediaQueryList(Arc<MediaQueryList>),
+}
+
+impl fmt::Debug for EventTargetArc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventTargetArc::Node(node) => f.debug_tuple("Node").field(node).finish(),
+            EventTargetArc::Window(window) => f
+                .debug_tuple("Window")
+                .field(&format!("{:p}", Arc::as_ptr(window)))
+                .finish(),
+            EventTargetArc::MediaQueryList(list) => f
+                .debug_tuple("MediaQueryList")
+                .field(&list.media())
+                .finish(),
+        }
+    }
+}
+
+impl EventTargetArc {
+    /// Gives the node, if this target is a node
+    pub fn node(&self) -> Option<AnyNodeArc> {
+        match self {
+            EventTargetArc::Node(node) => Some(node.clone()),
+            EventTargetArc::Window(_) | EventTargetArc::MediaQueryList(_) => None,
+        }
+    }
+
+    /// Checks whether two targets are the same object
+    pub fn ptr_eq(&self, other: &EventTargetArc) -> bool {
+        match (self, other) {
+            (EventTargetArc::Node(a), EventTargetArc::Node(b)) => Arc::ptr_eq(&a.common, &b.common),
+            (EventTargetArc::Window(a), EventTargetArc::Window(b)) => Arc::ptr_eq(a, b),
+            (EventTargetArc::MediaQueryList(a), EventTargetArc::MediaQueryList(b)) => {
+                Arc::ptr_eq(a, b)
+            }
+            _ => false,
+        }
+    }
+
+    fn listeners(&self) -> &EventTargetBehaviorStorage {
+        match self {
+            EventTargetArc::Node(node) => &node.common.event_target_behavior,
+            EventTargetArc::Window(window) => &window.event_target_behavior,
+            EventTargetArc::MediaQueryList(list) => &list.event_target_behavior,
+        }
+    }
+
+    /// The next target in the event path, per the "get the parent" algorithm
+    fn parent(&self) -> Option<EventTargetArc> {
+        match self {
+            EventTargetArc::Node(node) => match node.common.node_graph.parent_node() {
+                Some(parent) => Some(EventTargetArc::Node(parent)),
+                None => {
+                    let document: DocumentNodeArc = node.clone().try_into().ok()?;
+                    let window = document.contents.default_view.upgrade()?;
+                    Some(EventTargetArc::Window(window))
+                }
+            },
+            EventTargetArc::Window(_) | EventTargetArc::MediaQueryList(_) => None,
+        }
+    }
+}
+
+/// Conversion of a reference to an event target into an owned target which can be put into an
+/// event path.
+pub(crate) trait AsEventTarget {
+    fn as_event_target(&self) -> Result<EventTargetArc, DomError>;
+}
+
+impl AsEventTarget for AnyNodeArc {
+    fn as_event_target(&self) -> Result<EventTargetArc, DomError> {
+        Ok(EventTargetArc::Node(self.clone()))
+    }
+}
+
+impl<S: AnyNodeStore + Clone> AsEventTarget for ConcreteNodeArc<S>
+where
+    ConcreteNodeArc<S>: Into<AnyNodeArc>,
+{
+    fn as_event_target(&self) -> Result<EventTargetArc, DomError> {
+        Ok(EventTargetArc::Node(self.clone().into()))
+    }
+}
+
+impl AsEventTarget for Window {
+    fn as_event_target(&self) -> Result<EventTargetArc, DomError> {
+        Ok(EventTargetArc::Window(self.self_arc()?))
+    }
+}
+
+impl AsEventTarget for MediaQueryList {
+    fn as_event_target(&self) -> Result<EventTargetArc, DomError> {
+        Ok(EventTargetArc::MediaQueryList(self.self_arc()?))
+    }
+}
+
+/// State of the modifier keys while an event is fired
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ModifierState {
+    /// Whether the Alt (Option) key was held
+    pub alt_key: bool,
+    /// Whether the Control key was held
+    pub ctrl_key: bool,
+    /// Whether the Meta (Command) key was held
+    pub meta_key: bool,
+    /// Whether the Shift key was held
+    pub shift_key: bool,
+}
+
+/// Data carried by a [MouseEvent](https://developer.mozilla.org/en-US/docs/Web/API/MouseEvent)
+#[derive(Clone, Debug, Default)]
+pub struct MouseEventData {
+    /// The button which changed state (0 is the main button)
+    pub button: i16,
+    /// Bitmask of the buttons held while the event was fired
+    pub buttons: u16,
+    /// Horizontal coordinate within the viewport
+    pub client_x: f64,
+    /// Vertical coordinate within the viewport
+    pub client_y: f64,
+    /// The click count, for click-like events
+    pub detail: i32,
+    /// Modifier keys held while the event was fired
+    pub modifiers: ModifierState,
+}
+
+/// Data carried by a [PointerEvent](https://developer.mozilla.org/en-US/docs/Web/API/PointerEvent)
+#[derive(Clone, Debug, Default)]
+pub struct PointerEventData {
+    /// The mouse-compatible part of the event
+    pub mouse: MouseEventData,
+    /// Identifier of the pointer causing the event
+    pub pointer_id: i32,
+    /// Kind of device, e.g. "mouse", "pen" or "touch"
+    pub pointer_type: String,
+    /// Whether this is the primary pointer of its type
+    pub is_primary: bool,
+}
+
+/// Data carried by a [KeyboardEvent](https://developer.mozilla.org/en-US/docs/Web/API/KeyboardEvent)
+#[derive(Clone, Debug, Default)]
+pub struct KeyboardEventData {
+    /// The key value, e.g. "a" or "Enter"
+    pub key: String,
+    /// The physical key code, e.g. "KeyA" or "Enter"
+    pub code: String,
+    /// Whether the key is being held down such that it is automatically repeating
+    pub repeat: bool,
+    /// Modifier keys held while the event was fired
+    pub modifiers: ModifierState,
+}
+
+/// Data carried by an [InputEvent](https://developer.mozilla.org/en-US/docs/Web/API/InputEvent)
+#[derive(Clone, Debug, Default)]
+pub struct InputEventData {
+    /// The type of change, e.g. "insertText" or "deleteContentBackward"
+    pub input_type: String,
+    /// The inserted characters, if any
+    pub data: Option<String>,
+}
+
+/// Data carried by a [FocusEvent](https://developer.mozilla.org/en-US/docs/Web/API/FocusEvent)
+#[derive(Clone, Debug, Default)]
+pub struct FocusEventData {
+    /// The element losing focus for a focus event, or gaining focus for a blur event
+    pub related_target: Option<AnyNodeArc>,
+}
+
+/// Data carried by a [SubmitEvent](https://developer.mozilla.org/en-US/docs/Web/API/SubmitEvent)
+#[derive(Clone, Debug, Default)]
+pub struct SubmitEventData {
+    /// The button which submitted the form, if any
+    pub submitter: Option<AnyNodeArc>,
+}
+
+/// Data carried by a [MediaQueryListEvent](https://developer.mozilla.org/en-US/docs/Web/API/MediaQueryListEvent)
+#[derive(Clone, Debug, Default)]
+pub struct MediaQueryListEventData {
+    /// The media query which changed
+    pub media: String,
+    /// Whether the query matches now
+    pub matches: bool,
+}
+
+/// Data carried by a [PopStateEvent](https://developer.mozilla.org/en-US/docs/Web/API/PopStateEvent)
+#[derive(Clone, Debug, Default)]
+pub struct PopStateEventData {
+    /// The state of the history entry which became current
+    pub state: Option<String>,
+}
+
+/// Data carried by a [HashChangeEvent](https://developer.mozilla.org/en-US/docs/Web/API/HashChangeEvent)
+#[derive(Clone, Debug, Default)]
+pub struct HashChangeEventData {
+    /// The URL before the fragment changed
+    pub old_url: String,
+    /// The URL after the fragment changed
+    pub new_url: String,
+}
+
+/// Data carried by a [StorageEvent](https://developer.mozilla.org/en-US/docs/Web/API/StorageEvent)
+#[derive(Clone, Debug, Default)]
+pub struct StorageEventData {
+    /// The key of the changed item, or none if the storage was cleared
+    pub key: Option<String>,
+    /// The item's value before the change
+    pub old_value: Option<String>,
+    /// The item's value after the change
+    pub new_value: Option<String>,
+    /// The URL of the document which made the change
+    pub url: String,
+    /// The storage object of the window receiving the event
+    pub storage_area: Option<Arc<Storage>>,
+}
+
+/// The interface-specific data of an event
+#[derive(Clone, Debug, Default)]
+pub enum EventKind {
+    /// A plain [Event](https://developer.mozilla.org/en-US/docs/Web/API/Event)
+    #[default]
+    Basic,
+    /// A [MouseEvent](https://developer.mozilla.org/en-US/docs/Web/API/MouseEvent)
+    Mouse(MouseEventData),
+    /// A [PointerEvent](https://developer.mozilla.org/en-US/docs/Web/API/PointerEvent)
+    Pointer(PointerEventData),
+    /// A [KeyboardEvent](https://developer.mozilla.org/en-US/docs/Web/API/KeyboardEvent)
+    Keyboard(KeyboardEventData),
+    /// An [InputEvent](https://developer.mozilla.org/en-US/docs/Web/API/InputEvent)
+    Input(InputEventData),
+    /// A [FocusEvent](https://developer.mozilla.org/en-US/docs/Web/API/FocusEvent)
+    Focus(FocusEventData),
+    /// A [SubmitEvent](https://developer.mozilla.org/en-US/docs/Web/API/SubmitEvent)
+    Submit(SubmitEventData),
+    /// A [MediaQueryListEvent](https://developer.mozilla.org/en-US/docs/Web/API/MediaQueryListEvent)
+    MediaQueryList(MediaQueryListEventData),
+    /// A [PopStateEvent](https://developer.mozilla.org/en-US/docs/Web/API/PopStateEvent)
+    PopState(PopStateEventData),
+    /// A [HashChangeEvent](https://developer.mozilla.org/en-US/docs/Web/API/HashChangeEvent)
+    HashChange(HashChangeEventData),
+    /// A [StorageEvent](https://developer.mozilla.org/en-US/docs/Web/API/StorageEvent)
+    Storage(StorageEventData),
+}
+
+/// Initialization values for an event
+#[derive(Clone, Debug, Default)]
+pub struct EventInit {
+    /// Whether the event bubbles up through the tree
+    pub bubbles: bool,
+    /// Whether the event can be canceled
+    pub cancelable: bool,
+    /// Interface-specific data
+    pub kind: EventKind,
+}
+
+struct EventState {
+    target: Option<EventTargetArc>,
+    current_target: Option<EventTargetArc>,
+    phase: EventPhase,
+    default_prevented: bool,
+    propagation_stopped: bool,
+    immediate_propagation_stopped: bool,
+}
+
+/// An [Event](https://developer.mozilla.org/en-US/docs/Web/API/Event)
+pub struct Event {
+    event_type: String,
+    bubbles: bool,
+    cancelable: bool,
+    kind: EventKind,
+    /// Whether the event has a type, which events created by `Document::create_event` only
+    /// get from `init_event`
+    initialized: bool,
+    state: RwLock<EventState>,
+}
+
+impl fmt::Debug for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Event")
+            .field("event_type", &self.event_type)
+            .field("bubbles", &self.bubbles)
+            .field("cancelable", &self.cancelable)
+            .field("kind", &self.kind)
+            .finish()
+    }
+}
+
+impl Event {
+    /// Creates a new event of the given type
+    pub fn new(event_type: String, init: EventInit) -> Event {
+        Event {
+            event_type,
+            bubbles: init.bubbles,
+            cancelable: init.cancelable,
+            kind: init.kind,
+            initialized: true,
+            state: RwLock::new(EventState {
+                target: None,
+                current_target: None,
+                phase: EventPhase::None,
+                default_prevented: false,
+                propagation_stopped: false,
+                immediate_propagation_stopped: false,
+            }),
+        }
+    }
+
+    /// Creates an event which must be initialized with `init_event` before it is dispatched
+    pub(crate) fn uninitialized(kind: EventKind) -> Event {
+        Event {
+            initialized: false,
+            ..Event::new(
+                String::new(),
+                EventInit {
+                    kind,
+                    ..Default::default()
+                },
+            )
+        }
+    }
+
+    /// [Event.initEvent](https://developer.mozilla.org/en-US/docs/Web/API/Event/initEvent): sets
+    /// the type and flags of an event, e.g. one created by `Document::create_event`
+    pub fn init_event(&mut self, event_type: String, bubbles: bool, cancelable: bool) {
+        self.event_type = event_type;
+        self.bubbles = bubbles;
+        self.cancelable = cancelable;
+        self.initialized = true;
+        let state = self.state.get_mut().unwrap();
+        state.target = None;
+        state.default_prevented = false;
+        state.propagation_stopped = false;
+        state.immediate_propagation_stopped = false;
+    }
+
+    /// [Event.type](https://developer.mozilla.org/en-US/docs/Web/API/Event/type)
+    pub fn event_type(&self) -> String {
+        self.event_type.clone()
+    }
+
+    /// [Event.bubbles](https://developer.mozilla.org/en-US/docs/Web/API/Event/bubbles)
+    pub fn bubbles(&self) -> bool {
+        self.bubbles
+    }
+
+    /// [Event.cancelable](https://developer.mozilla.org/en-US/docs/Web/API/Event/cancelable)
+    pub fn cancelable(&self) -> bool {
+        self.cancelable
+    }
+
+    /// The interface-specific data of the event
+    pub fn kind(&self) -> &EventKind {
+        &self.kind
+    }
+
+    /// [Event.target](https://developer.mozilla.org/en-US/docs/Web/API/Event/target)
+    pub fn target(&self) -> Option<EventTargetArc> {
+        self.state.read().unwrap().target.clone()
+    }
+
+    /// [Event.currentTarget](https://developer.mozilla.org/en-US/docs/Web/API/Event/currentTarget)
+    pub fn current_target(&self) -> Option<EventTargetArc> {
+        self.state.read().unwrap().current_target.clone()
+    }
+
+    /// [Event.eventPhase](https://developer.mozilla.org/en-US/docs/Web/API/Event/eventPhase)
+    pub fn event_phase(&self) -> EventPhase {
+        self.state.read().unwrap().phase
+    }
+
+    /// [Event.defaultPrevented](https://developer.mozilla.org/en-US/docs/Web/API/Event/defaultPrevented)
+    pub fn default_prevented(&self) -> bool {
+        self.state.read().unwrap().default_prevented
+    }
+
+    /// [Event.preventDefault](https://developer.mozilla.org/en-US/docs/Web/API/Event/preventDefault)
+    pub fn prevent_default(&self) {
+        if self.cancelable {
+            self.state.write().unwrap().default_prevented = true;
+        }
+    }
+
+    /// [Event.stopPropagation](https://developer.mozilla.org/en-US/docs/Web/API/Event/stopPropagation)
+    pub fn stop_propagation(&self) {
+        self.state.write().unwrap().propagation_stopped = true;
+    }
+
+    /// [Event.stopImmediatePropagation](https://developer.mozilla.org/en-US/docs/Web/API/Event/stopImmediatePropagation)
+    pub fn stop_immediate_propagation(&self) {
+        let mut state = self.state.write().unwrap();
+        state.propagation_stopped = true;
+        state.immediate_propagation_stopped = true;
+    }
+
+    fn propagation_stopped(&self) -> bool {
+        self.state.read().unwrap().propagation_stopped
+    }
+
+    pub(crate) fn immediate_propagation_stopped(&self) -> bool {
+        self.state.read().unwrap().immediate_propagation_stopped
+    }
+
+    fn set_current(&self, current_target: Option<EventTargetArc>, phase: EventPhase) {
+        let mut state = self.state.write().unwrap();
+        state.current_target = current_target;
+        state.phase = phase;
+    }
+}
+
+/// Dispatches `event` to `target`, running the capturing, at-target and bubbling phases and then
+/// any activation behavior. Returns false if the event was canceled, true otherwise.
+pub(crate) fn dispatch(target: EventTargetArc, event: &Event) -> Result<bool, DomError> {
+    if !event.initialized {
+        return Err(DomError::InvalidState);
+    }
+    {
+        let mut state = event.state.write().unwrap();
+        state.target = Some(target.clone());
+        state.propagation_stopped = false;
+        state.immediate_propagation_stopped = false;
+    }
+
+    let mut path = vec![target.clone()];
+    while let Some(parent) = path.last().unwrap().parent() {
+        path.push(parent);
+    }
+
+    let activation_target = activation::activation_target(event, &path);
+    let saved_activation_state = match &activation_target {
+        Some(element) => Some(activation::legacy_pre_activation(element)?),
+        None => None,
+    };
+
+    for current in path.iter().skip(1).rev() {
+        if event.propagation_stopped() {
+            break;
+        }
+        event.set_current(Some(current.clone()), EventPhase::Capturing);
+        current.listeners().invoke(event, Some(true));
+    }
+
+    if !event.propagation_stopped() {
+        event.set_current(Some(target.clone()), EventPhase::AtTarget);
+        target.listeners().invoke(event, None);
+    }
+
+    if event.bubbles {
+        for current in path.iter().skip(1) {
+            if event.propagation_stopped() {
+                break;
+            }
+            event.set_current(Some(current.clone()), EventPhase::Bubbling);
+            current.listeners().invoke(event, Some(false));
+        }
+    }
+
+    event.set_current(None, EventPhase::None);
+
+    if let (Some(element), Some(saved)) = (activation_target, saved_activation_state) {
+        if event.default_prevented() {
+            activation::legacy_canceled_activation(&element, saved)?;
+        } else {
+            activation::activation_behavior(&element, event, saved)?;
+        }
+    }
+
+    Ok(!event.default_prevented())
+}
@@ -0,0 +1,123 @@
+//! A deterministic model of the [event loop](https://html.spec.whatwg.org/multipage/webappapis.html#event-loops):
+//! a task queue ordered by a virtual clock, and a microtask queue which is drained after every
+//! task. Time only passes when the sandbox is told to advance it, so tests which depend on
+//! timing run reproducibly and without real sleeps.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// A callback run by the event loop
+pub type Task = Box<dyn FnOnce() + Send>;
+
+/// Identifies a scheduled task, so that it can be canceled before it runs
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct TaskHandle(u64);
+
+struct ScheduledTask {
+    /// Virtual time at which the task becomes runnable
+    run_at: Duration,
+    /// Tasks which become runnable at the same time run in the order they were scheduled
+    handle: TaskHandle,
+    callback: Task,
+}
+
+/// The queues and clock of a sandbox's event loop
+#[derive(Default)]
+pub(crate) struct EventLoop {
+    now: Mutex<Duration>,
+    tasks: Mutex<Vec<ScheduledTask>>,
+    microtasks: Mutex<VecDeque<Task>>,
+    next_handle: AtomicU64,
+    performing_microtask_checkpoint: AtomicBool,
+}
+
+impl EventLoop {
+    /// The virtual time elapsed since the sandbox was created
+    pub(crate) fn now(&self) -> Duration {
+        *self.now.lock().unwrap()
+    }
+
+    /// Schedules `callback` to run as a task once `delay` has elapsed
+    pub(crate) fn schedule_task(&self, delay: Duration, callback: Task) -> TaskHandle {
+        let handle = TaskHandle(self.next_handle.fetch_add(1, Ordering::SeqCst));
+        let run_at = self.now() + delay;
+        self.tasks.lock().unwrap().push(ScheduledTask {
+            run_at,
+            handle,
+            callback,
+        });
+        handle
+    }
+
+    /// Removes a task which has not run yet. Returns whether it was found.
+    pub(crate) fn cancel_task(&self, handle: TaskHandle) -> bool {
+        let mut tasks = self.tasks.lock().unwrap();
+        let len_before = tasks.len();
+        tasks.retain(|task| task.handle != handle);
+        tasks.len() != len_before
+    }
+
+    pub(crate) fn queue_microtask(&self, callback: Task) {
+        self.microtasks.lock().unwrap().push_back(callback);
+    }
+
+    /// Runs microtasks until the queue is empty, including those queued while it runs. A
+    /// checkpoint requested from within a microtask does nothing.
+    pub(crate) fn perform_microtask_checkpoint(&self) {
+        if self
+            .performing_microtask_checkpoint
+            .swap(true, Ordering::SeqCst)
+        {
+            return;
+        }
+        // Clears the flag even if a microtask panics, so that later checkpoints still run
+        struct CheckpointGuard<'a>(&'a AtomicBool);
+        impl Drop for CheckpointGuard<'_> {
+            fn drop(&mut self) {
+                self.0.store(false, Ordering::SeqCst);
+            }
+        }
+        let _guard = CheckpointGuard(&self.performing_microtask_checkpoint);
+
+        loop {
+            // The lock must be released before running the microtask, which may queue more
+            let microtask = self.microtasks.lock().unwrap().pop_front();
+            match microtask {
+                Some(microtask) => microtask(),
+                None => break,
+            }
+        }
+    }
+
+    /// Removes the earliest task which is runnable at `deadline`, if any
+    fn take_next_task(&self, deadline: Duration) -> Option<ScheduledTask> {
+        let mut tasks = self.tasks.lock().unwrap();
+        let index = tasks
+            .iter()
+            .enumerate()
+            .filter(|(_, task)| task.run_at <= deadline)
+            .min_by_key(|(_, task)| (task.run_at, task.handle.0))
+            .map(|(index, _)| index)?;
+        Some(tasks.remove(index))
+    }
+
+    /// Runs every task which is runnable before `deadline`, in order, moving the clock forward
+    /// to each task's time and performing a microtask checkpoint after each one. The clock ends
+    /// at `deadline`.
+    pub(crate) fn run_until(&self, deadline: Duration) {
+        self.perform_microtask_checkpoint();
+        while let Some(task) = self.take_next_task(deadline) {
+            {
+                let mut now = self.now.lock().unwrap();
+                *now = (*now).max(task.run_at);
+            }
+            (task.callback)();
+            self.perform_microtask_checkpoint();
+        }
+
+        let mut now = self.now.lock().unwrap();
+        *now = (*now).max(deadline);
+    }
+}
@@ -0,0 +1,233 @@
+//! A [fetch](https://developer.mozilla.org/en-US/docs/Web/API/Fetch_API)-like API. A sandbox has
+//! no network: requests go to the [RequestHandler] set with `Sandbox::set_request_handler`,
+//! which can act as an in-process mock server. Responses are delivered in a task, like a
+//! browser's fetch resolves once the response arrives.
+
+use crate::internal_prelude::*;
+
+use url::Url;
+
+/// Answers the requests made by a sandbox
+pub trait RequestHandler: Send + Sync {
+    /// Gives the response to `request`. Return [Response::error] to fail the request as a
+    /// network error would.
+    fn handle(&self, request: &Request) -> Response;
+}
+
+impl<F> RequestHandler for F
+where
+    F: Fn(&Request) -> Response + Send + Sync,
+{
+    fn handle(&self, request: &Request) -> Response {
+        self(request)
+    }
+}
+
+/// Gives the value of the first header named `name`, ignoring case
+fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_ref())
+}
+
+/// Options of a request, as given to `fetch`
+#[derive(Clone, Debug, Default)]
+pub struct RequestInit {
+    /// The HTTP method. Defaults to "GET".
+    pub method: Option<String>,
+    /// Headers to send, as (name, value) pairs
+    pub headers: Vec<(String, String)>,
+    /// The request body
+    pub body: Option<String>,
+}
+
+/// A [Request](https://developer.mozilla.org/en-US/docs/Web/API/Request), as received by a
+/// [RequestHandler]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Request {
+    /// The HTTP method, in upper case for the standard methods
+    pub method: String,
+    /// The URL, resolved against the document's URL
+    pub url: String,
+    /// The headers, as (name, value) pairs. Same-origin requests include the document's
+    /// cookies in a "cookie" header.
+    pub headers: Vec<(String, String)>,
+    /// The request body
+    pub body: Option<String>,
+}
+
+impl Request {
+    /// Gives the value of the header named `name`, ignoring case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+}
+
+/// A [Response](https://developer.mozilla.org/en-US/docs/Web/API/Response), as created by a
+/// [RequestHandler] and delivered by `fetch`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Response {
+    status: u16,
+    status_text: String,
+    headers: Vec<(String, String)>,
+    body: String,
+    url: String,
+    /// Whether this is a [network error](https://fetch.spec.whatwg.org/#concept-network-error)
+    error: bool,
+}
+
+impl Response {
+    /// Creates a response with the given status and body
+    pub fn new(status: u16, body: impl Into<String>) -> Response {
+        Response {
+            status,
+            status_text: String::new(),
+            headers: Vec::new(),
+            body: body.into(),
+            url: String::new(),
+            error: false,
+        }
+    }
+
+    /// [Response.error](https://developer.mozilla.org/en-US/docs/Web/API/Response/error_static):
+    /// a network error, which makes `fetch` fail with `DomError::Type`
+    pub fn error() -> Response {
+        Response {
+            error: true,
+            ..Response::new(0, "")
+        }
+    }
+
+    /// Adds a header
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Response {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sets the status message, e.g. "Not Found"
+    pub fn with_status_text(mut self, status_text: impl Into<String>) -> Response {
+        self.status_text = status_text.into();
+        self
+    }
+
+    /// [Response.status](https://developer.mozilla.org/en-US/docs/Web/API/Response/status)
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// [Response.ok](https://developer.mozilla.org/en-US/docs/Web/API/Response/ok): whether the
+    /// status is in the range 200-299
+    pub fn ok(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// [Response.statusText](https://developer.mozilla.org/en-US/docs/Web/API/Response/statusText)
+    pub fn status_text(&self) -> String {
+        self.status_text.clone()
+    }
+
+    /// [Response.headers](https://developer.mozilla.org/en-US/docs/Web/API/Response/headers),
+    /// as (name, value) pairs
+    pub fn headers(&self) -> Vec<(String, String)> {
+        self.headers.clone()
+    }
+
+    /// Gives the value of the header named `name`, ignoring case
+    pub fn header(&self, name: &str) -> Option<String> {
+        find_header(&self.headers, name).map(String::from)
+    }
+
+    /// [Response.url](https://developer.mozilla.org/en-US/docs/Web/API/Response/url): the URL
+    /// which was requested
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    /// [Response.text](https://developer.mozilla.org/en-US/docs/Web/API/Response/text)
+    pub fn text(&self) -> String {
+        self.body.clone()
+    }
+
+    /// [Response.json](https://developer.mozilla.org/en-US/docs/Web/API/Response/json): the body
+    /// parsed as JSON. Fails with `DomError::Syntax` if it is not JSON.
+    pub fn json(&self) -> Result<serde_json::Value, DomError> {
+        serde_json::from_str(&self.body).map_err(|_| DomError::Syntax)
+    }
+}
+
+/// Runs a fetch in a task: asks the sandbox's request handler for the response,
+/// stores the cookies it sets, and calls `callback` with it
+pub(crate) fn fetch(
+    context: &Arc<Sandbox>,
+    input: String,
+    init: RequestInit,
+    callback: Box<dyn FnOnce(Result<Response, DomError>) + Send>,
+) {
+    let context_weak = Arc::downgrade(context);
+    context.queue_task(move || {
+        let context = match context_weak.upgrade() {
+            Some(context) => context,
+            None => return,
+        };
+        callback(fetch_now(&context, input, init));
+    });
+}
+
+fn fetch_now(
+    context: &Arc<Sandbox>,
+    input: String,
+    init: RequestInit,
+) -> Result<Response, DomError> {
+    let document_url = Url::parse(&context.window().document().url()).ok();
+    let url = Url::options()
+        .base_url(document_url.as_ref())
+        .parse(&input)
+        .map_err(|_| DomError::Type)?;
+    // The standard methods are normalized to upper case, others are sent as they are
+    let mut method = init.method.unwrap_or_else(|| "GET".to_owned());
+    let upper_case = method.to_ascii_uppercase();
+    if ["DELETE", "GET", "HEAD", "OPTIONS", "POST", "PUT"].contains(&upper_case.as_ref()) {
+        method = upper_case;
+    }
+    if init.body.is_some() && (method == "GET" || method == "HEAD") {
+        return Err(DomError::Type);
+    }
+
+    // Cookies are sent with, and stored from, requests to the document's origin
+    let config = context.config();
+    let same_origin = document_url.is_some_and(|document_url| {
+        document_url.origin().is_tuple() && document_url.origin() == url.origin()
+    });
+    let mut headers = init.headers;
+    if same_origin && config.cookie_enabled() {
+        let cookies = config.cookie_jar().header_for(&url, context.current_time());
+        if !cookies.is_empty() && find_header(&headers, "cookie").is_none() {
+            headers.push(("cookie".to_owned(), cookies));
+        }
+    }
+
+    let request = Request {
+        method,
+        url: url.to_string(),
+        headers,
+        body: init.body,
+    };
+    let handler = context.request_handler().ok_or(DomError::Type)?;
+    let mut response = handler.handle(&request);
+    if response.error {
+        return Err(DomError::Type);
+    }
+
+    if same_origin && config.cookie_enabled() {
+        for (name, value) in &response.headers {
+            if name.eq_ignore_ascii_case("set-cookie") {
+                config
+                    .cookie_jar()
+                    .set_from_header(&url, value, context.current_time());
+            }
+        }
+    }
+    response.url = request.url;
+    Ok(response)
+}
@@ -0,0 +1,313 @@
+//! The [History](https://developer.mozilla.org/en-US/docs/Web/API/History) of a window: its
+//! session history entries, and navigation within the document. A sandbox never leaves its
+//! document, so navigations to other documents are only recorded (see
+//! `Sandbox::navigation_requests`), while fragment navigations and history traversal update the
+//! document's URL and fire popstate and hashchange events.
+
+use crate::event::{Event, EventInit, EventKind, HashChangeEventData, PopStateEventData};
+use crate::internal_prelude::*;
+use crate::navigation::{NavigationRequest, NavigationSource};
+use crate::window::Window;
+
+use std::sync::RwLock;
+use url::Url;
+
+/// A [session history entry](https://html.spec.whatwg.org/multipage/browsing-the-web.html#session-history-entry)
+#[derive(Clone, Debug)]
+struct HistoryEntry {
+    url: String,
+    /// The serialized state given to `push_state` or `replace_state`
+    state: Option<String>,
+}
+
+struct SessionHistory {
+    entries: Vec<HistoryEntry>,
+    /// Index of the current entry
+    index: usize,
+}
+
+/// A [History](https://developer.mozilla.org/en-US/docs/Web/API/History) object
+#[sourcegen::sourcegen(generator = "behave", script = "SandboxMember context")]
+// Generated. All manual edits to the block annotated with #[sourcegen...] will be discarded.
+pub struct History {
+    /// SandboxMember implementation
+    context: Weak<Sandbox>,
+    /// The window whose history this is
+    window: Weak<Window>,
+    session_history: RwLock<SessionHistory>,
+    self_weak: Weak<History>,
+}
+
+#[sourcegen::generated]
+impl History {
+    /// gets `Weak<Sandbox>` to the `Sandbox` that it is in
+    pub fn get_context(&self) -> Weak<Sandbox> {
+        self.context.clone()
+    }
+}
+
+#[sourcegen::generated]
+impl SandboxMemberBehavior for History {
+    fn get_context(&self) -> Weak<Sandbox> {
+        self.get_context()
+    }
+}
+
+impl History {
+    pub(crate) fn new(context: Weak<Sandbox>, window: Weak<Window>, url: String) -> Arc<History> {
+        Arc::new_cyclic(|self_weak| History {
+            context,
+            window,
+            session_history: RwLock::new(SessionHistory {
+                entries: vec![HistoryEntry { url, state: None }],
+                index: 0,
+            }),
+            self_weak: self_weak.clone(),
+        })
+    }
+
+    /// [History.length](https://developer.mozilla.org/en-US/docs/Web/API/History/length)
+    pub fn length(&self) -> usize {
+        self.session_history.read().unwrap().entries.len()
+    }
+
+    /// [History.state](https://developer.mozilla.org/en-US/docs/Web/API/History/state)
+    pub fn state(&self) -> Option<String> {
+        let session_history = self.session_history.read().unwrap();
+        session_history.entries[session_history.index].state.clone()
+    }
+
+    /// [History.pushState](https://developer.mozilla.org/en-US/docs/Web/API/History/pushState):
+    /// adds an entry after the current one, discarding any entries after it, and changes the
+    /// document's URL to `url` (if given) without navigating. Fails with `DomError::Security`
+    /// if the document's URL cannot be rewritten to `url`.
+    pub fn push_state(&self, state: Option<String>, url: Option<String>) -> Result<(), DomError> {
+        let url = self.rewritable_url(url)?;
+        self.push_entry(HistoryEntry { url, state })
+    }
+
+    /// [History.replaceState](https://developer.mozilla.org/en-US/docs/Web/API/History/replaceState):
+    /// like `push_state`, but replaces the current entry
+    pub fn replace_state(
+        &self,
+        state: Option<String>,
+        url: Option<String>,
+    ) -> Result<(), DomError> {
+        let url = self.rewritable_url(url)?;
+        self.replace_entry(HistoryEntry { url, state })
+    }
+
+    /// [History.back](https://developer.mozilla.org/en-US/docs/Web/API/History/back)
+    pub fn back(&self) -> Result<(), DomError> {
+        self.go(-1)
+    }
+
+    /// [History.forward](https://developer.mozilla.org/en-US/docs/Web/API/History/forward)
+    pub fn forward(&self) -> Result<(), DomError> {
+        self.go(1)
+    }
+
+    /// [History.go](https://developer.mozilla.org/en-US/docs/Web/API/History/go). Traversal
+    /// happens in a task, so the URL changes and popstate fires once the sandbox's event loop
+    /// runs. Going nowhere (`delta` 0) reloads the document.
+    pub fn go(&self, delta: i32) -> Result<(), DomError> {
+        let context = self.context.upgrade().ok_or(DomError::SandboxDropped)?;
+        if delta == 0 {
+            context.request_navigation(NavigationRequest {
+                url: self.document_url()?.to_string(),
+                method: "GET".to_owned(),
+                body: None,
+                target: None,
+                source: NavigationSource::Script,
+            });
+            return Ok(());
+        }
+
+        let history = self.self_weak.clone();
+        context.queue_task(move || {
+            if let Some(history) = history.upgrade() {
+                let _ = history.traverse(delta);
+            }
+        });
+        Ok(())
+    }
+
+    /// Moves `delta` entries through the session history, if there is an entry there
+    fn traverse(&self, delta: i32) -> Result<(), DomError> {
+        let (old_entry, new_entry) = {
+            let mut session_history = self.session_history.write().unwrap();
+            let target = session_history.index as i64 + i64::from(delta);
+            if target < 0 || target >= session_history.entries.len() as i64 {
+                return Ok(());
+            }
+            let old_entry = session_history.entries[session_history.index].clone();
+            session_history.index = target as usize;
+            (old_entry, session_history.entries[target as usize].clone())
+        };
+
+        self.set_document_url(&new_entry.url)?;
+        self.fire_popstate(new_entry.state)?;
+        self.fire_hashchange_if_needed(&old_entry.url, &new_entry.url)
+    }
+
+    /// [Navigates](https://html.spec.whatwg.org/multipage/browsing-the-web.html#navigate) to
+    /// `url`. Navigating to a fragment of the current document happens within the sandbox; any
+    /// other navigation is recorded.
+    pub(crate) fn navigate(
+        &self,
+        url: Url,
+        replace: bool,
+        source: NavigationSource,
+    ) -> Result<(), DomError> {
+        if !self.navigate_to_fragment(&url, replace)? {
+            let context = self.context.upgrade().ok_or(DomError::SandboxDropped)?;
+            context.request_navigation(NavigationRequest {
+                url: url.to_string(),
+                method: "GET".to_owned(),
+                body: None,
+                target: None,
+                source,
+            });
+        }
+        Ok(())
+    }
+
+    /// Performs a [fragment navigation](https://html.spec.whatwg.org/multipage/browsing-the-web.html#navigate-fragid)
+    /// if `url` only differs from the document's URL in its fragment, and `url` has one.
+    /// Returns whether it did.
+    pub(crate) fn navigate_to_fragment(&self, url: &Url, replace: bool) -> Result<bool, DomError> {
+        let current = self.document_url()?;
+        if url.fragment().is_none() || !equal_excluding_fragments(url, &current) {
+            return Ok(false);
+        }
+
+        let entry = HistoryEntry {
+            url: url.to_string(),
+            state: None,
+        };
+        if replace {
+            self.replace_entry(entry)?;
+        } else {
+            self.push_entry(entry)?;
+        }
+        self.fire_popstate(None)?;
+
+        if current.fragment() != url.fragment() {
+            let context = self.context.upgrade().ok_or(DomError::SandboxDropped)?;
+            let history = self.self_weak.clone();
+            let (old_url, new_url) = (current.to_string(), url.to_string());
+            context.queue_task(move || {
+                if let Some(history) = history.upgrade() {
+                    let _ = history.fire_hashchange_if_needed(&old_url, &new_url);
+                }
+            });
+        }
+        Ok(true)
+    }
+
+    fn push_entry(&self, entry: HistoryEntry) -> Result<(), DomError> {
+        let url = entry.url.clone();
+        {
+            let mut session_history = self.session_history.write().unwrap();
+            let index = session_history.index;
+            session_history.entries.truncate(index + 1);
+            session_history.entries.push(entry);
+            session_history.index = index + 1;
+        }
+        self.set_document_url(&url)
+    }
+
+    fn replace_entry(&self, entry: HistoryEntry) -> Result<(), DomError> {
+        let url = entry.url.clone();
+        {
+            let mut session_history = self.session_history.write().unwrap();
+            let index = session_history.index;
+            session_history.entries[index] = entry;
+        }
+        self.set_document_url(&url)
+    }
+
+    /// Resolves the URL given to `push_state` or `replace_state`, checking that the document's
+    /// URL [can be rewritten](https://html.spec.whatwg.org/multipage/nav-history-apis.html#can-have-its-url-rewritten)
+    /// to it
+    fn rewritable_url(&self, url: Option<String>) -> Result<String, DomError> {
+        let current = self.document_url()?;
+        let url = match url {
+            Some(url) => current.join(&url).map_err(|_| DomError::Security)?,
+            None => return Ok(current.to_string()),
+        };
+
+        let same_authority = url.scheme() == current.scheme()
+            && url.username() == current.username()
+            && url.password() == current.password()
+            && url.host_str() == current.host_str()
+            && url.port() == current.port();
+        let rewritable = same_authority
+            && match url.scheme() {
+                "http" | "https" => true,
+                "file" => url.query() == current.query(),
+                _ => url.path() == current.path() && url.query() == current.query(),
+            };
+        if rewritable {
+            Ok(url.to_string())
+        } else {
+            Err(DomError::Security)
+        }
+    }
+
+    fn window(&self) -> Result<Arc<Window>, DomError> {
+        self.window.upgrade().ok_or(DomError::SandboxDropped)
+    }
+
+    pub(crate) fn document_url(&self) -> Result<Url, DomError> {
+        let url = self.window()?.document().url();
+        Url::parse(&url).map_err(|_| DomError::Syntax)
+    }
+
+    fn set_document_url(&self, url: &str) -> Result<(), DomError> {
+        *self.window()?.document().contents.url.write().unwrap() = url.to_owned();
+        Ok(())
+    }
+
+    fn fire_popstate(&self, state: Option<String>) -> Result<(), DomError> {
+        let event = Event::new(
+            "popstate".to_owned(),
+            EventInit {
+                kind: EventKind::PopState(PopStateEventData { state }),
+                ..Default::default()
+            },
+        );
+        self.window()?.dispatch_event(&event).map(|_| ())
+    }
+
+    fn fire_hashchange_if_needed(&self, old_url: &str, new_url: &str) -> Result<(), DomError> {
+        let fragment = |url: &str| {
+            Url::parse(url)
+                .ok()
+                .and_then(|url| url.fragment().map(String::from))
+        };
+        if fragment(old_url) == fragment(new_url) {
+            return Ok(());
+        }
+
+        let event = Event::new(
+            "hashchange".to_owned(),
+            EventInit {
+                kind: EventKind::HashChange(HashChangeEventData {
+                    old_url: old_url.to_owned(),
+                    new_url: new_url.to_owned(),
+                }),
+                ..Default::default()
+            },
+        );
+        self.window()?.dispatch_event(&event).map(|_| ())
+    }
+}
+
+fn equal_excluding_fragments(a: &Url, b: &Url) -> bool {
+    let mut a = a.clone();
+    let mut b = b.clone();
+    a.set_fragment(None);
+    b.set_fragment(None);
+    a == b
+}
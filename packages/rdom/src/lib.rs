@@ -14,13 +14,28 @@
 
 pub(crate) mod behavior;
 pub mod config;
+pub mod console;
+pub mod cookie;
+pub mod dom_implementation;
 pub mod error;
+pub mod event;
+pub mod event_loop;
+pub mod fetch;
+pub mod history;
 pub(crate) mod internal_prelude;
+pub mod location;
+pub mod media_query;
+pub mod mutation_observer;
 pub mod named_node_map;
+pub mod navigation;
 
 pub mod node;
 pub mod node_list;
+pub mod range;
 pub mod sandbox;
 mod selector;
+pub mod storage;
 pub mod tests;
+pub mod tree_walker;
+pub mod user_event;
 pub mod window;
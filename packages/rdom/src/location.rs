@@ -0,0 +1,179 @@
+//! The [Location](https://developer.mozilla.org/en-US/docs/Web/API/Location) of a window's
+//! document, as parsed URL components. Changing it navigates through the window's `History`.
+
+use crate::history::History;
+use crate::internal_prelude::*;
+use crate::navigation::{NavigationRequest, NavigationSource};
+
+use url::{Position, Url};
+
+/// A [Location](https://developer.mozilla.org/en-US/docs/Web/API/Location) object
+#[sourcegen::sourcegen(generator = "behave", script = "SandboxMember context")]
+// Generated. All manual edits to the block annotated with #[sourcegen...] will be discarded.
+pub struct Location {
+    /// SandboxMember implementation
+    context: Weak<Sandbox>,
+    /// The history through which the location navigates
+    history: Weak<History>,
+}
+
+#[sourcegen::generated]
+impl Location {
+    /// gets `Weak<Sandbox>` to the `Sandbox` that it is in
+    pub fn get_context(&self) -> Weak<Sandbox> {
+        self.context.clone()
+    }
+}
+
+#[sourcegen::generated]
+impl SandboxMemberBehavior for Location {
+    fn get_context(&self) -> Weak<Sandbox> {
+        self.get_context()
+    }
+}
+
+impl Location {
+    pub(crate) fn new(context: Weak<Sandbox>, history: Weak<History>) -> Arc<Location> {
+        Arc::new(Location { context, history })
+    }
+
+    fn history(&self) -> Result<Arc<History>, DomError> {
+        self.history.upgrade().ok_or(DomError::SandboxDropped)
+    }
+
+    /// The document's URL, or about:blank if it cannot be parsed
+    fn url(&self) -> Url {
+        self.history()
+            .and_then(|history| history.document_url())
+            .unwrap_or_else(|_| Url::parse("about:blank").unwrap())
+    }
+
+    /// [Location.href](https://developer.mozilla.org/en-US/docs/Web/API/Location/href)
+    pub fn href(&self) -> String {
+        self.url().to_string()
+    }
+
+    /// [Location.protocol](https://developer.mozilla.org/en-US/docs/Web/API/Location/protocol),
+    /// e.g. "https:"
+    pub fn protocol(&self) -> String {
+        format!("{}:", self.url().scheme())
+    }
+
+    /// [Location.host](https://developer.mozilla.org/en-US/docs/Web/API/Location/host): the
+    /// hostname, followed by the port if it is not the scheme's default
+    pub fn host(&self) -> String {
+        let url = self.url();
+        url[Position::BeforeHost..Position::AfterPort].to_owned()
+    }
+
+    /// [Location.hostname](https://developer.mozilla.org/en-US/docs/Web/API/Location/hostname)
+    pub fn hostname(&self) -> String {
+        self.url().host_str().unwrap_or_default().to_owned()
+    }
+
+    /// [Location.port](https://developer.mozilla.org/en-US/docs/Web/API/Location/port), or ""
+    /// if it is the scheme's default
+    pub fn port(&self) -> String {
+        self.url()
+            .port()
+            .map(|port| port.to_string())
+            .unwrap_or_default()
+    }
+
+    /// [Location.pathname](https://developer.mozilla.org/en-US/docs/Web/API/Location/pathname)
+    pub fn pathname(&self) -> String {
+        self.url().path().to_owned()
+    }
+
+    /// [Location.search](https://developer.mozilla.org/en-US/docs/Web/API/Location/search):
+    /// the query with a leading "?", or "" if it is empty
+    pub fn search(&self) -> String {
+        match self.url().query() {
+            Some(query) if !query.is_empty() => format!("?{}", query),
+            _ => String::new(),
+        }
+    }
+
+    /// [Location.hash](https://developer.mozilla.org/en-US/docs/Web/API/Location/hash): the
+    /// fragment with a leading "#", or "" if it is empty
+    pub fn hash(&self) -> String {
+        match self.url().fragment() {
+            Some(fragment) if !fragment.is_empty() => format!("#{}", fragment),
+            _ => String::new(),
+        }
+    }
+
+    /// [Location.origin](https://developer.mozilla.org/en-US/docs/Web/API/Location/origin)
+    pub fn origin(&self) -> String {
+        self.url().origin().ascii_serialization()
+    }
+
+    /// [Location.assign](https://developer.mozilla.org/en-US/docs/Web/API/Location/assign):
+    /// navigates to `url`, resolved against the document's URL
+    pub fn assign(&self, url: String) -> Result<(), DomError> {
+        let url = self.url().join(&url).map_err(|_| DomError::Syntax)?;
+        self.history()?
+            .navigate(url, false, NavigationSource::Script)
+    }
+
+    /// [Location.replace](https://developer.mozilla.org/en-US/docs/Web/API/Location/replace):
+    /// like `assign`, but replaces the current history entry
+    pub fn replace(&self, url: String) -> Result<(), DomError> {
+        let url = self.url().join(&url).map_err(|_| DomError::Syntax)?;
+        self.history()?
+            .navigate(url, true, NavigationSource::Script)
+    }
+
+    /// [Location.reload](https://developer.mozilla.org/en-US/docs/Web/API/Location/reload)
+    pub fn reload(&self) -> Result<(), DomError> {
+        let context = self.context.upgrade().ok_or(DomError::SandboxDropped)?;
+        context.request_navigation(NavigationRequest {
+            url: self.href(),
+            method: "GET".to_owned(),
+            body: None,
+            target: None,
+            source: NavigationSource::Script,
+        });
+        Ok(())
+    }
+
+    /// Sets [Location.href](https://developer.mozilla.org/en-US/docs/Web/API/Location/href),
+    /// which is the same as `assign`
+    pub fn set_href(&self, href: String) -> Result<(), DomError> {
+        self.assign(href)
+    }
+
+    /// Sets [Location.pathname](https://developer.mozilla.org/en-US/docs/Web/API/Location/pathname)
+    pub fn set_pathname(&self, pathname: String) -> Result<(), DomError> {
+        let mut url = self.url();
+        if url.cannot_be_a_base() {
+            return Ok(());
+        }
+        url.set_path(&pathname);
+        self.history()?
+            .navigate(url, false, NavigationSource::Script)
+    }
+
+    /// Sets [Location.search](https://developer.mozilla.org/en-US/docs/Web/API/Location/search)
+    pub fn set_search(&self, search: String) -> Result<(), DomError> {
+        let mut url = self.url();
+        let search = search.strip_prefix('?').unwrap_or(&search);
+        url.set_query(if search.is_empty() {
+            None
+        } else {
+            Some(search)
+        });
+        self.history()?
+            .navigate(url, false, NavigationSource::Script)
+    }
+
+    /// Sets [Location.hash](https://developer.mozilla.org/en-US/docs/Web/API/Location/hash),
+    /// navigating to the fragment within the document
+    pub fn set_hash(&self, hash: String) -> Result<(), DomError> {
+        let mut url = self.url();
+        let hash = hash.strip_prefix('#').unwrap_or(&hash);
+        url.set_fragment(Some(hash));
+        self.history()?
+            .navigate(url, false, NavigationSource::Script)
+    }
+}
@@ -0,0 +1,661 @@
+//! [Media queries](https://drafts.csswg.org/mediaqueries-4/): a parser for media query lists,
+//! their evaluation against the sandbox's screen metrics and user preferences, and the
+//! [MediaQueryList](https://developer.mozilla.org/en-US/docs/Web/API/MediaQueryList) returned by
+//! `Window::match_media`.
+
+use crate::config::{ColorScheme, ScreenMetrics, UserPreferences};
+use crate::internal_prelude::*;
+use crate::window::Window;
+use crate::{impl_event_target, proxy_event_target_behavior};
+
+use std::sync::RwLock;
+
+crate::use_behaviors!(event_target);
+
+/// Font size used to resolve `em` and `rem` lengths
+const DEFAULT_FONT_SIZE: f64 = 16.0;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    /// A number with its (lowercase, possibly empty) unit
+    Number(f64, String),
+    Colon,
+    Comma,
+    Slash,
+    LeftParen,
+    RightParen,
+    Comparison(Comparison),
+    Other(char),
+}
+
+/// How a feature's actual value must compare to the value given in the query
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Comparison {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+}
+
+impl Comparison {
+    /// The comparison with its operands swapped, e.g. `100px < width` is `width > 100px`
+    fn flip(self) -> Comparison {
+        match self {
+            Comparison::Lt => Comparison::Gt,
+            Comparison::Le => Comparison::Ge,
+            Comparison::Gt => Comparison::Lt,
+            Comparison::Ge => Comparison::Le,
+            Comparison::Eq => Comparison::Eq,
+        }
+    }
+
+    fn holds(self, actual: f64, expected: f64) -> bool {
+        match self {
+            Comparison::Lt => actual < expected,
+            Comparison::Le => actual <= expected,
+            Comparison::Gt => actual > expected,
+            Comparison::Ge => actual >= expected,
+            Comparison::Eq => (actual - expected).abs() < f64::EPSILON,
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit()
+            || (c == '.' && next.is_some_and(|n| n.is_ascii_digit()))
+            || (c == '-' && next.is_some_and(|n| n.is_ascii_digit() || n == '.'))
+        {
+            let start = i;
+            i += 1;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let number: String = chars[start..i].iter().collect();
+            let unit_start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphabetic() || chars[i] == '%') {
+                i += 1;
+            }
+            let unit: String = chars[unit_start..i].iter().collect();
+            match number.parse::<f64>() {
+                Ok(number) => tokens.push(Token::Number(number, unit.to_ascii_lowercase())),
+                Err(_) => tokens.push(Token::Other(c)),
+            }
+        } else if c.is_ascii_alphabetic() || c == '-' || c == '_' {
+            let start = i;
+            while i < chars.len() && is_name_char(chars[i]) {
+                i += 1;
+            }
+            let ident: String = chars[start..i].iter().collect();
+            tokens.push(Token::Ident(ident.to_ascii_lowercase()));
+        } else {
+            i += 1;
+            let token = match (c, next) {
+                ('<', Some('=')) | ('>', Some('=')) => {
+                    i += 1;
+                    Token::Comparison(if c == '<' {
+                        Comparison::Le
+                    } else {
+                        Comparison::Ge
+                    })
+                }
+                ('<', _) => Token::Comparison(Comparison::Lt),
+                ('>', _) => Token::Comparison(Comparison::Gt),
+                ('=', _) => Token::Comparison(Comparison::Eq),
+                (':', _) => Token::Colon,
+                (',', _) => Token::Comma,
+                ('/', _) => Token::Slash,
+                ('(', _) => Token::LeftParen,
+                (')', _) => Token::RightParen,
+                _ => Token::Other(c),
+            };
+            tokens.push(token);
+        }
+    }
+    tokens
+}
+
+/// A value given for a media feature
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Number(f64),
+    /// A length in CSS pixels
+    Length(f64),
+    /// A resolution in dots per CSS pixel
+    Resolution(f64),
+    Ratio(f64),
+    Ident(String),
+}
+
+impl Value {
+    fn parse(tokens: &[Token]) -> Option<Value> {
+        match tokens {
+            [Token::Number(number, unit)] => match unit.as_str() {
+                "" => Some(Value::Number(*number)),
+                "px" => Some(Value::Length(*number)),
+                "em" | "rem" => Some(Value::Length(number * DEFAULT_FONT_SIZE)),
+                "dppx" | "x" => Some(Value::Resolution(*number)),
+                "dpi" => Some(Value::Resolution(number / 96.0)),
+                "dpcm" => Some(Value::Resolution(number * 2.54 / 96.0)),
+                _ => None,
+            },
+            [Token::Number(numerator, unit), Token::Slash, Token::Number(denominator, unit2)]
+                if unit.is_empty() && unit2.is_empty() && *denominator != 0.0 =>
+            {
+                Some(Value::Ratio(numerator / denominator))
+            }
+            [Token::Ident(ident)] => Some(Value::Ident(ident.clone())),
+            _ => None,
+        }
+    }
+
+    fn as_length(&self) -> Option<f64> {
+        match self {
+            Value::Length(length) => Some(*length),
+            Value::Number(number) if *number == 0.0 => Some(0.0),
+            _ => None,
+        }
+    }
+
+    fn as_ratio(&self) -> Option<f64> {
+        match self {
+            Value::Ratio(ratio) | Value::Number(ratio) => Some(*ratio),
+            _ => None,
+        }
+    }
+
+    fn as_resolution(&self) -> Option<f64> {
+        match self {
+            Value::Resolution(resolution) => Some(*resolution),
+            _ => None,
+        }
+    }
+}
+
+/// A test of a media feature, e.g. `(min-width: 600px)` or `(400px <= width < 800px)`. A
+/// feature in a boolean context, e.g. `(hover)`, has no comparisons.
+#[derive(Clone, Debug, PartialEq)]
+struct Feature {
+    name: String,
+    comparisons: Vec<(Comparison, Value)>,
+}
+
+impl Feature {
+    fn parse(tokens: &[Token]) -> Option<Feature> {
+        match tokens {
+            [Token::Ident(name)] if !is_prefixed(name) => Some(Feature {
+                name: name.clone(),
+                comparisons: Vec::new(),
+            }),
+            [Token::Ident(name), Token::Colon, value @ ..] => {
+                let value = Value::parse(value)?;
+                let (comparison, name) = if let Some(name) = name.strip_prefix("min-") {
+                    (Comparison::Ge, name)
+                } else if let Some(name) = name.strip_prefix("max-") {
+                    (Comparison::Le, name)
+                } else {
+                    (Comparison::Eq, name.as_str())
+                };
+                Some(Feature {
+                    name: name.to_owned(),
+                    comparisons: vec![(comparison, value)],
+                })
+            }
+            _ => Feature::parse_range(tokens),
+        }
+    }
+
+    /// Parses the range syntax: `name op value`, `value op name` or `value op name op value`
+    fn parse_range(tokens: &[Token]) -> Option<Feature> {
+        let mut segments: Vec<&[Token]> = Vec::new();
+        let mut comparisons = Vec::new();
+        let mut start = 0;
+        for (i, token) in tokens.iter().enumerate() {
+            if let Token::Comparison(comparison) = token {
+                segments.push(&tokens[start..i]);
+                comparisons.push(*comparison);
+                start = i + 1;
+            }
+        }
+        segments.push(&tokens[start..]);
+
+        let name_of = |segment: &[Token]| match segment {
+            [Token::Ident(name)] if !is_prefixed(name) => Some(name.clone()),
+            _ => None,
+        };
+        match (segments.as_slice(), comparisons.as_slice()) {
+            ([left, right], [comparison]) => {
+                if let Some(name) = name_of(left) {
+                    Some(Feature {
+                        name,
+                        comparisons: vec![(*comparison, Value::parse(right)?)],
+                    })
+                } else {
+                    Some(Feature {
+                        name: name_of(right)?,
+                        comparisons: vec![(comparison.flip(), Value::parse(left)?)],
+                    })
+                }
+            }
+            ([low, name, high], [first, second]) => {
+                let ascending = |c: &Comparison| matches!(c, Comparison::Lt | Comparison::Le);
+                let descending = |c: &Comparison| matches!(c, Comparison::Gt | Comparison::Ge);
+                if !(ascending(first) && ascending(second)
+                    || descending(first) && descending(second))
+                {
+                    return None;
+                }
+                Some(Feature {
+                    name: name_of(name)?,
+                    comparisons: vec![
+                        (first.flip(), Value::parse(low)?),
+                        (*second, Value::parse(high)?),
+                    ],
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Evaluates the feature, giving `None` ("unknown") for features which are not supported
+    fn evaluate(&self, environment: &MediaEnvironment) -> Option<bool> {
+        let metrics = environment.metrics;
+        let width = f64::from(metrics.inner_width);
+        let height = f64::from(metrics.inner_height);
+
+        let range = |actual: f64, convert: fn(&Value) -> Option<f64>| -> Option<bool> {
+            if self.comparisons.is_empty() {
+                return Some(actual != 0.0);
+            }
+            let mut result = true;
+            for (comparison, value) in &self.comparisons {
+                result &= comparison.holds(actual, convert(value)?);
+            }
+            Some(result)
+        };
+        let discrete = |actual: &str, boolean: bool| -> Option<bool> {
+            match self.comparisons.as_slice() {
+                [] => Some(boolean),
+                [(Comparison::Eq, Value::Ident(expected))] => Some(expected == actual),
+                _ => None,
+            }
+        };
+
+        match self.name.as_str() {
+            "width" => range(width, Value::as_length),
+            "height" => range(height, Value::as_length),
+            "aspect-ratio" => range(width / height, Value::as_ratio),
+            "resolution" => range(metrics.device_pixel_ratio, Value::as_resolution),
+            "orientation" => {
+                let orientation = if height >= width {
+                    "portrait"
+                } else {
+                    "landscape"
+                };
+                discrete(orientation, true)
+            }
+            "prefers-color-scheme" => match environment.preferences.color_scheme {
+                ColorScheme::Light => discrete("light", true),
+                ColorScheme::Dark => discrete("dark", true),
+            },
+            "prefers-reduced-motion" => {
+                if environment.preferences.reduced_motion {
+                    discrete("reduce", true)
+                } else {
+                    discrete("no-preference", false)
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+fn is_prefixed(name: &str) -> bool {
+    name.starts_with("min-") || name.starts_with("max-")
+}
+
+/// A media condition, evaluated with three-valued logic: `None` means "unknown"
+#[derive(Clone, Debug, PartialEq)]
+enum Condition {
+    Not(Box<Condition>),
+    And(Vec<Condition>),
+    Or(Vec<Condition>),
+    Feature(Feature),
+    /// Something in parentheses which is not understood, which is neither true nor false
+    Unknown,
+}
+
+impl Condition {
+    fn evaluate(&self, environment: &MediaEnvironment) -> Option<bool> {
+        match self {
+            Condition::Not(condition) => condition.evaluate(environment).map(|result| !result),
+            Condition::And(conditions) => {
+                let results: Vec<Option<bool>> =
+                    conditions.iter().map(|c| c.evaluate(environment)).collect();
+                if results.contains(&Some(false)) {
+                    Some(false)
+                } else if results.contains(&None) {
+                    None
+                } else {
+                    Some(true)
+                }
+            }
+            Condition::Or(conditions) => {
+                let results: Vec<Option<bool>> =
+                    conditions.iter().map(|c| c.evaluate(environment)).collect();
+                if results.contains(&Some(true)) {
+                    Some(true)
+                } else if results.contains(&None) {
+                    None
+                } else {
+                    Some(false)
+                }
+            }
+            Condition::Feature(feature) => feature.evaluate(environment),
+            Condition::Unknown => None,
+        }
+    }
+}
+
+/// A single media query of a comma-separated list
+#[derive(Clone, Debug, PartialEq)]
+enum MediaQuery {
+    Query {
+        negated: bool,
+        /// Whether the media type (if any) applies to the sandbox, which is a screen
+        type_matches: bool,
+        condition: Option<Condition>,
+    },
+    /// A query which could not be parsed, which never matches ("not all")
+    Invalid,
+}
+
+impl MediaQuery {
+    fn evaluate(&self, environment: &MediaEnvironment) -> bool {
+        match self {
+            MediaQuery::Query {
+                negated,
+                type_matches,
+                condition,
+            } => {
+                let condition = match condition {
+                    Some(condition) => condition.evaluate(environment),
+                    None => Some(true),
+                };
+                let result = if *type_matches {
+                    condition
+                } else {
+                    Some(false)
+                };
+                let result = if *negated { result.map(|r| !r) } else { result };
+                result == Some(true)
+            }
+            MediaQuery::Invalid => false,
+        }
+    }
+}
+
+/// Parses a comma-separated media query list. Queries which cannot be parsed become
+/// [MediaQuery::Invalid] without affecting the others.
+fn parse_media_query_list(input: &str) -> Vec<MediaQuery> {
+    let tokens = tokenize(input);
+    if tokens.is_empty() {
+        return Vec::new();
+    }
+
+    let mut queries = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::LeftParen => depth += 1,
+            Token::RightParen => depth -= 1,
+            Token::Comma if depth == 0 => {
+                queries.push(parse_media_query(&tokens[start..i]));
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    queries.push(parse_media_query(&tokens[start..]));
+    queries
+}
+
+fn parse_media_query(tokens: &[Token]) -> MediaQuery {
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+    };
+    match parser.media_query() {
+        Some(query) if parser.position == tokens.len() => query,
+        _ => MediaQuery::Invalid,
+    }
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_ident(&self, ident: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(name)) if name == ident)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        token
+    }
+
+    fn media_query(&mut self) -> Option<MediaQuery> {
+        let starts_with_condition = matches!(self.peek(), Some(Token::LeftParen))
+            || (self.peek_ident("not")
+                && matches!(self.tokens.get(self.position + 1), Some(Token::LeftParen)));
+        if starts_with_condition {
+            return Some(MediaQuery::Query {
+                negated: false,
+                type_matches: true,
+                condition: Some(self.condition(true)?),
+            });
+        }
+
+        let negated = self.peek_ident("not");
+        if negated || self.peek_ident("only") {
+            self.next();
+        }
+        let type_matches = match self.next()? {
+            Token::Ident(media_type) => match media_type.as_str() {
+                "all" | "screen" => true,
+                "only" | "not" | "and" | "or" | "layer" => return None,
+                _ => false,
+            },
+            _ => return None,
+        };
+
+        let condition = if self.peek_ident("and") {
+            self.next();
+            Some(self.condition(false)?)
+        } else {
+            None
+        };
+        Some(MediaQuery::Query {
+            negated,
+            type_matches,
+            condition,
+        })
+    }
+
+    /// Parses a media condition. Conditions following a media type may not use `or`.
+    fn condition(&mut self, allow_or: bool) -> Option<Condition> {
+        if self.peek_ident("not") {
+            self.next();
+            return Some(Condition::Not(Box::new(self.in_parens()?)));
+        }
+
+        let mut conditions = vec![self.in_parens()?];
+        let combinator = match self.peek() {
+            Some(Token::Ident(name)) if name == "and" || (allow_or && name == "or") => name.clone(),
+            _ => return conditions.pop(),
+        };
+        while self.peek_ident(&combinator) {
+            self.next();
+            conditions.push(self.in_parens()?);
+        }
+
+        if combinator == "and" {
+            Some(Condition::And(conditions))
+        } else {
+            Some(Condition::Or(conditions))
+        }
+    }
+
+    fn in_parens(&mut self) -> Option<Condition> {
+        if self.next()? != &Token::LeftParen {
+            return None;
+        }
+
+        let start = self.position;
+        let nested = matches!(self.peek(), Some(Token::LeftParen)) || self.peek_ident("not");
+        if nested {
+            if let Some(condition) = self.condition(true) {
+                if self.peek() == Some(&Token::RightParen) {
+                    self.next();
+                    return Some(condition);
+                }
+            }
+            self.position = start;
+        }
+
+        // Find the matching parenthesis
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next()? {
+                Token::LeftParen => depth += 1,
+                Token::RightParen => depth -= 1,
+                _ => {}
+            }
+        }
+        let contents = &self.tokens[start..self.position - 1];
+        Some(match Feature::parse(contents) {
+            Some(feature) => Condition::Feature(feature),
+            None => Condition::Unknown,
+        })
+    }
+}
+
+/// What media queries are evaluated against
+struct MediaEnvironment<'a> {
+    metrics: &'a ScreenMetrics,
+    preferences: &'a UserPreferences,
+}
+
+/// A [MediaQueryList](https://developer.mozilla.org/en-US/docs/Web/API/MediaQueryList), which
+/// fires a change event whenever its query starts or stops matching. Change events are only
+/// fired while the list is retained.
+#[sourcegen::sourcegen(generator = "behave", script = "SandboxMember context")]
+// Generated. All manual edits to the block annotated with #[sourcegen...] will be discarded.
+pub struct MediaQueryList {
+    /// SandboxMember implementation
+    context: Weak<Sandbox>,
+    /// The query as written
+    media: String,
+    /// The parsed queries
+    queries: Vec<MediaQuery>,
+    /// The window the queries are evaluated in
+    window: Weak<Window>,
+    /// Whether the queries matched when they were last evaluated
+    last_matches: RwLock<bool>,
+    self_weak: Weak<MediaQueryList>,
+    pub(crate) event_target_behavior: EventTargetBehaviorStorage,
+}
+
+#[sourcegen::generated]
+impl MediaQueryList {
+    /// gets `Weak<Sandbox>` to the `Sandbox` that it is in
+    pub fn get_context(&self) -> Weak<Sandbox> {
+        self.context.clone()
+    }
+}
+
+#[sourcegen::generated]
+impl SandboxMemberBehavior for MediaQueryList {
+    fn get_context(&self) -> Weak<Sandbox> {
+        self.get_context()
+    }
+}
+
+impl MediaQueryList {
+    pub(crate) fn new(context: Weak<Sandbox>, window: &Window, media: &str) -> Arc<MediaQueryList> {
+        let queries = parse_media_query_list(media);
+        let matches = evaluate(&queries, window);
+        Arc::new_cyclic(|self_weak| MediaQueryList {
+            context,
+            media: media.trim().to_owned(),
+            queries,
+            window: window.self_weak(),
+            last_matches: RwLock::new(matches),
+            self_weak: self_weak.clone(),
+            event_target_behavior: EventTargetBehaviorStorage::new(),
+        })
+    }
+
+    pub(crate) fn self_arc(&self) -> Result<Arc<MediaQueryList>, DomError> {
+        self.self_weak.upgrade().ok_or(DomError::SandboxDropped)
+    }
+
+    proxy_event_target_behavior!();
+
+    /// [MediaQueryList.media](https://developer.mozilla.org/en-US/docs/Web/API/MediaQueryList/media)
+    pub fn media(&self) -> String {
+        self.media.clone()
+    }
+
+    /// [MediaQueryList.matches](https://developer.mozilla.org/en-US/docs/Web/API/MediaQueryList/matches)
+    pub fn matches(&self) -> bool {
+        match self.window.upgrade() {
+            Some(window) => evaluate(&self.queries, &window),
+            None => false,
+        }
+    }
+
+    /// Re-evaluates the queries, and records and returns the new result if it changed
+    pub(crate) fn update(&self) -> Option<bool> {
+        let matches = self.matches();
+        let mut last_matches = self.last_matches.write().unwrap();
+        if *last_matches == matches {
+            None
+        } else {
+            *last_matches = matches;
+            Some(matches)
+        }
+    }
+}
+
+impl_event_target!(MediaQueryList, event_target_behavior);
+
+/// Whether any of the queries matches. An empty list matches everything.
+fn evaluate(queries: &[MediaQuery], window: &Window) -> bool {
+    let metrics = window.screen_metrics();
+    let preferences = window.user_preferences();
+    let environment = MediaEnvironment {
+        metrics: &metrics,
+        preferences: &preferences,
+    };
+    queries.is_empty() || queries.iter().any(|query| query.evaluate(&environment))
+}
@@ -0,0 +1,351 @@
+//! [MutationObserver](https://developer.mozilla.org/en-US/docs/Web/API/MutationObserver):
+//! notifications of changes to the node tree. Records are queued as the tree is mutated and
+//! delivered in batches at the sandbox's next microtask checkpoint.
+
+use crate::internal_prelude::*;
+use crate::node_list::NodeList;
+use std::sync::RwLock;
+
+/// The function called with each batch of mutation records
+pub type MutationCallback = Arc<dyn Fn(&[MutationRecord], &MutationObserver) + Send + Sync>;
+
+/// Options for [MutationObserver.observe](https://developer.mozilla.org/en-US/docs/Web/API/MutationObserver/observe)
+#[derive(Clone, Debug, Default)]
+pub struct MutationObserverInit {
+    /// Observe the addition and removal of children
+    pub child_list: bool,
+    /// Observe attribute changes. Implied by `attribute_old_value` and `attribute_filter`.
+    pub attributes: Option<bool>,
+    /// Observe text data changes. Implied by `character_data_old_value`.
+    pub character_data: Option<bool>,
+    /// Also observe all descendants of the target
+    pub subtree: bool,
+    /// Record the previous value of changed attributes
+    pub attribute_old_value: Option<bool>,
+    /// Record the previous data of changed text
+    pub character_data_old_value: Option<bool>,
+    /// Only observe attributes with these (lowercase) names
+    pub attribute_filter: Option<Vec<String>>,
+}
+
+impl MutationObserverInit {
+    /// Fills in the implied options and checks that the result observes something, per the
+    /// [spec](https://dom.spec.whatwg.org/#dom-mutationobserver-observe)
+    fn normalize(mut self) -> Result<MutationObserverInit, DomError> {
+        if self.attributes.is_none()
+            && (self.attribute_old_value.is_some() || self.attribute_filter.is_some())
+        {
+            self.attributes = Some(true);
+        }
+        if self.character_data.is_none() && self.character_data_old_value.is_some() {
+            self.character_data = Some(true);
+        }
+
+        let attributes = self.attributes.unwrap_or(false);
+        let character_data = self.character_data.unwrap_or(false);
+        if !self.child_list && !attributes && !character_data {
+            return Err(DomError::Type);
+        }
+        if !attributes
+            && (self.attribute_old_value == Some(true) || self.attribute_filter.is_some())
+        {
+            return Err(DomError::Type);
+        }
+        if !character_data && self.character_data_old_value == Some(true) {
+            return Err(DomError::Type);
+        }
+        Ok(self)
+    }
+
+    fn wants(&self, record: &MutationRecord) -> bool {
+        match record.record_type {
+            MutationRecordType::ChildList => self.child_list,
+            MutationRecordType::CharacterData => self.character_data == Some(true),
+            MutationRecordType::Attributes => {
+                self.attributes == Some(true)
+                    && match (&self.attribute_filter, &record.attribute_name) {
+                        (Some(filter), Some(name)) => filter.contains(name),
+                        _ => true,
+                    }
+            }
+        }
+    }
+
+    fn wants_old_value(&self, record_type: MutationRecordType) -> bool {
+        match record_type {
+            MutationRecordType::ChildList => false,
+            MutationRecordType::Attributes => self.attribute_old_value == Some(true),
+            MutationRecordType::CharacterData => self.character_data_old_value == Some(true),
+        }
+    }
+}
+
+/// The kind of change a mutation record describes
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MutationRecordType {
+    /// Children were added or removed
+    ChildList,
+    /// An attribute was set or removed
+    Attributes,
+    /// The data of a text or comment node changed
+    CharacterData,
+}
+
+/// A [MutationRecord](https://developer.mozilla.org/en-US/docs/Web/API/MutationRecord)
+#[derive(Clone)]
+pub struct MutationRecord {
+    record_type: MutationRecordType,
+    target: AnyNodeArc,
+    added_nodes: Arc<NodeList>,
+    removed_nodes: Arc<NodeList>,
+    previous_sibling: Option<AnyNodeArc>,
+    next_sibling: Option<AnyNodeArc>,
+    attribute_name: Option<String>,
+    old_value: Option<String>,
+}
+
+impl MutationRecord {
+    fn new(record_type: MutationRecordType, target: &AnyNodeArc) -> MutationRecord {
+        MutationRecord {
+            record_type,
+            target: target.clone(),
+            added_nodes: NodeList::new_static(target.get_context(), Vec::new()),
+            removed_nodes: NodeList::new_static(target.get_context(), Vec::new()),
+            previous_sibling: None,
+            next_sibling: None,
+            attribute_name: None,
+            old_value: None,
+        }
+    }
+
+    pub(crate) fn child_list(
+        target: &AnyNodeArc,
+        added_nodes: Vec<AnyNodeArc>,
+        removed_nodes: Vec<AnyNodeArc>,
+        previous_sibling: Option<AnyNodeArc>,
+        next_sibling: Option<AnyNodeArc>,
+    ) -> MutationRecord {
+        MutationRecord {
+            added_nodes: NodeList::new_static(target.get_context(), added_nodes),
+            removed_nodes: NodeList::new_static(target.get_context(), removed_nodes),
+            previous_sibling,
+            next_sibling,
+            ..MutationRecord::new(MutationRecordType::ChildList, target)
+        }
+    }
+
+    pub(crate) fn attributes(
+        target: &AnyNodeArc,
+        attribute_name: String,
+        old_value: Option<String>,
+    ) -> MutationRecord {
+        MutationRecord {
+            attribute_name: Some(attribute_name),
+            old_value,
+            ..MutationRecord::new(MutationRecordType::Attributes, target)
+        }
+    }
+
+    pub(crate) fn character_data(target: &AnyNodeArc, old_value: String) -> MutationRecord {
+        MutationRecord {
+            old_value: Some(old_value),
+            ..MutationRecord::new(MutationRecordType::CharacterData, target)
+        }
+    }
+
+    /// [MutationRecord.type](https://developer.mozilla.org/en-US/docs/Web/API/MutationRecord/type)
+    pub fn record_type(&self) -> MutationRecordType {
+        self.record_type
+    }
+
+    /// [MutationRecord.target](https://developer.mozilla.org/en-US/docs/Web/API/MutationRecord/target)
+    pub fn target(&self) -> AnyNodeArc {
+        self.target.clone()
+    }
+
+    /// [MutationRecord.addedNodes](https://developer.mozilla.org/en-US/docs/Web/API/MutationRecord/addedNodes)
+    pub fn added_nodes(&self) -> Arc<NodeList> {
+        self.added_nodes.clone()
+    }
+
+    /// [MutationRecord.removedNodes](https://developer.mozilla.org/en-US/docs/Web/API/MutationRecord/removedNodes)
+    pub fn removed_nodes(&self) -> Arc<NodeList> {
+        self.removed_nodes.clone()
+    }
+
+    /// [MutationRecord.previousSibling](https://developer.mozilla.org/en-US/docs/Web/API/MutationRecord/previousSibling)
+    pub fn previous_sibling(&self) -> Option<AnyNodeArc> {
+        self.previous_sibling.clone()
+    }
+
+    /// [MutationRecord.nextSibling](https://developer.mozilla.org/en-US/docs/Web/API/MutationRecord/nextSibling)
+    pub fn next_sibling(&self) -> Option<AnyNodeArc> {
+        self.next_sibling.clone()
+    }
+
+    /// [MutationRecord.attributeName](https://developer.mozilla.org/en-US/docs/Web/API/MutationRecord/attributeName)
+    pub fn attribute_name(&self) -> Option<String> {
+        self.attribute_name.clone()
+    }
+
+    /// [MutationRecord.oldValue](https://developer.mozilla.org/en-US/docs/Web/API/MutationRecord/oldValue),
+    /// only recorded if the observer asked for it
+    pub fn old_value(&self) -> Option<String> {
+        self.old_value.clone()
+    }
+}
+
+/// An observer registered on a node, together with the options it was registered with
+pub(crate) struct RegisteredObserver {
+    observer: Arc<MutationObserver>,
+    options: MutationObserverInit,
+}
+
+/// A [MutationObserver](https://developer.mozilla.org/en-US/docs/Web/API/MutationObserver)
+#[sourcegen::sourcegen(generator = "behave", script = "SandboxMember context")]
+// Generated. All manual edits to the block annotated with #[sourcegen...] will be discarded.
+pub struct MutationObserver {
+    /// SandboxMember implementation
+    context: Weak<Sandbox>,
+    /// Called with each batch of records
+    callback: MutationCallback,
+    /// Records which have not been delivered yet
+    record_queue: RwLock<Vec<MutationRecord>>,
+    /// The nodes this observer is registered on
+    node_list: RwLock<Vec<AnyNodeWeak>>,
+    /// Reference to self, for registering on nodes
+    self_weak: Weak<MutationObserver>,
+}
+
+#[sourcegen::generated]
+impl MutationObserver {
+    /// gets `Weak<Sandbox>` to the `Sandbox` that it is in
+    pub fn get_context(&self) -> Weak<Sandbox> {
+        self.context.clone()
+    }
+}
+
+#[sourcegen::generated]
+impl SandboxMemberBehavior for MutationObserver {
+    fn get_context(&self) -> Weak<Sandbox> {
+        self.get_context()
+    }
+}
+
+impl MutationObserver {
+    /// Creates a mutation observer which calls `callback` with the records queued for it
+    pub fn new(context: &Arc<Sandbox>, callback: MutationCallback) -> Arc<MutationObserver> {
+        Arc::new_cyclic(|self_weak| MutationObserver {
+            context: Arc::downgrade(context),
+            callback,
+            record_queue: RwLock::new(Vec::new()),
+            node_list: RwLock::new(Vec::new()),
+            self_weak: self_weak.clone(),
+        })
+    }
+
+    /// [MutationObserver.observe](https://developer.mozilla.org/en-US/docs/Web/API/MutationObserver/observe).
+    /// Observing a node again replaces the options it was observed with. Fails with
+    /// `DomError::Type` if the options do not observe anything or contradict each other.
+    pub fn observe(
+        &self,
+        target: &AnyNodeArc,
+        options: MutationObserverInit,
+    ) -> Result<(), DomError> {
+        let options = options.normalize()?;
+        let this = self
+            .self_weak
+            .upgrade()
+            .ok_or(DomError::ObjectOutOfMemory)?;
+
+        let mut registered_observers = target.common.registered_observers.write().unwrap();
+        match registered_observers
+            .iter_mut()
+            .find(|registered| Arc::ptr_eq(&registered.observer, &this))
+        {
+            Some(registered) => registered.options = options,
+            None => {
+                registered_observers.push(RegisteredObserver {
+                    observer: this,
+                    options,
+                });
+                self.node_list.write().unwrap().push(target.downgrade());
+            }
+        }
+        Ok(())
+    }
+
+    /// [MutationObserver.disconnect](https://developer.mozilla.org/en-US/docs/Web/API/MutationObserver/disconnect).
+    /// Stops observing all nodes and discards any undelivered records.
+    pub fn disconnect(&self) {
+        for node in self.node_list.write().unwrap().drain(..) {
+            if let Some(node) = node.upgrade() {
+                node.common
+                    .registered_observers
+                    .write()
+                    .unwrap()
+                    .retain(|registered| !std::ptr::eq(Arc::as_ptr(&registered.observer), self));
+            }
+        }
+        self.record_queue.write().unwrap().clear();
+    }
+
+    /// [MutationObserver.takeRecords](https://developer.mozilla.org/en-US/docs/Web/API/MutationObserver/takeRecords):
+    /// removes and returns the undelivered records
+    pub fn take_records(&self) -> Vec<MutationRecord> {
+        std::mem::take(&mut *self.record_queue.write().unwrap())
+    }
+
+    /// Delivers the undelivered records to the callback, if there are any
+    pub(crate) fn notify(&self) {
+        let records = self.take_records();
+        if !records.is_empty() {
+            (self.callback)(&records, self);
+        }
+    }
+}
+
+/// [Queues a mutation record](https://dom.spec.whatwg.org/#queue-a-mutation-record) with every
+/// observer interested in it: those registered on the target, and those registered with
+/// `subtree` on one of its ancestors.
+pub(crate) fn queue_mutation_record(record: MutationRecord) {
+    let mut interested: Vec<(Arc<MutationObserver>, Option<String>)> = Vec::new();
+
+    let mut node = Some(record.target.clone());
+    while let Some(current) = node {
+        let is_target = Arc::ptr_eq(&current.common, &record.target.common);
+        for registered in current.common.registered_observers.read().unwrap().iter() {
+            if (!is_target && !registered.options.subtree) || !registered.options.wants(&record) {
+                continue;
+            }
+
+            let old_value = if registered.options.wants_old_value(record.record_type) {
+                record.old_value.clone()
+            } else {
+                None
+            };
+            match interested
+                .iter_mut()
+                .find(|(observer, _)| Arc::ptr_eq(observer, &registered.observer))
+            {
+                Some(entry) => {
+                    if old_value.is_some() {
+                        entry.1 = old_value;
+                    }
+                }
+                None => interested.push((registered.observer.clone(), old_value)),
+            }
+        }
+        node = current.common.node_graph.parent_node();
+    }
+
+    for (observer, old_value) in interested {
+        observer.record_queue.write().unwrap().push(MutationRecord {
+            old_value,
+            ..record.clone()
+        });
+        if let Some(context) = observer.context.upgrade() {
+            context.queue_mutation_observer_microtask(observer);
+        }
+    }
+}
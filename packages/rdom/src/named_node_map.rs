@@ -2,6 +2,7 @@
 //! and associated metadata.
 
 use crate::internal_prelude::*;
+use crate::mutation_observer::{queue_mutation_record, MutationRecord};
 use crate::node::concrete::{AttributeNodeArc, ElementNodeWeak};
 use std::convert::TryInto;
 
@@ -67,7 +68,7 @@ impl NamedNodeMap {
             _ => {
                 let name = item.contents.name();
                 let name = name.to_ascii_lowercase();
-                let existing_index = self.get_attribute_idx(name);
+                let existing_index = self.get_attribute_idx(name.clone());
                 item.contents
                     .set_owner_element(Some(self.owning_element.clone()));
                 Ok(if let Some(existing_index) = existing_index {
@@ -75,11 +76,13 @@ impl NamedNodeMap {
                     if existing_attr == item {
                         Some(item)
                     } else {
+                        self.queue_attribute_record(name, Some(existing_attr.contents.value()));
                         let old = self.replace_attribute(existing_index, item);
                         old.contents.set_owner_element(None);
                         Some(existing_attr)
                     }
                 } else {
+                    self.queue_attribute_record(name, None);
                     self.attribute_list.push(item);
                     None
                 })
@@ -93,17 +96,26 @@ impl NamedNodeMap {
         qualified_name: String,
     ) -> Result<AttributeNodeArc, DomError> {
         let name = qualified_name.to_ascii_lowercase();
-        let existing_index = self.get_attribute_idx(name);
+        let existing_index = self.get_attribute_idx(name.clone());
         match existing_index {
             None => Err(DomError::NotFound),
             Some(existing_index) => Ok({
                 let old_attr = self.attribute_list.remove(existing_index);
+                self.queue_attribute_record(name, Some(old_attr.contents.value()));
                 old_attr.contents.set_owner_element(None);
                 old_attr
             }),
         }
     }
 
+    /// Queues an "attributes" mutation record for a change to the attribute `name` of the owning
+    /// element
+    pub(crate) fn queue_attribute_record(&self, name: String, old_value: Option<String>) {
+        if let Some(element) = self.owning_element.upgrade() {
+            queue_mutation_record(MutationRecord::attributes(&element.into(), name, old_value));
+        }
+    }
+
     // TODO: namespaces
     fn get_attribute_idx(&self, local_name: String) -> Option<usize> {
         self.attribute_list.iter().position(|attr| {
@@ -0,0 +1,28 @@
+//! Navigations requested by a document, e.g. by following a link or submitting a form. A
+//! sandbox never leaves its document; it records each request so that tests can inspect it.
+
+/// What caused a navigation to be requested
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum NavigationSource {
+    /// A hyperlink was followed
+    Link,
+    /// A form was submitted
+    Form,
+    /// A script navigated through `Location` or `History`
+    Script,
+}
+
+/// A navigation which the document asked the browser to perform
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NavigationRequest {
+    /// The URL to navigate to, resolved against the document's URL
+    pub url: String,
+    /// The HTTP method, "GET" or "POST"
+    pub method: String,
+    /// The `application/x-www-form-urlencoded` request body, for POST form submissions
+    pub body: Option<String>,
+    /// The browsing context the navigation targets, e.g. "_blank"
+    pub target: Option<String>,
+    /// What caused the navigation
+    pub source: NavigationSource,
+}
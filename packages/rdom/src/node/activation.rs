@@ -0,0 +1,217 @@
+//! [Activation behavior](https://dom.spec.whatwg.org/#eventtarget-activation-behavior): what
+//! an element does when it is clicked, after the click event was dispatched without being
+//! canceled. Checkboxes and radio buttons also change state before the event is dispatched, and
+//! roll that change back if it is canceled.
+
+use super::concrete::ElementNodeArc;
+use super::element::HtmlElementStore;
+use crate::event::{Event, EventInit, EventKind, EventTargetArc, MouseEventData};
+use crate::internal_prelude::*;
+use crate::navigation::{NavigationRequest, NavigationSource};
+
+use std::convert::TryInto;
+
+/// State saved by the legacy-pre-activation behavior, so that it can be rolled back
+pub(crate) enum SavedActivationState {
+    None,
+    Checkbox {
+        was_checked: bool,
+    },
+    Radio {
+        was_checked: bool,
+        previously_checked: Option<ElementNodeArc>,
+    },
+}
+
+/// Whether clicking the element does anything beyond dispatching the click event
+fn has_activation_behavior(element: &ElementNodeArc) -> bool {
+    if element.disabled() {
+        return false;
+    }
+    match element.contents.html_element() {
+        Some(HtmlElementStore::HtmlButton(_)) | Some(HtmlElementStore::HtmlLabel(_)) => true,
+        Some(HtmlElementStore::HtmlInput(_)) => matches!(
+            element.input_type().as_str(),
+            "checkbox" | "radio" | "submit" | "image" | "reset"
+        ),
+        Some(HtmlElementStore::HtmlAnchor(_)) => element.has_attribute("href".to_owned()),
+        _ => false,
+    }
+}
+
+/// Finds the element whose activation behavior a click along `path` triggers: the target if it
+/// has activation behavior, or else (for bubbling events) its nearest ancestor which does.
+pub(crate) fn activation_target(event: &Event, path: &[EventTargetArc]) -> Option<ElementNodeArc> {
+    let is_click = event.event_type() == "click"
+        && matches!(event.kind(), EventKind::Mouse(_) | EventKind::Pointer(_));
+    if !is_click {
+        return None;
+    }
+
+    let candidates = if event.bubbles() { path.len() } else { 1 };
+    path.iter().take(candidates).find_map(|target| {
+        let element: ElementNodeArc = target.node()?.try_into().ok()?;
+        if has_activation_behavior(&element) {
+            Some(element)
+        } else {
+            None
+        }
+    })
+}
+
+/// Runs the legacy-pre-activation behavior: checkboxes toggle and radio buttons become checked
+/// before any listener sees the click.
+pub(crate) fn legacy_pre_activation(
+    element: &ElementNodeArc,
+) -> Result<SavedActivationState, DomError> {
+    if !matches!(
+        element.contents.html_element(),
+        Some(HtmlElementStore::HtmlInput(_))
+    ) {
+        return Ok(SavedActivationState::None);
+    }
+
+    match element.input_type().as_str() {
+        "checkbox" => {
+            let was_checked = element.checked()?;
+            element.set_checked(!was_checked)?;
+            Ok(SavedActivationState::Checkbox { was_checked })
+        }
+        "radio" => {
+            let was_checked = element.checked()?;
+            let previously_checked = super::form::radio_group(element)
+                .into_iter()
+                .find(|other| other.checked().unwrap_or(false));
+            element.set_checked(true)?;
+            Ok(SavedActivationState::Radio {
+                was_checked,
+                previously_checked,
+            })
+        }
+        _ => Ok(SavedActivationState::None),
+    }
+}
+
+/// Runs the legacy-canceled-activation behavior, undoing the legacy-pre-activation behavior
+pub(crate) fn legacy_canceled_activation(
+    element: &ElementNodeArc,
+    saved: SavedActivationState,
+) -> Result<(), DomError> {
+    match saved {
+        SavedActivationState::None => {}
+        SavedActivationState::Checkbox { was_checked } => element.set_checked(was_checked)?,
+        SavedActivationState::Radio {
+            was_checked,
+            previously_checked,
+        } => {
+            element.set_checked(was_checked)?;
+            if let Some(previously_checked) = previously_checked {
+                previously_checked.set_checked(true)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs the activation behavior of `element` for the (uncanceled) click `event`
+pub(crate) fn activation_behavior(
+    element: &ElementNodeArc,
+    event: &Event,
+    saved: SavedActivationState,
+) -> Result<(), DomError> {
+    match element.contents.html_element() {
+        Some(HtmlElementStore::HtmlButton(_)) => submit_or_reset(element),
+        Some(HtmlElementStore::HtmlInput(_)) => match saved {
+            SavedActivationState::Checkbox { .. }
+            | SavedActivationState::Radio {
+                was_checked: false, ..
+            } => {
+                fire_simple(element, "input")?;
+                fire_simple(element, "change")
+            }
+            SavedActivationState::Radio { .. } => Ok(()),
+            SavedActivationState::None => submit_or_reset(element),
+        },
+        Some(HtmlElementStore::HtmlAnchor(_)) => {
+            let context = element
+                .get_context()
+                .upgrade()
+                .ok_or(DomError::SandboxDropped)?;
+            let href = element.get_attribute("href".to_owned()).unwrap_or_default();
+            let target = element.get_attribute("target".to_owned());
+
+            // Links which are not valid URLs are not followed
+            let history = context.window().history();
+            let url = match history.document_url()?.join(&href) {
+                Ok(url) => url,
+                Err(_) => return Ok(()),
+            };
+
+            // Links to a fragment of this document are followed within the sandbox
+            if target.is_none() && history.navigate_to_fragment(&url, false)? {
+                return Ok(());
+            }
+
+            context.request_navigation(NavigationRequest {
+                url: url.to_string(),
+                method: "GET".to_owned(),
+                body: None,
+                target,
+                source: NavigationSource::Link,
+            });
+            Ok(())
+        }
+        Some(HtmlElementStore::HtmlLabel(_)) => {
+            let control = match element.control() {
+                Some(control) => control,
+                None => return Ok(()),
+            };
+
+            // Clicks on the control itself are not forwarded again
+            let mut node = event.target().and_then(|target| target.node());
+            while let Some(current) = node {
+                if Arc::ptr_eq(&current.common, &control.common) {
+                    return Ok(());
+                }
+                node = current.common.node_graph.parent_node();
+            }
+
+            let click = Event::new(
+                "click".to_owned(),
+                EventInit {
+                    bubbles: true,
+                    cancelable: true,
+                    kind: EventKind::Mouse(MouseEventData {
+                        detail: 1,
+                        ..Default::default()
+                    }),
+                },
+            );
+            control.dispatch_event(&click).map(|_| ())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn submit_or_reset(element: &ElementNodeArc) -> Result<(), DomError> {
+    let form = match element.form() {
+        Some(form) => form,
+        None => return Ok(()),
+    };
+    match element.input_type().as_str() {
+        "submit" | "image" => form.request_submit(Some(element)),
+        "reset" => form.reset(),
+        _ => Ok(()),
+    }
+}
+
+fn fire_simple(element: &ElementNodeArc, event_type: &str) -> Result<(), DomError> {
+    let event = Event::new(
+        event_type.to_owned(),
+        EventInit {
+            bubbles: true,
+            ..Default::default()
+        },
+    );
+    element.dispatch_event(&event).map(|_| ())
+}
@@ -0,0 +1,129 @@
+//! Moving nodes between documents: [cloning](https://dom.spec.whatwg.org/#concept-node-clone)
+//! a node into a document and [adopting](https://dom.spec.whatwg.org/#concept-node-adopt) one.
+//! Documents may belong to different sandboxes, so both rewrite the context of the nodes they
+//! move.
+
+use super::concrete::*;
+use super::contents::{
+    CDataSectionStore, CommentStore, DocumentStore, NodeContentsArc, ProcessingInstructionStore,
+    TextStore,
+};
+use super::element::ElementStore;
+use crate::internal_prelude::*;
+
+use std::sync::RwLock;
+
+/// Makes `node`, and its attributes if it is an element, belong to `document` and its sandbox.
+/// Its descendants are left alone.
+fn set_node_document(node: &AnyNodeArc, document: &DocumentNodeArc) {
+    let context = document.get_context();
+    node.common.set_context(context.clone());
+    node.common.set_owner_document(document);
+    if let NodeContentsArc::Element(element) = &node.contents {
+        element.set_attributes_context(context);
+        for attribute in element.attributes() {
+            attribute.common.set_context(document.get_context());
+            attribute.common.set_owner_document(document);
+        }
+    }
+}
+
+/// Copies `node` alone into the sandbox `context`. The copy has its own data, and no document.
+pub(crate) fn copy_node(node: &AnyNodeArc, context: Weak<Sandbox>) -> AnyNodeArc {
+    match &node.contents {
+        NodeContentsArc::Element(element) => {
+            let copy = ElementNodeArc::new_cyclic(context.clone(), |node_weak| {
+                ElementStore::new_ns(
+                    element.clone_kind(),
+                    element.namespace_uri(),
+                    context.clone(),
+                    node_weak.clone().into(),
+                )
+            });
+            for attribute in element.attributes() {
+                let attribute =
+                    AttributeNodeArc::new(context.clone(), Arc::new(attribute.contents.copy()));
+                // A new attribute, on an element which has none, cannot be in use
+                let _ = copy.contents.set_attribute_node(attribute);
+            }
+            copy.into()
+        }
+        NodeContentsArc::Attribute(attribute) => {
+            AttributeNodeArc::new(context, Arc::new(attribute.copy())).into()
+        }
+        NodeContentsArc::Text(text) => {
+            let store = TextStore::new(text.character_data.data());
+            TextNodeArc::new(context, Arc::new(store)).into()
+        }
+        NodeContentsArc::Comment(comment) => {
+            let store = CommentStore::new(comment.character_data.data());
+            CommentNodeArc::new(context, Arc::new(store)).into()
+        }
+        NodeContentsArc::Document(source) => {
+            let store = DocumentStore {
+                url: Arc::new(RwLock::new(source.url.read().unwrap().clone())),
+                referrer: source.referrer.clone(),
+                content_type: source.content_type.clone(),
+                mode: Arc::new(RwLock::new(*source.mode.read().unwrap())),
+                ..Default::default()
+            };
+            DocumentNodeArc::new(context, Arc::new(store)).into()
+        }
+        NodeContentsArc::CDataSection(cdata) => {
+            let store = CDataSectionStore::new(cdata.character_data.data());
+            CDataSectionNodeArc::new(context, Arc::new(store)).into()
+        }
+        NodeContentsArc::ProcessingInstruction(instruction) => {
+            let store = ProcessingInstructionStore::new(
+                instruction.target(),
+                instruction.character_data.data(),
+            );
+            ProcessingInstructionNodeArc::new(context, Arc::new(store)).into()
+        }
+        NodeContentsArc::DocumentType(store) => {
+            DocumentTypeNodeArc::new(context, store.clone()).into()
+        }
+        NodeContentsArc::DocumentFragment(store) => {
+            DocumentFragmentNodeArc::new(context, store.clone()).into()
+        }
+    }
+}
+
+/// [Clones](https://dom.spec.whatwg.org/#concept-node-clone) `node` into `document`, with its
+/// descendants if `deep` is set
+pub(crate) fn clone_node(node: &AnyNodeArc, document: &DocumentNodeArc, deep: bool) -> AnyNodeArc {
+    let copy = copy_node(node, document.get_context());
+    set_node_document(&copy, document);
+
+    if deep {
+        for child in node.common.node_graph.static_child_nodes() {
+            copy.common
+                .node_graph
+                .append_child(clone_node(&child, document, true));
+        }
+    }
+    copy
+}
+
+/// [Adopts](https://dom.spec.whatwg.org/#concept-node-adopt) `node` into `document`: removes it
+/// from its parent, and makes it and its descendants belong to `document`
+pub(crate) fn adopt_node(node: &AnyNodeArc, document: &DocumentNodeArc) {
+    if let Some(parent) = node.common.node_graph.parent_node() {
+        parent.common.node_graph.remove_child(node);
+    }
+
+    let already_adopted = node
+        .common
+        .owner_document()
+        .is_some_and(|owner| Arc::ptr_eq(&owner.common, &document.common));
+    if !already_adopted {
+        set_document_of_inclusive_descendants(node, document);
+    }
+}
+
+fn set_document_of_inclusive_descendants(node: &AnyNodeArc, document: &DocumentNodeArc) {
+    set_node_document(node, document);
+    for child in node.common.node_graph.static_child_nodes() {
+        set_document_of_inclusive_descendants(&child, document);
+    }
+}
@@ -1,29 +1,39 @@
 //! Concrete (as opposed to abstract) types of nodes. Each node class is represented in this module.
 
+use crate::dom_implementation::DomImplementation;
+use crate::event::{Event, EventKind};
 use crate::internal_prelude::*;
-use crate::node::element::ElementStore;
-use crate::node_list::NodeList;
+use crate::node::element::{ElementStore, HtmlElementStore, HTML_NAMESPACE};
+use crate::node_list::{ElementFilter, NodeList, NodeListStorage, Query};
+use crate::range::{self, Range};
 use crate::selector::Selector;
-use crate::{impl_parent_node, proxy_node_behavior, proxy_parent_node_behavior};
+use crate::tree_walker::{NodeFilter, TreeWalker};
+use crate::{
+    impl_character_data, impl_child_node, impl_event_target, impl_parent_node,
+    proxy_character_data_behavior, proxy_child_node_behavior, proxy_event_target_behavior,
+    proxy_node_behavior, proxy_non_document_type_child_node_behavior, proxy_parent_node_behavior,
+};
 
 use super::contents::{
     AttributeStore, CDataSectionStore, CommentStore, DocumentFragmentStore, DocumentStore,
     DocumentTypeStore, ProcessingInstructionStore, TextStore,
 };
+use super::quirks::QuirksMode;
 use super::{
-    AnyNodeStore, Buildable, NodeBehavior, NodeCommon, NodeContentsArc, NodeContentsWeak,
+    adoption, AnyNodeStore, Buildable, NodeBehavior, NodeCommon, NodeContentsArc, NodeContentsWeak,
     NodeGraphStorage,
 };
 
 use arc_new_cyclic_n::arc::new_cyclic_2;
 use std::convert::TryFrom;
+use std::sync::RwLock;
 
-crate::use_behaviors!(parent_node);
+crate::use_behaviors!(parent_node, event_target, character_data, child_node);
 
 /// A strongly-typed handle to a node with a strong reference.
 /// Generic type `S` may be the underlying storage
 /// type of any node class.
-#[sourcegen::sourcegen(generator = "behave", script = "SandboxMember common.context")]
+#[sourcegen::sourcegen(generator = "behave", script = "SandboxMember common.context()")]
 // Generated. All manual edits to the block annotated with #[sourcegen...] will be discarded.
 #[derive(Clone)]
 pub struct ConcreteNodeArc<S: AnyNodeStore> {
@@ -35,7 +45,7 @@ pub struct ConcreteNodeArc<S: AnyNodeStore> {
 impl<S: AnyNodeStore> ConcreteNodeArc<S> {
     /// gets `Weak<Sandbox>` to the `Sandbox` that it is in
     pub fn get_context(&self) -> Weak<Sandbox> {
-        self.common.context.clone()
+        self.common.context().clone()
     }
 }
 
@@ -57,6 +67,16 @@ impl<S: AnyNodeStore> PartialEq for ConcreteNodeArc<S> {
     }
 }
 
+impl<S: AnyNodeStore> ConcreteNodeArc<S> {
+    /// Creates a weak reference to the node
+    pub fn downgrade(&self) -> ConcreteNodeWeak<S> {
+        ConcreteNodeWeak {
+            contents: Arc::downgrade(&self.contents),
+            common: Arc::downgrade(&self.common),
+        }
+    }
+}
+
 /// A strongly-typed handle to a node with a weak reference.
 /// Generic type `S` may be the underlying storage
 /// type of any node class.
@@ -66,6 +86,16 @@ pub struct ConcreteNodeWeak<S: AnyNodeStore> {
     pub(crate) common: Weak<NodeCommon>,
 }
 
+impl<S: AnyNodeStore> ConcreteNodeWeak<S> {
+    /// Attempts to upgrade to a strong reference, which fails if the node was dropped
+    pub fn upgrade(&self) -> Option<ConcreteNodeArc<S>> {
+        Some(ConcreteNodeArc {
+            contents: self.contents.upgrade()?,
+            common: self.common.upgrade()?,
+        })
+    }
+}
+
 impl<S: AnyNodeStore> PartialEq for ConcreteNodeWeak<S> {
     fn eq(&self, other: &Self) -> bool {
         let a = self.contents.ptr_eq(&other.contents);
@@ -114,7 +144,11 @@ macro_rules! impl_concrete {
                                     common: common_weak.clone(),
                                 }),
                                 parent_node_behavior: ParentNodeBehaviorStorage::new(common_weak.clone()),
-                                context,
+                                child_node_behavior: ChildNodeBehaviorStorage::new(common_weak.clone()),
+                                event_target_behavior: EventTargetBehaviorStorage::new(),
+                                registered_observers: Default::default(),
+                                owner_document: Default::default(),
+                                context: RwLock::new(context),
                             };
 
                             (common, contents)
@@ -124,8 +158,11 @@ macro_rules! impl_concrete {
                     }
 
                     proxy_node_behavior!();
+                    proxy_event_target_behavior!();
                 }
 
+                impl_event_target!(ConcreteNodeArc<[<$name Store>]>, common.event_target_behavior);
+
                 impl Buildable for ConcreteNodeArc<[<$name Store>]> {
                     type Storage = [<$name Store>];
                 }
@@ -205,9 +242,49 @@ macro_rules! impl_concrete {
                         $ti
                     }
 
+                    fn owner_document(&self) -> Option<DocumentNodeArc> {
+                        AnyNodeArc::from(self.clone()).owner_document()
+                    }
+
                     fn query_selector(&self, selector: &Selector) -> Result<Option<ElementNodeArc>, DomError> {
                         self.common.parent_node_behavior.query_selector(selector)
                     }
+
+                    fn normalize(&self) {
+                        AnyNodeArc::from(self.clone()).normalize()
+                    }
+
+                    fn is_same_node(&self, other: &AnyNodeArc) -> bool {
+                        AnyNodeArc::from(self.clone()).is_same_node(other)
+                    }
+
+                    fn is_equal_node(&self, other: &AnyNodeArc) -> bool {
+                        AnyNodeArc::from(self.clone()).is_equal_node(other)
+                    }
+
+                    fn compare_document_position(&self, other: &AnyNodeArc) -> u16 {
+                        AnyNodeArc::from(self.clone()).compare_document_position(other)
+                    }
+
+                    fn node_name(&self) -> String {
+                        AnyNodeArc::from(self.clone()).node_name()
+                    }
+
+                    fn node_value(&self) -> Option<String> {
+                        AnyNodeArc::from(self.clone()).node_value()
+                    }
+
+                    fn set_node_value(&self, value: String) {
+                        AnyNodeArc::from(self.clone()).set_node_value(value)
+                    }
+
+                    fn text_content(&self) -> Option<String> {
+                        AnyNodeArc::from(self.clone()).text_content()
+                    }
+
+                    fn set_text_content(&self, text: String) {
+                        AnyNodeArc::from(self.clone()).set_text_content(text)
+                    }
                 }
             )*
         }
@@ -228,38 +305,702 @@ impl_concrete! {
 
 impl_parent_node!(ConcreteNodeArc<ElementStore>, common.parent_node_behavior);
 impl_parent_node!(ConcreteNodeArc<DocumentStore>, common.parent_node_behavior);
+impl_parent_node!(
+    ConcreteNodeArc<DocumentFragmentStore>,
+    common.parent_node_behavior
+);
+
+impl_child_node!(ConcreteNodeArc<ElementStore>, common.child_node_behavior);
+impl_child_node!(ConcreteNodeArc<TextStore>, common.child_node_behavior);
+impl_child_node!(ConcreteNodeArc<CommentStore>, common.child_node_behavior);
+impl_child_node!(
+    ConcreteNodeArc<CDataSectionStore>,
+    common.child_node_behavior
+);
+impl_child_node!(
+    ConcreteNodeArc<ProcessingInstructionStore>,
+    common.child_node_behavior
+);
+impl_child_node!(
+    ConcreteNodeArc<DocumentTypeStore>,
+    common.child_node_behavior
+);
 
 impl ConcreteNodeArc<ElementStore> {
     proxy_parent_node_behavior!();
+    proxy_child_node_behavior!();
+    proxy_non_document_type_child_node_behavior!();
+}
+
+impl ConcreteNodeArc<AttributeStore> {
+    /// [Attr.value](https://developer.mozilla.org/en-US/docs/Web/API/Attr/value)
+    pub fn value(&self) -> String {
+        self.contents.value()
+    }
+
+    /// Sets [Attr.value](https://developer.mozilla.org/en-US/docs/Web/API/Attr/value), queueing
+    /// an "attributes" mutation record if the attribute belongs to an element
+    pub fn set_value(&self, value: String) {
+        match self
+            .contents
+            .owner_element()
+            .and_then(|owner| owner.upgrade())
+        {
+            Some(owner) => owner.contents.change_attribute(&self.contents, value),
+            None => *self.contents.value.write().unwrap() = value,
+        }
+    }
+}
+
+impl_character_data!(ConcreteNodeArc<TextStore>, contents.character_data);
+impl_character_data!(ConcreteNodeArc<CommentStore>, contents.character_data);
+impl_character_data!(ConcreteNodeArc<CDataSectionStore>, contents.character_data);
+impl_character_data!(
+    ConcreteNodeArc<ProcessingInstructionStore>,
+    contents.character_data
+);
+
+impl ConcreteNodeArc<TextStore> {
+    proxy_character_data_behavior!();
+    proxy_child_node_behavior!();
+    proxy_non_document_type_child_node_behavior!();
+
+    /// [Text.splitText](https://developer.mozilla.org/en-US/docs/Web/API/Text/splitText): moves
+    /// the data after `offset` to a new text node, which is inserted as the next sibling. Live
+    /// ranges after the offset move to the new node.
+    pub fn split_text(&self, offset: usize) -> Result<TextNodeArc, DomError> {
+        let length = self.length();
+        if offset > length {
+            return Err(DomError::Range);
+        }
+        let new_data = self.substring_data(offset, length - offset)?;
+        let new_node = TextNodeArc::new(self.get_context(), Arc::new(TextStore::new(new_data)));
+        if let Some(document) = self.common.owner_document() {
+            new_node.common.set_owner_document(&document);
+        }
+
+        if let Some(parent) = self.common.node_graph.parent_node() {
+            let next_sibling = self.common.node_graph.next_sibling();
+            parent
+                .common
+                .node_graph
+                .insert_before(new_node.clone().into(), next_sibling.as_ref());
+            range::text_split(
+                &self.clone().into(),
+                &new_node.clone().into(),
+                &parent,
+                offset,
+            );
+        }
+        self.delete_data(offset, length - offset)?;
+        Ok(new_node)
+    }
+
+    /// [Text.wholeText](https://developer.mozilla.org/en-US/docs/Web/API/Text/wholeText): the
+    /// data of the node and of the text and CDATA section siblings next to it
+    pub fn whole_text(&self) -> String {
+        let is_text = |node: &AnyNodeArc| {
+            matches!(
+                node.contents,
+                NodeContentsArc::Text(_) | NodeContentsArc::CDataSection(_)
+            )
+        };
+        let mut node: AnyNodeArc = self.clone().into();
+        while let Some(previous) = node.common.node_graph.previous_sibling().filter(is_text) {
+            node = previous;
+        }
+
+        let mut text = String::new();
+        let mut current = Some(node);
+        while let Some(node) = current.filter(is_text) {
+            if let Some(character_data) = node.contents.character_data() {
+                text.push_str(&character_data.data());
+            }
+            current = node.common.node_graph.next_sibling();
+        }
+        text
+    }
+}
+
+impl ConcreteNodeArc<CommentStore> {
+    proxy_character_data_behavior!();
+    proxy_child_node_behavior!();
+    proxy_non_document_type_child_node_behavior!();
+}
+
+impl ConcreteNodeArc<CDataSectionStore> {
+    proxy_character_data_behavior!();
+    proxy_child_node_behavior!();
+    proxy_non_document_type_child_node_behavior!();
+}
+
+impl ConcreteNodeArc<ProcessingInstructionStore> {
+    proxy_character_data_behavior!();
+    proxy_child_node_behavior!();
+    proxy_non_document_type_child_node_behavior!();
+
+    /// [ProcessingInstruction.target](https://developer.mozilla.org/en-US/docs/Web/API/ProcessingInstruction/target)
+    pub fn target(&self) -> String {
+        self.contents.target()
+    }
+}
+
+impl ConcreteNodeArc<DocumentTypeStore> {
+    proxy_child_node_behavior!();
+
+    /// [DocumentType.name](https://developer.mozilla.org/en-US/docs/Web/API/DocumentType/name)
+    pub fn name(&self) -> String {
+        self.contents.name()
+    }
+
+    /// [DocumentType.publicId](https://developer.mozilla.org/en-US/docs/Web/API/DocumentType/publicId)
+    pub fn public_id(&self) -> String {
+        self.contents.public_id()
+    }
+
+    /// [DocumentType.systemId](https://developer.mozilla.org/en-US/docs/Web/API/DocumentType/systemId)
+    pub fn system_id(&self) -> String {
+        self.contents.system_id()
+    }
+}
+
+impl ConcreteNodeArc<DocumentFragmentStore> {
+    proxy_parent_node_behavior!();
 }
 
 impl ConcreteNodeArc<DocumentStore> {
     proxy_parent_node_behavior!();
 
+    /// [Document.URL](https://developer.mozilla.org/en-US/docs/Web/API/Document/URL)
+    pub fn url(&self) -> String {
+        self.contents.url.read().unwrap().clone()
+    }
+
+    /// [Document.referrer](https://developer.mozilla.org/en-US/docs/Web/API/Document/referrer)
+    pub fn referrer(&self) -> String {
+        self.contents.referrer.clone()
+    }
+
+    /// [Document.cookie](https://developer.mozilla.org/en-US/docs/Web/API/Document/cookie): the
+    /// cookies of the document's URL which are visible to scripts. Documents without a window,
+    /// or whose URL is not http(s), have no cookies.
+    pub fn cookie(&self) -> String {
+        match self.cookie_context() {
+            Some((context, url)) => {
+                context
+                    .config()
+                    .cookie_jar()
+                    .cookie_string(&url, context.current_time(), false)
+            }
+            None => String::new(),
+        }
+    }
+
+    /// Sets [Document.cookie](https://developer.mozilla.org/en-US/docs/Web/API/Document/cookie):
+    /// stores one cookie, given as it would be in a Set-Cookie header
+    pub fn set_cookie(&self, cookie: String) {
+        if let Some((context, url)) = self.cookie_context() {
+            context
+                .config()
+                .cookie_jar()
+                .store(&url, &cookie, context.current_time(), false);
+        }
+    }
+
+    /// The sandbox and URL of a document which may use cookies, unless it is
+    /// [cookie-averse](https://html.spec.whatwg.org/multipage/dom.html#cookie-averse-document-object)
+    fn cookie_context(&self) -> Option<(Arc<Sandbox>, url::Url)> {
+        self.contents.default_view.upgrade()?;
+        let context = self.get_context().upgrade()?;
+        if !context.config().cookie_enabled() {
+            return None;
+        }
+        let url = url::Url::parse(&self.url()).ok()?;
+        match url.scheme() {
+            "http" | "https" => Some((context, url)),
+            _ => None,
+        }
+    }
+
+    /// Whether this is an [HTML document](https://dom.spec.whatwg.org/#html-document), as
+    /// opposed to an XML document
+    pub(crate) fn is_html_document(&self) -> bool {
+        self.contents.content_type == "text/html"
+    }
+
+    /// Makes a node which was just built belong to this document
+    fn owned<S: AnyNodeStore>(&self, node: ConcreteNodeArc<S>) -> ConcreteNodeArc<S> {
+        node.common.set_owner_document(self);
+        node
+    }
+
+    fn context(&self) -> Result<Arc<Sandbox>, DomError> {
+        self.get_context().upgrade().ok_or(DomError::SandboxDropped)
+    }
+
     /// Creates a new text node with the given text contents
     pub fn create_text_node(&self, text: String) -> Result<TextNodeArc, DomError> {
-        match self.get_context().upgrade() {
-            Some(context) => Ok(context
+        Ok(self.owned(
+            self.context()?
                 .builder::<TextNodeArc>()
-                .build(TextStore { data: text })),
-            None => Err(DomError::SandboxDropped),
+                .build(TextStore::new(text)),
+        ))
+    }
+
+    /// [Document.createComment](https://developer.mozilla.org/en-US/docs/Web/API/Document/createComment)
+    pub fn create_comment(&self, data: String) -> Result<CommentNodeArc, DomError> {
+        Ok(self.owned(
+            self.context()?
+                .builder::<CommentNodeArc>()
+                .build(CommentStore::new(data)),
+        ))
+    }
+
+    /// [Document.createDocumentFragment](https://developer.mozilla.org/en-US/docs/Web/API/Document/createDocumentFragment)
+    pub fn create_document_fragment(&self) -> Result<DocumentFragmentNodeArc, DomError> {
+        Ok(self.owned(
+            self.context()?
+                .builder::<DocumentFragmentNodeArc>()
+                .build(DocumentFragmentStore),
+        ))
+    }
+
+    /// [Document.createAttribute](https://developer.mozilla.org/en-US/docs/Web/API/Document/createAttribute):
+    /// creates an attribute which is not on any element. The name is lowercased in HTML
+    /// documents.
+    pub fn create_attribute(&self, local_name: String) -> Result<AttributeNodeArc, DomError> {
+        if !is_valid_name(&local_name) {
+            return Err(DomError::InvalidCharacter);
+        }
+        if self.is_html_document() {
+            Ok(self.owned(
+                self.context()?
+                    .builder::<AttributeNodeArc>()
+                    .build(AttributeStore::new(local_name, None)),
+            ))
+        } else {
+            Ok(self.owned(
+                self.context()?
+                    .builder::<AttributeNodeArc>()
+                    .build(AttributeStore::new_ns(None, local_name)),
+            ))
+        }
+    }
+
+    /// [Document.createAttributeNS](https://developer.mozilla.org/en-US/docs/Web/API/Document/createAttributeNS):
+    /// creates an attribute in `namespace`. The prefix of `qualified_name` must suit the
+    /// namespace, or this fails with `DomError::Namespace`.
+    pub fn create_attribute_ns(
+        &self,
+        namespace: Option<String>,
+        qualified_name: String,
+    ) -> Result<AttributeNodeArc, DomError> {
+        let namespace = validate_namespace(namespace, &qualified_name)?;
+        Ok(self.owned(
+            self.context()?
+                .builder::<AttributeNodeArc>()
+                .build(AttributeStore::new_ns(namespace, qualified_name)),
+        ))
+    }
+
+    /// [Document.createCDATASection](https://developer.mozilla.org/en-US/docs/Web/API/Document/createCDATASection).
+    /// HTML documents cannot have CDATA sections, and the data cannot contain "]]>".
+    pub fn create_cdata_section(&self, data: String) -> Result<CDataSectionNodeArc, DomError> {
+        if self.is_html_document() {
+            return Err(DomError::NotSupported);
+        }
+        if data.contains("]]>") {
+            return Err(DomError::InvalidCharacter);
         }
+        Ok(self.owned(
+            self.context()?
+                .builder::<CDataSectionNodeArc>()
+                .build(CDataSectionStore::new(data)),
+        ))
+    }
+
+    /// [Document.createProcessingInstruction](https://developer.mozilla.org/en-US/docs/Web/API/Document/createProcessingInstruction).
+    /// The target must be a valid name, and the data cannot contain "?>".
+    pub fn create_processing_instruction(
+        &self,
+        target: String,
+        data: String,
+    ) -> Result<ProcessingInstructionNodeArc, DomError> {
+        if !is_valid_name(&target) || data.contains("?>") {
+            return Err(DomError::InvalidCharacter);
+        }
+        Ok(self.owned(
+            self.context()?
+                .builder::<ProcessingInstructionNodeArc>()
+                .build(ProcessingInstructionStore::new(target, data)),
+        ))
+    }
+
+    /// [Document.createEvent](https://developer.mozilla.org/en-US/docs/Web/API/Document/createEvent):
+    /// creates an event of the given interface, e.g. "MouseEvent", which must be initialized
+    /// with `Event::init_event` before it is dispatched. Unknown interfaces fail with
+    /// `DomError::NotSupported`.
+    pub fn create_event(&self, interface: String) -> Result<Event, DomError> {
+        let kind = match interface.to_ascii_lowercase().as_ref() {
+            "event" | "events" | "htmlevents" | "svgevents" | "uievent" | "uievents" => {
+                EventKind::Basic
+            }
+            "mouseevent" | "mouseevents" => EventKind::Mouse(Default::default()),
+            "keyboardevent" => EventKind::Keyboard(Default::default()),
+            "focusevent" => EventKind::Focus(Default::default()),
+            "hashchangeevent" => EventKind::HashChange(Default::default()),
+            "storageevent" => EventKind::Storage(Default::default()),
+            _ => return Err(DomError::NotSupported),
+        };
+        Ok(Event::uninitialized(kind))
+    }
+
+    /// [Document.createRange](https://developer.mozilla.org/en-US/docs/Web/API/Document/createRange):
+    /// creates a range collapsed at the start of the document
+    pub fn create_range(&self) -> Arc<Range> {
+        Range::new(self.get_context(), self.clone().into())
+    }
+
+    /// [Document.createTreeWalker](https://developer.mozilla.org/en-US/docs/Web/API/Document/createTreeWalker):
+    /// creates a walker over the subtree of `root`, visiting the node types in `what_to_show`
+    /// (see the `SHOW_` constants in [crate::tree_walker]) which `filter` accepts
+    pub fn create_tree_walker(
+        &self,
+        root: AnyNodeArc,
+        what_to_show: u32,
+        filter: Option<Arc<dyn NodeFilter>>,
+    ) -> TreeWalker {
+        TreeWalker::new(self.get_context(), root, what_to_show, filter)
     }
 
     /// Creates an HTML element with the given tag name
     pub fn create_element(&self, tag_name: String) -> Result<ElementNodeArc, DomError> {
-        let context = self
-            .get_context()
-            .upgrade()
-            .ok_or(DomError::SandboxDropped)?;
-
+        let context = self.context()?;
         let builder = context.builder::<ElementNodeArc>();
 
-        Ok(match tag_name.to_lowercase().as_ref() {
+        let element = match tag_name.to_lowercase().as_ref() {
             "html" => builder.build_html(),
+            "head" => builder.build_head(),
+            "title" => builder.build_title(),
             "body" => builder.build_body(),
             "button" => builder.build_button(),
+            "input" => builder.build_input(),
+            "textarea" => builder.build_textarea(),
+            "select" => builder.build_select(),
+            "option" => builder.build_option(),
+            "form" => builder.build_form(),
+            "label" => builder.build_label(),
+            "a" => builder.build_anchor(),
+            "img" => builder.build_image(),
+            "script" => builder.build_script(),
             _ => builder.build_unknown(tag_name),
-        })
+        };
+        Ok(self.owned(element))
+    }
+
+    /// [Document.createElementNS](https://developer.mozilla.org/en-US/docs/Web/API/Document/createElementNS):
+    /// creates an element in `namespace`. The prefix of `qualified_name` must suit the
+    /// namespace, or this fails with `DomError::Namespace`.
+    pub fn create_element_ns(
+        &self,
+        namespace: Option<String>,
+        qualified_name: String,
+    ) -> Result<ElementNodeArc, DomError> {
+        let namespace = validate_namespace(namespace, &qualified_name)?;
+        if namespace.as_deref() == Some(HTML_NAMESPACE) && !qualified_name.contains(':') {
+            return self.create_element(qualified_name);
+        }
+        Ok(self.owned(
+            self.context()?
+                .builder::<ElementNodeArc>()
+                .build_ns(namespace, qualified_name),
+        ))
+    }
+
+    /// [Document.documentElement](https://developer.mozilla.org/en-US/docs/Web/API/Document/documentElement):
+    /// the element which is a child of the document
+    pub fn document_element(&self) -> Option<ElementNodeArc> {
+        self.common
+            .node_graph
+            .static_child_nodes()
+            .into_iter()
+            .find_map(|child| ElementNodeArc::try_from(child).ok())
+    }
+
+    /// The first child of the html document element which `matches`
+    fn html_child(&self, matches: impl Fn(&HtmlElementStore) -> bool) -> Option<ElementNodeArc> {
+        let root = self.document_element()?;
+        if !matches!(
+            root.contents.html_element(),
+            Some(HtmlElementStore::HtmlHtml(_))
+        ) {
+            return None;
+        }
+        root.common
+            .node_graph
+            .static_child_nodes()
+            .into_iter()
+            .filter_map(|child| ElementNodeArc::try_from(child).ok())
+            .find(|element| element.contents.html_element().is_some_and(&matches))
+    }
+
+    /// [Document.head](https://developer.mozilla.org/en-US/docs/Web/API/Document/head)
+    pub fn head(&self) -> Option<ElementNodeArc> {
+        self.html_child(|element| matches!(element, HtmlElementStore::HtmlHead(_)))
+    }
+
+    /// [Document.body](https://developer.mozilla.org/en-US/docs/Web/API/Document/body): the
+    /// body or frameset element which is a child of the html element
+    pub fn body(&self) -> Option<ElementNodeArc> {
+        self.html_child(is_body_or_frameset)
+    }
+
+    /// Sets [Document.body](https://developer.mozilla.org/en-US/docs/Web/API/Document/body),
+    /// replacing the current body if there is one. Fails with `DomError::HierarchyRequest` if
+    /// `body` is not a body or frameset element, or if there is no document element to put it
+    /// in.
+    pub fn set_body(&self, body: ElementNodeArc) -> Result<(), DomError> {
+        if !body
+            .contents
+            .html_element()
+            .is_some_and(is_body_or_frameset)
+        {
+            return Err(DomError::HierarchyRequest);
+        }
+        match self.body() {
+            Some(old_body) if old_body == body => {}
+            Some(old_body) => {
+                let parent = old_body
+                    .common
+                    .node_graph
+                    .parent_node()
+                    .ok_or(DomError::HierarchyRequest)?;
+                parent
+                    .common
+                    .node_graph
+                    .replace_child(body.into(), &old_body.into());
+            }
+            None => {
+                let root = self.document_element().ok_or(DomError::HierarchyRequest)?;
+                root.append_child(body.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// The first title element in the document
+    pub(crate) fn title_element(&self) -> Option<ElementNodeArc> {
+        self.common
+            .node_graph
+            .descendants()
+            .into_iter()
+            .filter_map(|node| ElementNodeArc::try_from(node).ok())
+            .find(|element| {
+                matches!(
+                    element.contents.html_element(),
+                    Some(HtmlElementStore::HtmlTitle(_))
+                )
+            })
+    }
+
+    /// [Document.title](https://developer.mozilla.org/en-US/docs/Web/API/Document/title): the
+    /// text of the title element, with whitespace collapsed
+    pub fn title(&self) -> String {
+        let title = match self.title_element() {
+            Some(title) => title,
+            None => return String::new(),
+        };
+        let text: String = title
+            .common
+            .node_graph
+            .static_child_nodes()
+            .into_iter()
+            .filter_map(|child| TextNodeArc::try_from(child).ok())
+            .filter_map(|text| text.contents.data())
+            .collect();
+        text.split_ascii_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// Sets [Document.title](https://developer.mozilla.org/en-US/docs/Web/API/Document/title):
+    /// replaces the text of the title element, creating one in the head if there is none
+    pub fn set_title(&self, title: String) -> Result<(), DomError> {
+        let title_element = match self.title_element() {
+            Some(title_element) => title_element,
+            None => match self.head() {
+                Some(head) => {
+                    let title_element = self.create_element("title".to_owned())?;
+                    head.append_child(title_element.clone().into());
+                    title_element
+                }
+                None => return Ok(()),
+            },
+        };
+        // String replace all, queueing a single mutation record
+        title_element.set_text_content(title);
+        Ok(())
+    }
+
+    /// A live list of the document's descendant elements which match `filter`
+    fn element_list(&self, filter: ElementFilter) -> Arc<NodeList> {
+        NodeList::new(
+            self.get_context(),
+            NodeListStorage::Live(Query::Elements {
+                root: self.clone().into(),
+                filter,
+            }),
+        )
+    }
+
+    /// [Document.forms](https://developer.mozilla.org/en-US/docs/Web/API/Document/forms), as a
+    /// live list
+    pub fn forms(&self) -> Arc<NodeList> {
+        self.element_list(ElementFilter::Forms)
+    }
+
+    /// [Document.images](https://developer.mozilla.org/en-US/docs/Web/API/Document/images), as
+    /// a live list
+    pub fn images(&self) -> Arc<NodeList> {
+        self.element_list(ElementFilter::Images)
+    }
+
+    /// [Document.links](https://developer.mozilla.org/en-US/docs/Web/API/Document/links): the
+    /// a and area elements with an href attribute, as a live list
+    pub fn links(&self) -> Arc<NodeList> {
+        self.element_list(ElementFilter::Links)
+    }
+
+    /// [Document.scripts](https://developer.mozilla.org/en-US/docs/Web/API/Document/scripts),
+    /// as a live list
+    pub fn scripts(&self) -> Arc<NodeList> {
+        self.element_list(ElementFilter::Scripts)
+    }
+
+    /// [Document.getElementsByName](https://developer.mozilla.org/en-US/docs/Web/API/Document/getElementsByName):
+    /// the elements whose name attribute is `name`, as a live list
+    pub fn get_elements_by_name(&self, name: String) -> Arc<NodeList> {
+        self.element_list(ElementFilter::Name(name))
+    }
+
+    /// [Document.doctype](https://developer.mozilla.org/en-US/docs/Web/API/Document/doctype)
+    pub fn doctype(&self) -> Option<DocumentTypeNodeArc> {
+        self.common
+            .node_graph
+            .static_child_nodes()
+            .into_iter()
+            .find_map(|child| DocumentTypeNodeArc::try_from(child).ok())
+    }
+
+    /// The document's [mode](https://dom.spec.whatwg.org/#concept-document-mode). HTML documents
+    /// take it from a doctype inserted before their document element.
+    pub fn mode(&self) -> QuirksMode {
+        *self.contents.mode.read().unwrap()
+    }
+
+    /// [Document.compatMode](https://developer.mozilla.org/en-US/docs/Web/API/Document/compatMode):
+    /// "BackCompat" in quirks mode, otherwise "CSS1Compat"
+    pub fn compat_mode(&self) -> String {
+        match self.mode() {
+            QuirksMode::Quirks => "BackCompat",
+            QuirksMode::NoQuirks | QuirksMode::LimitedQuirks => "CSS1Compat",
+        }
+        .to_owned()
+    }
+
+    /// [Document.contentType](https://developer.mozilla.org/en-US/docs/Web/API/Document/contentType)
+    pub fn content_type(&self) -> String {
+        self.contents.content_type.clone()
+    }
+
+    /// [Document.implementation](https://developer.mozilla.org/en-US/docs/Web/API/Document/implementation):
+    /// creates further documents in the document's sandbox
+    pub fn implementation(&self) -> DomImplementation {
+        DomImplementation::new(self.get_context())
+    }
+
+    /// [Document.importNode](https://developer.mozilla.org/en-US/docs/Web/API/Document/importNode):
+    /// gives a copy of `node`, with its descendants if `deep` is set, which belongs to this
+    /// document. `node` may come from another sandbox. Documents cannot be imported.
+    pub fn import_node(&self, node: &AnyNodeArc, deep: bool) -> Result<AnyNodeArc, DomError> {
+        if let NodeContentsArc::Document(_) = node.contents {
+            return Err(DomError::NotSupported);
+        }
+        Ok(adoption::clone_node(node, self, deep))
+    }
+
+    /// [Document.adoptNode](https://developer.mozilla.org/en-US/docs/Web/API/Document/adoptNode):
+    /// removes `node` from its parent, and moves it and its descendants into this document and
+    /// its sandbox. Documents cannot be adopted.
+    pub fn adopt_node(&self, node: &AnyNodeArc) -> Result<AnyNodeArc, DomError> {
+        if let NodeContentsArc::Document(_) = node.contents {
+            return Err(DomError::NotSupported);
+        }
+        adoption::adopt_node(node, self);
+        Ok(node.clone())
+    }
+}
+
+/// Checks whether an element may be the [body](https://html.spec.whatwg.org/multipage/dom.html#the-body-element-2)
+/// of a document
+fn is_body_or_frameset(element: &HtmlElementStore) -> bool {
+    matches!(element, HtmlElementStore::HtmlBody(_))
+        || element.tag_name().eq_ignore_ascii_case("frameset")
+}
+
+/// The [XML namespace](https://infra.spec.whatwg.org/#xml-namespace)
+const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// The [XMLNS namespace](https://infra.spec.whatwg.org/#xmlns-namespace)
+const XMLNS_NAMESPACE: &str = "http://www.w3.org/2000/xmlns/";
+
+/// Checks whether `name` is an XML [Name](https://www.w3.org/TR/xml/#NT-Name)
+pub(crate) fn is_valid_name(name: &str) -> bool {
+    let is_start = |c: char| c.is_ascii_alphabetic() || c == '_' || c == ':' || !c.is_ascii();
+    let is_rest = |c: char| is_start(c) || c.is_ascii_digit() || c == '-' || c == '.';
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => is_start(first) && chars.all(is_rest),
+        None => false,
+    }
+}
+
+/// [Validates](https://dom.spec.whatwg.org/#validate) a qualified name, i.e. a name with at most
+/// one prefix, and gives its prefix
+pub(crate) fn validate_qualified_name(qualified_name: &str) -> Result<Option<&str>, DomError> {
+    let prefix = match qualified_name.split_once(':') {
+        Some((prefix, local_name)) => {
+            if prefix.is_empty() || local_name.is_empty() || local_name.contains(':') {
+                return Err(DomError::InvalidCharacter);
+            }
+            Some(prefix)
+        }
+        None => None,
+    };
+    if is_valid_name(qualified_name) {
+        Ok(prefix)
+    } else {
+        Err(DomError::InvalidCharacter)
+    }
+}
+
+/// [Validates](https://dom.spec.whatwg.org/#validate-and-extract) a qualified name in
+/// `namespace`, and gives the namespace, with the empty string meaning none
+pub(crate) fn validate_namespace(
+    namespace: Option<String>,
+    qualified_name: &str,
+) -> Result<Option<String>, DomError> {
+    let namespace = namespace.filter(|namespace| !namespace.is_empty());
+    let prefix = validate_qualified_name(qualified_name)?;
+
+    let is_xmlns = qualified_name == "xmlns" || prefix == Some("xmlns");
+    let valid = match namespace.as_deref() {
+        None => prefix.is_none() && !is_xmlns,
+        Some(XMLNS_NAMESPACE) => is_xmlns,
+        Some(XML_NAMESPACE) => !is_xmlns,
+        Some(_) => prefix != Some("xml") && !is_xmlns,
+    };
+    if valid {
+        Ok(namespace)
+    } else {
+        Err(DomError::Namespace)
     }
 }
@@ -1,11 +1,13 @@
 //! Data and functionality specific to each node type live here.
 
 use super::concrete::*;
+use crate::behavior::character_data_prelude::CharacterDataBehaviorStorage;
 use crate::internal_prelude::*;
 use crate::sandbox::Builder;
 use crate::window::Window;
 
 use super::element::ElementStore;
+use super::quirks::QuirksMode;
 use quote::quote;
 use std::fmt;
 use std::sync::RwLock;
@@ -165,25 +167,59 @@ macro_rules! impl_standard_builder {
 }
 
 /// Storage type for DocumentNode
-#[derive(Default, Clone)]
+#[derive(Clone)]
 pub struct DocumentStore {
     /// Pointer back up to the window
     pub(crate) default_view: Weak<Window>,
+
+    /// The element which currently has focus, if any
+    pub(crate) focused_element: Arc<RwLock<Option<ElementNodeWeak>>>,
+
+    /// The document's URL
+    pub(crate) url: Arc<RwLock<String>>,
+
+    /// The URL of the page which linked to the document, or the empty string
+    pub(crate) referrer: String,
+
+    /// The [content type](https://dom.spec.whatwg.org/#concept-document-content-type) of the
+    /// document
+    pub(crate) content_type: String,
+
+    /// The document's mode, which its doctype determines
+    pub(crate) mode: Arc<RwLock<QuirksMode>>,
+}
+
+impl Default for DocumentStore {
+    fn default() -> DocumentStore {
+        DocumentStore {
+            default_view: Default::default(),
+            focused_element: Default::default(),
+            url: Default::default(),
+            referrer: Default::default(),
+            content_type: "text/html".to_owned(),
+            mode: Default::default(),
+        }
+    }
 }
 
 /// Storage type for TextNode
 #[derive(Default, Clone)]
 pub struct TextStore {
     /// Text in the text node
-    pub(crate) data: String,
+    pub(crate) character_data: CharacterDataBehaviorStorage,
 }
 
 impl TextStore {
-    // TODO data should come from CharacterData
+    /// Creates the storage for a text node with the given text
+    pub fn new(data: String) -> TextStore {
+        TextStore {
+            character_data: CharacterDataBehaviorStorage::new(data),
+        }
+    }
 
     /// Gives the text contents of the text node
     pub fn data(&self) -> Option<String> {
-        Some(self.data.clone())
+        Some(self.character_data.data())
     }
 }
 
@@ -191,25 +227,34 @@ impl TextStore {
 #[derive(Default, Clone)]
 pub struct CommentStore {
     /// Text in the comment node
-    pub(crate) data: String,
+    pub(crate) character_data: CharacterDataBehaviorStorage,
 }
 
 impl CommentStore {
-    // TODO data should come from CharacterData
+    /// Creates the storage for a comment node with the given text
+    pub fn new(data: String) -> CommentStore {
+        CommentStore {
+            character_data: CharacterDataBehaviorStorage::new(data),
+        }
+    }
 
-    /// Gives the text contents of the text node
+    /// Gives the text contents of the comment node
     pub fn data(&self) -> Option<String> {
-        Some(self.data.clone())
+        Some(self.character_data.data())
     }
 }
 
 /// Storage type for AttributeNode
 #[derive(Default, Clone)]
 pub struct AttributeStore {
-    /// Name of the attribute, stored as lowercase.
+    /// Qualified name of the attribute. Attributes created without a namespace have their
+    /// name stored as lowercase.
     /// Read-only
     name: String,
 
+    /// Namespace of the attribute, if it was created with one
+    namespace: Option<String>,
+
     /// Value of the attribute
     pub(crate) value: Arc<RwLock<String>>,
 
@@ -221,11 +266,32 @@ impl AttributeStore {
     pub(crate) fn new(name: String, owner_element: Option<ElementNodeWeak>) -> AttributeStore {
         AttributeStore {
             name: name.to_ascii_lowercase(),
+            namespace: None,
             value: Arc::new(RwLock::new("".to_owned())),
             owner_element: Arc::new(RwLock::new(owner_element)),
         }
     }
 
+    /// Creates the storage for an attribute in `namespace`, keeping the case of its name
+    pub(crate) fn new_ns(namespace: Option<String>, qualified_name: String) -> AttributeStore {
+        AttributeStore {
+            name: qualified_name,
+            namespace,
+            ..Default::default()
+        }
+    }
+
+    /// Copies the name and value of the attribute into storage for a new attribute node,
+    /// which has no owner element
+    pub(crate) fn copy(&self) -> AttributeStore {
+        AttributeStore {
+            name: self.name.clone(),
+            namespace: self.namespace.clone(),
+            value: Arc::new(RwLock::new(self.value())),
+            owner_element: Default::default(),
+        }
+    }
+
     pub(crate) fn owner_element(&self) -> Option<ElementNodeWeak> {
         self.owner_element.read().unwrap().clone()
     }
@@ -243,19 +309,121 @@ impl AttributeStore {
     pub fn name(&self) -> String {
         self.name.clone()
     }
+
+    /// Gives the namespace of the attribute, if any
+    pub fn namespace_uri(&self) -> Option<String> {
+        self.namespace.clone()
+    }
+
+    /// Gives the prefix of the attribute's name, if any
+    pub fn prefix(&self) -> Option<String> {
+        self.name
+            .split_once(':')
+            .map(|(prefix, _)| prefix.to_owned())
+    }
+
+    /// Gives the attribute's name without its prefix
+    pub fn local_name(&self) -> String {
+        match self.name.split_once(':') {
+            Some((_, local_name)) => local_name.to_owned(),
+            None => self.name.clone(),
+        }
+    }
 }
 
 /// Storage type for CDataSectionNode
 #[derive(Default, Clone)]
-pub struct CDataSectionStore;
+pub struct CDataSectionStore {
+    /// Text in the CDATA section
+    pub(crate) character_data: CharacterDataBehaviorStorage,
+}
+
+impl CDataSectionStore {
+    /// Creates the storage for a CDATA section with the given text
+    pub fn new(data: String) -> CDataSectionStore {
+        CDataSectionStore {
+            character_data: CharacterDataBehaviorStorage::new(data),
+        }
+    }
+
+    /// Gives the text contents of the CDATA section
+    pub fn data(&self) -> Option<String> {
+        Some(self.character_data.data())
+    }
+}
 
 /// Storage type for ProcessingInstructionNode
 #[derive(Default, Clone)]
-pub struct ProcessingInstructionStore;
+pub struct ProcessingInstructionStore {
+    /// The application the instruction is for.
+    /// Read-only
+    target: String,
+
+    /// The contents of the instruction
+    pub(crate) character_data: CharacterDataBehaviorStorage,
+}
+
+impl ProcessingInstructionStore {
+    /// Creates the storage for a processing instruction with the given target and contents
+    pub fn new(target: String, data: String) -> ProcessingInstructionStore {
+        ProcessingInstructionStore {
+            target,
+            character_data: CharacterDataBehaviorStorage::new(data),
+        }
+    }
+
+    /// Gives the target of the processing instruction
+    pub fn target(&self) -> String {
+        self.target.clone()
+    }
+
+    /// Gives the contents of the processing instruction
+    pub fn data(&self) -> Option<String> {
+        Some(self.character_data.data())
+    }
+}
 
 /// Storage type for DocumentTypeNode
 #[derive(Default, Clone)]
-pub struct DocumentTypeStore;
+pub struct DocumentTypeStore {
+    /// The name of the document type, e.g. "html".
+    /// Read-only
+    pub(crate) name: String,
+
+    /// The public identifier, or the empty string.
+    /// Read-only
+    pub(crate) public_id: String,
+
+    /// The system identifier, or the empty string.
+    /// Read-only
+    pub(crate) system_id: String,
+}
+
+impl DocumentTypeStore {
+    /// Creates the storage for a document type with the given name and identifiers
+    pub fn new(name: String, public_id: String, system_id: String) -> DocumentTypeStore {
+        DocumentTypeStore {
+            name,
+            public_id,
+            system_id,
+        }
+    }
+
+    /// Gives the name of the document type
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// Gives the public identifier of the document type
+    pub fn public_id(&self) -> String {
+        self.public_id.clone()
+    }
+
+    /// Gives the system identifier of the document type
+    pub fn system_id(&self) -> String {
+        self.system_id.clone()
+    }
+}
 
 /// Storage type for DocumentFragmentNode
 #[derive(Default, Clone)]
@@ -283,3 +451,16 @@ impl_standard_builder! {
     DocumentType,
     DocumentFragment
 }
+
+impl NodeContentsArc {
+    /// The text of a text, comment, CDATA section or processing instruction node
+    pub(crate) fn character_data(&self) -> Option<&CharacterDataBehaviorStorage> {
+        match self {
+            NodeContentsArc::Text(store) => Some(&store.character_data),
+            NodeContentsArc::Comment(store) => Some(&store.character_data),
+            NodeContentsArc::CDataSection(store) => Some(&store.character_data),
+            NodeContentsArc::ProcessingInstruction(store) => Some(&store.character_data),
+            _ => None,
+        }
+    }
+}
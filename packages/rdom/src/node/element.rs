@@ -1,6 +1,9 @@
 //! Data and functionality to each element type live here.
 
-use super::concrete::{ConcreteNodeArc, ElementNodeArc, ElementNodeWeak};
+use super::concrete::{AttributeNodeArc, ConcreteNodeArc, ElementNodeArc, ElementNodeWeak};
+use super::contents::{AttributeStore, NodeContentsArc};
+use super::form::radio_group;
+use super::inner_text;
 use crate::sandbox::Builder;
 use crate::{internal_prelude::*, named_node_map::NamedNodeMap};
 use std::sync::RwLock;
@@ -40,12 +43,18 @@ macro_rules! declare_html_elements {
 #[derive(Clone)]
 pub enum SvgElementStore {}
 
+/// The [HTML namespace](https://infra.spec.whatwg.org/#html-namespace)
+pub(crate) const HTML_NAMESPACE: &str = "http://www.w3.org/1999/xhtml";
+
 /// Data common to all elements
 #[derive(Clone)]
 pub struct ElementStore {
     /// Data specific to this particular element
     node_store: ElementKind,
 
+    /// Namespace of the element, if any
+    namespace: Option<String>,
+
     /// Reference back up to the DOM node
     pub(crate) node: AnyNodeWeak,
 
@@ -58,9 +67,20 @@ impl ElementStore {
         node_store: ElementKind,
         context: Weak<Sandbox>,
         node: AnyNodeWeak,
+    ) -> ElementStore {
+        ElementStore::new_ns(node_store, Some(HTML_NAMESPACE.to_owned()), context, node)
+    }
+
+    /// Creates the storage for an element in `namespace`
+    pub(crate) fn new_ns(
+        node_store: ElementKind,
+        namespace: Option<String>,
+        context: Weak<Sandbox>,
+        node: AnyNodeWeak,
     ) -> ElementStore {
         ElementStore {
             node_store,
+            namespace,
             attrs: Arc::new(RwLock::new(NamedNodeMap::new(
                 context,
                 node.clone()
@@ -76,6 +96,11 @@ impl ElementStore {
         self.node_store.tag_name()
     }
 
+    /// [Element.namespaceURI](https://developer.mozilla.org/en-US/docs/Web/API/Element/namespaceURI)
+    pub fn namespace_uri(&self) -> Option<String> {
+        self.namespace.clone()
+    }
+
     /// [Element.hasAttribute](https://developer.mozilla.org/en-US/docs/Web/API/Element/hasAttribute)
     pub fn has_attribute(&self, attr_name: String) -> bool {
         self.attrs
@@ -94,6 +119,45 @@ impl ElementStore {
             .map(|item| item.contents.value.read().unwrap().clone())
     }
 
+    /// [Element.setAttribute](https://developer.mozilla.org/en-US/docs/Web/API/Element/setAttribute)
+    pub fn set_attribute(&self, attr_name: String, value: String) -> Result<(), DomError> {
+        let mut attrs = self
+            .attrs
+            .write()
+            .expect("Could not lock attributes for writing");
+
+        if let Some(existing) = attrs.get_named_item(attr_name.clone()) {
+            attrs.queue_attribute_record(existing.contents.name(), Some(existing.contents.value()));
+            *existing.contents.value.write().unwrap() = value;
+            return Ok(());
+        }
+
+        let context = attrs
+            .get_context()
+            .upgrade()
+            .ok_or(DomError::SandboxDropped)?;
+        let attr = context
+            .builder::<AttributeNodeArc>()
+            .build(AttributeStore::new(attr_name, None));
+        *attr.contents.value.write().unwrap() = value;
+        if let Some(document) = self
+            .node
+            .upgrade()
+            .and_then(|node| node.common.owner_document())
+        {
+            attr.common.set_owner_document(&document);
+        }
+        attrs.set_named_item(attr).map(|_| ())
+    }
+
+    /// Sets the value of `attribute`, one of the element's attributes, queueing an "attributes"
+    /// mutation record
+    pub(crate) fn change_attribute(&self, attribute: &AttributeStore, value: String) {
+        let attrs = self.attrs.read().unwrap();
+        attrs.queue_attribute_record(attribute.name(), Some(attribute.value()));
+        *attribute.value.write().unwrap() = value;
+    }
+
     /// [Element.removeAttribute](https://developer.mozilla.org/en-US/docs/Web/API/Element/removeAttribute)
     pub fn remove_attribute(&self, attr_name: String) -> Result<(), DomError> {
         self.attrs
@@ -102,6 +166,37 @@ impl ElementStore {
             .remove_named_item(attr_name)
             .map(|_| ())
     }
+
+    /// Adds `attribute` to the element's attributes, replacing one of the same name
+    pub(crate) fn set_attribute_node(
+        &self,
+        attribute: AttributeNodeArc,
+    ) -> Result<Option<AttributeNodeArc>, DomError> {
+        self.attrs.write().unwrap().set_named_item(attribute)
+    }
+
+    /// Gives the attribute nodes of the element, in order
+    pub(crate) fn attributes(&self) -> Vec<AttributeNodeArc> {
+        self.attrs.read().unwrap().attribute_list.clone()
+    }
+
+    /// Moves the element's attributes into another sandbox, when the element is adopted
+    pub(crate) fn set_attributes_context(&self, context: Weak<Sandbox>) {
+        self.attrs.write().unwrap().context = context;
+    }
+
+    /// Gives the data of the element for a clone of it
+    pub(crate) fn clone_kind(&self) -> ElementKind {
+        self.node_store.copy()
+    }
+
+    /// Gives the HTML-specific storage of the element, if it is an HTML element
+    pub fn html_element(&self) -> Option<&HtmlElementStore> {
+        match &self.node_store {
+            ElementKind::HtmlElement(store) => Some(store),
+            ElementKind::SvgElement(_) => None,
+        }
+    }
 }
 
 impl ConcreteNodeArc<ElementStore> {
@@ -115,10 +210,229 @@ impl ConcreteNodeArc<ElementStore> {
         self.contents.get_attribute(attr_name)
     }
 
+    /// [Element.setAttribute](https://developer.mozilla.org/en-US/docs/Web/API/Element/setAttribute)
+    pub fn set_attribute(&self, attr_name: String, value: String) -> Result<(), DomError> {
+        self.contents.set_attribute(attr_name, value)
+    }
+
     /// [Element.removeAttribute](https://developer.mozilla.org/en-US/docs/Web/API/Element/removeAttribute)
-    pub fn remove_attribute(&mut self, attr_name: String) -> Result<(), DomError> {
+    pub fn remove_attribute(&self, attr_name: String) -> Result<(), DomError> {
         self.contents.remove_attribute(attr_name)
     }
+
+    /// [HTMLElement.innerText](https://developer.mozilla.org/en-US/docs/Web/API/HTMLElement/innerText):
+    /// the text as it would be rendered, with blocks on their own lines and `<br>` as a line
+    /// break. Without a layout, this assumes every element has its default display.
+    pub fn inner_text(&self) -> String {
+        inner_text::inner_text(self)
+    }
+
+    /// Whether the element is a form control which has the `disabled` attribute
+    pub fn disabled(&self) -> bool {
+        match self.contents.html_element() {
+            Some(HtmlElementStore::HtmlButton(_))
+            | Some(HtmlElementStore::HtmlInput(_))
+            | Some(HtmlElementStore::HtmlSelect(_))
+            | Some(HtmlElementStore::HtmlTextArea(_))
+            | Some(HtmlElementStore::HtmlOption(_)) => self.has_attribute("disabled".to_owned()),
+            _ => false,
+        }
+    }
+
+    /// The current value of an input, textarea or select element.
+    /// See [HTMLInputElement.value](https://developer.mozilla.org/en-US/docs/Web/API/HTMLInputElement/value)
+    pub fn value(&self) -> Result<String, DomError> {
+        match self.contents.html_element() {
+            Some(HtmlElementStore::HtmlInput(store)) => Ok(store
+                .value
+                .read()
+                .unwrap()
+                .clone()
+                .or_else(|| self.get_attribute("value".to_owned()))
+                .unwrap_or_default()),
+            Some(HtmlElementStore::HtmlTextArea(store)) => Ok(store
+                .value
+                .read()
+                .unwrap()
+                .clone()
+                .unwrap_or_else(|| descendant_text(&self.clone().into()))),
+            Some(HtmlElementStore::HtmlOption(_)) => Ok(option_value(self)),
+            Some(HtmlElementStore::HtmlSelect(_)) => Ok(self
+                .options()
+                .into_iter()
+                .find(|option| option.selected().unwrap_or(false))
+                .map(|option| option_value(&option))
+                .unwrap_or_default()),
+            _ => Err(DomError::InvalidState),
+        }
+    }
+
+    /// Sets the current value of an input, textarea or select element. For a select element,
+    /// this selects the first option having the given value.
+    pub fn set_value(&self, value: String) -> Result<(), DomError> {
+        match self.contents.html_element() {
+            Some(HtmlElementStore::HtmlInput(store)) => {
+                *store.value.write().unwrap() = Some(value);
+                Ok(())
+            }
+            Some(HtmlElementStore::HtmlTextArea(store)) => {
+                *store.value.write().unwrap() = Some(value);
+                Ok(())
+            }
+            Some(HtmlElementStore::HtmlOption(_)) => self.set_attribute("value".to_owned(), value),
+            Some(HtmlElementStore::HtmlSelect(_)) => {
+                let mut found = false;
+                for option in self.options() {
+                    let is_match = !found && option_value(&option) == value;
+                    found |= is_match;
+                    option.set_selected(is_match)?;
+                }
+                Ok(())
+            }
+            _ => Err(DomError::InvalidState),
+        }
+    }
+
+    /// The checkedness of an input element.
+    /// See [HTMLInputElement.checked](https://developer.mozilla.org/en-US/docs/Web/API/HTMLInputElement/checked)
+    pub fn checked(&self) -> Result<bool, DomError> {
+        match self.contents.html_element() {
+            Some(HtmlElementStore::HtmlInput(store)) => Ok(store
+                .checked
+                .read()
+                .unwrap()
+                .unwrap_or_else(|| self.has_attribute("checked".to_owned()))),
+            _ => Err(DomError::InvalidState),
+        }
+    }
+
+    /// Sets the checkedness of an input element. Checking a radio button unchecks the other
+    /// radio buttons in its group.
+    pub fn set_checked(&self, checked: bool) -> Result<(), DomError> {
+        match self.contents.html_element() {
+            Some(HtmlElementStore::HtmlInput(store)) => {
+                if checked && self.input_type() == "radio" {
+                    for other in radio_group(self) {
+                        other.set_checked(false)?;
+                    }
+                }
+                *store.checked.write().unwrap() = Some(checked);
+                Ok(())
+            }
+            _ => Err(DomError::InvalidState),
+        }
+    }
+
+    /// The lowercased type of an input element (defaulting to "text"), or of a button element
+    /// (defaulting to "submit"). Missing and invalid types give the default. Empty for other
+    /// elements.
+    pub(crate) fn input_type(&self) -> String {
+        let (keywords, default) = match self.contents.html_element() {
+            Some(HtmlElementStore::HtmlInput(_)) => (INPUT_TYPES, "text"),
+            Some(HtmlElementStore::HtmlButton(_)) => (BUTTON_TYPES, "submit"),
+            _ => return String::new(),
+        };
+        self.get_attribute("type".to_owned())
+            .map(|input_type| input_type.to_ascii_lowercase())
+            .filter(|input_type| keywords.contains(&input_type.as_str()))
+            .unwrap_or_else(|| default.to_owned())
+    }
+
+    /// The selectedness of an option element.
+    /// See [HTMLOptionElement.selected](https://developer.mozilla.org/en-US/docs/Web/API/HTMLOptionElement/selected)
+    pub fn selected(&self) -> Result<bool, DomError> {
+        match self.contents.html_element() {
+            Some(HtmlElementStore::HtmlOption(store)) => Ok(store
+                .selected
+                .read()
+                .unwrap()
+                .unwrap_or_else(|| self.has_attribute("selected".to_owned()))),
+            _ => Err(DomError::InvalidState),
+        }
+    }
+
+    /// Sets the selectedness of an option element
+    pub fn set_selected(&self, selected: bool) -> Result<(), DomError> {
+        match self.contents.html_element() {
+            Some(HtmlElementStore::HtmlOption(store)) => {
+                *store.selected.write().unwrap() = Some(selected);
+                Ok(())
+            }
+            _ => Err(DomError::InvalidState),
+        }
+    }
+
+    /// The option elements which are descendants of a select element, in tree order
+    pub fn options(&self) -> Vec<ElementNodeArc> {
+        let mut options = Vec::new();
+        collect_descendant_elements(&self.clone().into(), &mut |element| {
+            if let Some(HtmlElementStore::HtmlOption(_)) = element.contents.html_element() {
+                options.push(element.clone());
+            }
+        });
+        options
+    }
+}
+
+/// The keywords of the [type](https://html.spec.whatwg.org/multipage/input.html#attr-input-type)
+/// attribute of input elements
+const INPUT_TYPES: &[&str] = &[
+    "hidden",
+    "text",
+    "search",
+    "tel",
+    "url",
+    "email",
+    "password",
+    "date",
+    "month",
+    "week",
+    "time",
+    "datetime-local",
+    "number",
+    "range",
+    "color",
+    "checkbox",
+    "radio",
+    "file",
+    "submit",
+    "image",
+    "reset",
+    "button",
+];
+
+/// The keywords of the [type](https://html.spec.whatwg.org/multipage/form-elements.html#attr-button-type)
+/// attribute of button elements
+const BUTTON_TYPES: &[&str] = &["submit", "reset", "button"];
+
+/// Calls `f` on each element which is a descendant of `node`, in tree order
+pub(crate) fn collect_descendant_elements(node: &AnyNodeArc, f: &mut impl FnMut(&ElementNodeArc)) {
+    for child in node.common.node_graph.static_child_nodes() {
+        if let Ok(element) = TryInto::<ElementNodeArc>::try_into(child.clone()) {
+            f(&element);
+        }
+        collect_descendant_elements(&child, f);
+    }
+}
+
+/// Concatenates the data of every text node which is a descendant of `node`, including CDATA
+/// sections, which the spec counts as text nodes
+pub(crate) fn descendant_text(node: &AnyNodeArc) -> String {
+    let mut text = String::new();
+    for child in node.common.node_graph.static_child_nodes() {
+        match &child.contents {
+            NodeContentsArc::Text(store) => text.push_str(&store.character_data.data()),
+            NodeContentsArc::CDataSection(store) => text.push_str(&store.character_data.data()),
+            _ => text.push_str(&descendant_text(&child)),
+        }
+    }
+    text
+}
+
+fn option_value(option: &ElementNodeArc) -> String {
+    option
+        .get_attribute("value".to_owned())
+        .unwrap_or_else(|| descendant_text(&option.clone().into()))
 }
 
 /// Enum of all concrete elements
@@ -131,7 +445,38 @@ pub(crate) enum ElementKind {
     SvgElement(SvgElementStore),
 }
 
+/// Copies the state behind `state`, so that the copy can change independently
+fn copy_state<T: Clone>(state: &Arc<RwLock<T>>) -> Arc<RwLock<T>> {
+    Arc::new(RwLock::new(state.read().unwrap().clone()))
+}
+
 impl ElementKind {
+    /// Copies the element's data, including the state of form controls, as the
+    /// [cloning steps](https://html.spec.whatwg.org/multipage/input.html#the-input-element:concept-node-clone-ext)
+    /// of the element do
+    pub(crate) fn copy(&self) -> ElementKind {
+        match self {
+            ElementKind::HtmlElement(store) => ElementKind::HtmlElement(match store {
+                HtmlElementStore::HtmlInput(input) => HtmlElementStore::HtmlInput(HtmlInputStore {
+                    value: copy_state(&input.value),
+                    checked: copy_state(&input.checked),
+                }),
+                HtmlElementStore::HtmlTextArea(textarea) => {
+                    HtmlElementStore::HtmlTextArea(HtmlTextAreaStore {
+                        value: copy_state(&textarea.value),
+                    })
+                }
+                HtmlElementStore::HtmlOption(option) => {
+                    HtmlElementStore::HtmlOption(HtmlOptionStore {
+                        selected: copy_state(&option.selected),
+                    })
+                }
+                store => store.clone(),
+            }),
+            ElementKind::SvgElement(store) => match *store {},
+        }
+    }
+
     /// [Element.tagName](https://developer.mozilla.org/en-US/docs/Web/API/Element/tagName)
     pub fn tag_name(&self) -> String {
         match self {
@@ -145,8 +490,19 @@ impl ElementKind {
 
 declare_html_elements! {
     "HTML" => HtmlHtml,
+    "HEAD" => HtmlHead,
+    "TITLE" => HtmlTitle,
     "BODY" => HtmlBody,
-    "BUTTON" => HtmlButton
+    "BUTTON" => HtmlButton,
+    "INPUT" => HtmlInput,
+    "TEXTAREA" => HtmlTextArea,
+    "SELECT" => HtmlSelect,
+    "OPTION" => HtmlOption,
+    "FORM" => HtmlForm,
+    "LABEL" => HtmlLabel,
+    "A" => HtmlAnchor,
+    "IMG" => HtmlImage,
+    "SCRIPT" => HtmlScript
 }
 
 /// html element storage
@@ -157,57 +513,159 @@ pub struct HtmlHtmlStore;
 pub struct HtmlUnknownStore {
     tag_name: String,
 }
+/// head element storage
+#[derive(Clone)]
+pub struct HtmlHeadStore;
+/// title element storage
+#[derive(Clone)]
+pub struct HtmlTitleStore;
 /// body element storage
 #[derive(Clone)]
 pub struct HtmlBodyStore;
 /// button element storage
 #[derive(Clone)]
 pub struct HtmlButtonStore;
+/// input element storage
+#[derive(Clone, Default)]
+pub struct HtmlInputStore {
+    /// The value as last set by the user or by script; None until it is first set
+    pub(crate) value: Arc<RwLock<Option<String>>>,
+    /// The checkedness as last set by the user or by script; None until it is first set
+    pub(crate) checked: Arc<RwLock<Option<bool>>>,
+}
+/// textarea element storage
+#[derive(Clone, Default)]
+pub struct HtmlTextAreaStore {
+    /// The value as last set by the user or by script; None until it is first set
+    pub(crate) value: Arc<RwLock<Option<String>>>,
+}
+/// select element storage
+#[derive(Clone)]
+pub struct HtmlSelectStore;
+/// option element storage
+#[derive(Clone, Default)]
+pub struct HtmlOptionStore {
+    /// The selectedness as last set by the user or by script; None until it is first set
+    pub(crate) selected: Arc<RwLock<Option<bool>>>,
+}
+/// form element storage
+#[derive(Clone)]
+pub struct HtmlFormStore;
+/// label element storage
+#[derive(Clone)]
+pub struct HtmlLabelStore;
+/// a element storage
+#[derive(Clone)]
+pub struct HtmlAnchorStore;
+/// img element storage
+#[derive(Clone)]
+pub struct HtmlImageStore;
+/// script element storage
+#[derive(Clone)]
+pub struct HtmlScriptStore;
 
 impl Builder<ElementNodeArc> {
     // TODO it would be nice if these didn't all return generic Elements but instead we had some kind of
     // concrete types representing each element type.
 
-    /// Builds a new HtmlHtmlElement node with a weak reference to its corresponding window
-    pub fn build_html(&self) -> ConcreteNodeArc<ElementStore> {
+    fn build_html_element(&self, store: HtmlElementStore) -> ConcreteNodeArc<ElementStore> {
         ConcreteNodeArc::<ElementStore>::new_cyclic(self.sandbox.clone(), |node_weak| {
             ElementStore::new(
-                ElementKind::HtmlElement(HtmlElementStore::HtmlHtml(HtmlHtmlStore)),
+                ElementKind::HtmlElement(store),
                 self.sandbox.clone(),
                 node_weak.clone().into(),
             )
         })
     }
 
+    /// Builds a new HtmlHtmlElement node with a weak reference to its corresponding window
+    pub fn build_html(&self) -> ConcreteNodeArc<ElementStore> {
+        self.build_html_element(HtmlElementStore::HtmlHtml(HtmlHtmlStore))
+    }
+
+    /// Builds a new HtmlHeadElement node
+    pub fn build_head(&self) -> ConcreteNodeArc<ElementStore> {
+        self.build_html_element(HtmlElementStore::HtmlHead(HtmlHeadStore))
+    }
+
+    /// Builds a new HtmlTitleElement node
+    pub fn build_title(&self) -> ConcreteNodeArc<ElementStore> {
+        self.build_html_element(HtmlElementStore::HtmlTitle(HtmlTitleStore))
+    }
+
     /// Builds a new HtmlBodyElement node
     pub fn build_body(&self) -> ConcreteNodeArc<ElementStore> {
-        ConcreteNodeArc::<ElementStore>::new_cyclic(self.sandbox.clone(), |node_weak| {
-            ElementStore::new(
-                ElementKind::HtmlElement(HtmlElementStore::HtmlBody(HtmlBodyStore)),
-                self.sandbox.clone(),
-                node_weak.clone().into(),
-            )
-        })
+        self.build_html_element(HtmlElementStore::HtmlBody(HtmlBodyStore))
     }
 
     /// Builds a new HtmlButtonElement node
     pub fn build_button(&self) -> ConcreteNodeArc<ElementStore> {
-        ConcreteNodeArc::<ElementStore>::new_cyclic(self.sandbox.clone(), |node_weak| {
-            ElementStore::new(
-                ElementKind::HtmlElement(HtmlElementStore::HtmlButton(HtmlButtonStore)),
-                self.sandbox.clone(),
-                node_weak.clone().into(),
-            )
-        })
+        self.build_html_element(HtmlElementStore::HtmlButton(HtmlButtonStore))
+    }
+
+    /// Builds a new HtmlInputElement node
+    pub fn build_input(&self) -> ConcreteNodeArc<ElementStore> {
+        self.build_html_element(HtmlElementStore::HtmlInput(Default::default()))
+    }
+
+    /// Builds a new HtmlTextAreaElement node
+    pub fn build_textarea(&self) -> ConcreteNodeArc<ElementStore> {
+        self.build_html_element(HtmlElementStore::HtmlTextArea(Default::default()))
+    }
+
+    /// Builds a new HtmlSelectElement node
+    pub fn build_select(&self) -> ConcreteNodeArc<ElementStore> {
+        self.build_html_element(HtmlElementStore::HtmlSelect(HtmlSelectStore))
+    }
+
+    /// Builds a new HtmlOptionElement node
+    pub fn build_option(&self) -> ConcreteNodeArc<ElementStore> {
+        self.build_html_element(HtmlElementStore::HtmlOption(Default::default()))
+    }
+
+    /// Builds a new HtmlFormElement node
+    pub fn build_form(&self) -> ConcreteNodeArc<ElementStore> {
+        self.build_html_element(HtmlElementStore::HtmlForm(HtmlFormStore))
+    }
+
+    /// Builds a new HtmlLabelElement node
+    pub fn build_label(&self) -> ConcreteNodeArc<ElementStore> {
+        self.build_html_element(HtmlElementStore::HtmlLabel(HtmlLabelStore))
+    }
+
+    /// Builds a new HtmlAnchorElement node
+    pub fn build_anchor(&self) -> ConcreteNodeArc<ElementStore> {
+        self.build_html_element(HtmlElementStore::HtmlAnchor(HtmlAnchorStore))
+    }
+
+    /// Builds a new HtmlImageElement node
+    pub fn build_image(&self) -> ConcreteNodeArc<ElementStore> {
+        self.build_html_element(HtmlElementStore::HtmlImage(HtmlImageStore))
+    }
+
+    /// Builds a new HtmlScriptElement node
+    pub fn build_script(&self) -> ConcreteNodeArc<ElementStore> {
+        self.build_html_element(HtmlElementStore::HtmlScript(HtmlScriptStore))
     }
 
     /// Builds a new HtmlUnknownElement node
     pub fn build_unknown(&self, tag_name: String) -> ConcreteNodeArc<ElementStore> {
+        self.build_html_element(HtmlElementStore::HtmlUnknown(HtmlUnknownStore { tag_name }))
+    }
+
+    /// Builds a new element named `qualified_name` in `namespace`, with no element-specific data
+    pub fn build_ns(
+        &self,
+        namespace: Option<String>,
+        qualified_name: String,
+    ) -> ConcreteNodeArc<ElementStore> {
+        let store = HtmlElementStore::HtmlUnknown(HtmlUnknownStore {
+            tag_name: qualified_name,
+        });
         ConcreteNodeArc::<ElementStore>::new_cyclic(self.sandbox.clone(), |node_weak| {
-            ElementStore::new(
-                ElementKind::HtmlElement(HtmlElementStore::HtmlUnknown(HtmlUnknownStore {
-                    tag_name,
-                })),
+            ElementStore::new_ns(
+                ElementKind::HtmlElement(store),
+                namespace,
                 self.sandbox.clone(),
                 node_weak.clone().into(),
             )
@@ -0,0 +1,246 @@
+//! Focus: which element of a document receives keyboard input, how focus moves between
+//! elements, and the order in which the Tab key visits them.
+
+use super::concrete::{ConcreteNodeArc, DocumentNodeArc, ElementNodeArc};
+use super::contents::DocumentStore;
+use super::element::{collect_descendant_elements, ElementStore, HtmlElementStore};
+use crate::event::{Event, EventInit, EventKind, FocusEventData};
+use crate::internal_prelude::*;
+
+use std::convert::TryInto;
+
+impl ConcreteNodeArc<ElementStore> {
+    /// [HTMLElement.tabIndex](https://developer.mozilla.org/en-US/docs/Web/API/HTMLElement/tabIndex):
+    /// the parsed `tabindex` attribute, defaulting to 0 for elements which are focusable by
+    /// default and -1 for all others.
+    pub fn tab_index(&self) -> i32 {
+        match self
+            .get_attribute("tabindex".to_owned())
+            .and_then(|tab_index| tab_index.trim().parse::<i32>().ok())
+        {
+            Some(tab_index) => tab_index,
+            None if self.is_focusable_by_default() => 0,
+            None => -1,
+        }
+    }
+
+    /// Whether the element can be focused, by script or by clicking it: it is connected to its
+    /// document, is not disabled, and is either focusable by default or has a valid `tabindex`.
+    pub fn is_focusable(&self) -> bool {
+        if self.disabled() || document_of(self).is_err() {
+            return false;
+        }
+        self.is_focusable_by_default()
+            || self
+                .get_attribute("tabindex".to_owned())
+                .and_then(|tab_index| tab_index.trim().parse::<i32>().ok())
+                .is_some()
+    }
+
+    fn is_focusable_by_default(&self) -> bool {
+        match self.contents.html_element() {
+            Some(HtmlElementStore::HtmlInput(_)) => self.input_type() != "hidden",
+            Some(HtmlElementStore::HtmlButton(_))
+            | Some(HtmlElementStore::HtmlSelect(_))
+            | Some(HtmlElementStore::HtmlTextArea(_)) => true,
+            Some(HtmlElementStore::HtmlAnchor(_)) => self.has_attribute("href".to_owned()),
+            _ => false,
+        }
+    }
+
+    /// [HTMLElement.focus](https://developer.mozilla.org/en-US/docs/Web/API/HTMLElement/focus).
+    /// Does nothing if the element is not focusable.
+    pub fn focus(&self) -> Result<(), DomError> {
+        if !self.is_focusable() {
+            return Ok(());
+        }
+        set_focus(&document_of(self)?, Some(self.clone()))
+    }
+
+    /// [HTMLElement.blur](https://developer.mozilla.org/en-US/docs/Web/API/HTMLElement/blur).
+    /// Does nothing if the element is not focused.
+    pub fn blur(&self) -> Result<(), DomError> {
+        let document = match document_of(self) {
+            Ok(document) => document,
+            Err(_) => return Ok(()),
+        };
+        match document.focused_element() {
+            Some(focused) if focused == *self => set_focus(&document, None),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the element is its document's focused element (matches `:focus`)
+    pub fn has_focus(&self) -> bool {
+        document_of(self)
+            .ok()
+            .and_then(|document| document.focused_element())
+            .is_some_and(|focused| focused == *self)
+    }
+
+    /// Whether the element or one of its descendants is focused (matches `:focus-within`)
+    pub fn has_focus_within(&self) -> bool {
+        let focused = match document_of(self)
+            .ok()
+            .and_then(|document| document.focused_element())
+        {
+            Some(focused) => focused,
+            None => return false,
+        };
+
+        let mut node: Option<AnyNodeArc> = Some(focused.into());
+        while let Some(current) = node {
+            if Arc::ptr_eq(&current.common, &self.common) {
+                return true;
+            }
+            node = current.common.node_graph.parent_node();
+        }
+        false
+    }
+}
+
+impl ConcreteNodeArc<DocumentStore> {
+    /// [Document.activeElement](https://developer.mozilla.org/en-US/docs/Web/API/Document/activeElement):
+    /// the focused element, or else the body element (or the document element if there is no
+    /// body).
+    pub fn active_element(&self) -> Option<ElementNodeArc> {
+        if let Some(focused) = self.focused_element() {
+            return Some(focused);
+        }
+
+        let document_element = self
+            .common
+            .node_graph
+            .static_child_nodes()
+            .into_iter()
+            .find_map(|child| TryInto::<ElementNodeArc>::try_into(child).ok())?;
+        let body = document_element
+            .common
+            .node_graph
+            .static_child_nodes()
+            .into_iter()
+            .filter_map(|child| TryInto::<ElementNodeArc>::try_into(child).ok())
+            .find(|child| {
+                matches!(
+                    child.contents.html_element(),
+                    Some(HtmlElementStore::HtmlBody(_))
+                )
+            });
+        Some(body.unwrap_or(document_element))
+    }
+
+    /// [Document.hasFocus](https://developer.mozilla.org/en-US/docs/Web/API/Document/hasFocus):
+    /// whether any element of the document is focused
+    pub fn has_focus(&self) -> bool {
+        self.focused_element().is_some()
+    }
+
+    /// The focused element, if it is still connected to this document. An element which was
+    /// removed from the document loses focus without firing any event (the "focus fixup rule").
+    pub(crate) fn focused_element(&self) -> Option<ElementNodeArc> {
+        let focused = self.contents.focused_element.read().unwrap().clone()?;
+        let focused = focused.upgrade()?;
+        let root = focused.common.node_graph.root_node().ok()?;
+        if Arc::ptr_eq(&root.common, &self.common) {
+            Some(focused)
+        } else {
+            *self.contents.focused_element.write().unwrap() = None;
+            None
+        }
+    }
+
+    /// The focusable elements of the document in
+    /// [sequential navigation order](https://html.spec.whatwg.org/multipage/interaction.html#sequential-navigation-order):
+    /// positive `tabindex` values first, ascending, then the elements with a `tabindex` of zero
+    /// (or focusable by default), all in tree order. Elements with a negative `tabindex` are
+    /// left out.
+    pub fn sequential_navigation_order(&self) -> Vec<ElementNodeArc> {
+        let mut elements = Vec::new();
+        collect_descendant_elements(&self.clone().into(), &mut |element| {
+            if element.is_focusable() && element.tab_index() >= 0 {
+                elements.push(element.clone());
+            }
+        });
+
+        // A stable sort keeps tree order among equal tabindex values
+        elements.sort_by_key(|element| match element.tab_index() {
+            0 => i64::MAX,
+            tab_index => i64::from(tab_index),
+        });
+        elements
+    }
+}
+
+/// The document an element is connected to. Fails if the element is not in its sandbox's
+/// document.
+pub(crate) fn document_of(element: &ElementNodeArc) -> Result<DocumentNodeArc, DomError> {
+    let context = element
+        .get_context()
+        .upgrade()
+        .ok_or(DomError::SandboxDropped)?;
+    let document = context.window().document();
+    let root = element.common.node_graph.root_node()?;
+    if Arc::ptr_eq(&root.common, &document.common) {
+        Ok(document)
+    } else {
+        Err(DomError::NotFound)
+    }
+}
+
+/// The nearest inclusive ancestor of `element` which is focusable, which is what gets focused
+/// when the element is clicked.
+pub(crate) fn focusable_inclusive_ancestor(element: &ElementNodeArc) -> Option<ElementNodeArc> {
+    let mut current: AnyNodeArc = element.clone().into();
+    loop {
+        if let Ok(element) = TryInto::<ElementNodeArc>::try_into(current.clone()) {
+            if element.is_focusable() {
+                return Some(element);
+            }
+        }
+        current = current.common.node_graph.parent_node()?;
+    }
+}
+
+/// Moves focus to `new_focus` (or to nothing), firing blur and focusout at the element losing
+/// focus, then focus and focusin at the element gaining it.
+pub(crate) fn set_focus(
+    document: &DocumentNodeArc,
+    new_focus: Option<ElementNodeArc>,
+) -> Result<(), DomError> {
+    let old_focus = document.focused_element();
+    if old_focus == new_focus {
+        return Ok(());
+    }
+
+    *document.contents.focused_element.write().unwrap() = None;
+    if let Some(old) = &old_focus {
+        let related_target: Option<AnyNodeArc> = new_focus.clone().map(Into::into);
+        fire_focus_event(old, "blur", false, related_target.clone())?;
+        fire_focus_event(old, "focusout", true, related_target)?;
+    }
+
+    if let Some(new) = &new_focus {
+        *document.contents.focused_element.write().unwrap() = Some(new.downgrade());
+        let related_target: Option<AnyNodeArc> = old_focus.map(Into::into);
+        fire_focus_event(new, "focus", false, related_target.clone())?;
+        fire_focus_event(new, "focusin", true, related_target)?;
+    }
+    Ok(())
+}
+
+fn fire_focus_event(
+    target: &ElementNodeArc,
+    event_type: &str,
+    bubbles: bool,
+    related_target: Option<AnyNodeArc>,
+) -> Result<bool, DomError> {
+    let event = Event::new(
+        event_type.to_owned(),
+        EventInit {
+            bubbles,
+            cancelable: false,
+            kind: EventKind::Focus(FocusEventData { related_target }),
+        },
+    );
+    target.dispatch_event(&event)
+}
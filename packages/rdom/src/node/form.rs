@@ -0,0 +1,405 @@
+//! Forms and the elements associated with them: form owners, labels, submission and reset.
+
+use super::concrete::{ConcreteNodeArc, ElementNodeArc};
+use super::element::{collect_descendant_elements, ElementStore, HtmlElementStore};
+use crate::event::{Event, EventInit, EventKind, SubmitEventData};
+use crate::internal_prelude::*;
+use crate::navigation::{NavigationRequest, NavigationSource};
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+
+impl ConcreteNodeArc<ElementStore> {
+    /// The form owner of a form-associated element: the form named by its `form` attribute, or
+    /// else its nearest form ancestor.
+    /// See [HTMLInputElement.form](https://developer.mozilla.org/en-US/docs/Web/API/HTMLInputElement/form)
+    pub fn form(&self) -> Option<ElementNodeArc> {
+        if !is_form_associated(self) {
+            return None;
+        }
+
+        if let Some(form_id) = self.get_attribute("form".to_owned()) {
+            let root = self.common.node_graph.root_node().ok()?;
+            return find_element_by_id(&root, &form_id).filter(is_form);
+        }
+
+        let mut node = self.common.node_graph.parent_node();
+        while let Some(current) = node {
+            if let Ok(element) = TryInto::<ElementNodeArc>::try_into(current.clone()) {
+                if is_form(&element) {
+                    return Some(element);
+                }
+            }
+            node = current.common.node_graph.parent_node();
+        }
+        None
+    }
+
+    /// The form control labeled by a label element: the element named by its `for` attribute,
+    /// or else its first labelable descendant.
+    /// See [HTMLLabelElement.control](https://developer.mozilla.org/en-US/docs/Web/API/HTMLLabelElement/control)
+    pub fn control(&self) -> Option<ElementNodeArc> {
+        match self.contents.html_element() {
+            Some(HtmlElementStore::HtmlLabel(_)) => {}
+            _ => return None,
+        }
+
+        if let Some(for_id) = self.get_attribute("for".to_owned()) {
+            let root = self.common.node_graph.root_node().ok()?;
+            return find_element_by_id(&root, &for_id).filter(is_labelable);
+        }
+
+        let mut control = None;
+        collect_descendant_elements(&self.clone().into(), &mut |element| {
+            if control.is_none() && is_labelable(element) {
+                control = Some(element.clone());
+            }
+        });
+        control
+    }
+
+    /// [HTMLFormElement.requestSubmit](https://developer.mozilla.org/en-US/docs/Web/API/HTMLFormElement/requestSubmit).
+    /// Fires a cancelable submit event at the form and, unless it is canceled, records a
+    /// navigation request carrying the form data.
+    pub fn request_submit(&self, submitter: Option<&ElementNodeArc>) -> Result<(), DomError> {
+        if !is_form(self) {
+            return Err(DomError::InvalidState);
+        }
+
+        let event = Event::new(
+            "submit".to_owned(),
+            EventInit {
+                bubbles: true,
+                cancelable: true,
+                kind: EventKind::Submit(SubmitEventData {
+                    submitter: submitter.cloned().map(Into::into),
+                }),
+            },
+        );
+        if self.dispatch_event(&event)? {
+            submit_form(self, submitter)?;
+        }
+        Ok(())
+    }
+
+    /// [HTMLFormElement.submit](https://developer.mozilla.org/en-US/docs/Web/API/HTMLFormElement/submit).
+    /// Records a navigation request carrying the form data, without firing a submit event.
+    pub fn submit(&self) -> Result<(), DomError> {
+        if !is_form(self) {
+            return Err(DomError::InvalidState);
+        }
+        submit_form(self, None)
+    }
+
+    /// [HTMLFormElement.reset](https://developer.mozilla.org/en-US/docs/Web/API/HTMLFormElement/reset).
+    /// Fires a cancelable reset event at the form and, unless it is canceled, restores the
+    /// default values of its controls.
+    pub fn reset(&self) -> Result<(), DomError> {
+        if !is_form(self) {
+            return Err(DomError::InvalidState);
+        }
+
+        let event = Event::new(
+            "reset".to_owned(),
+            EventInit {
+                bubbles: true,
+                cancelable: true,
+                ..Default::default()
+            },
+        );
+        if !self.dispatch_event(&event)? {
+            return Ok(());
+        }
+
+        for control in associated_controls(self) {
+            match control.contents.html_element() {
+                Some(HtmlElementStore::HtmlInput(store)) => {
+                    *store.value.write().unwrap() = None;
+                    *store.checked.write().unwrap() = None;
+                }
+                Some(HtmlElementStore::HtmlTextArea(store)) => {
+                    *store.value.write().unwrap() = None;
+                }
+                Some(HtmlElementStore::HtmlSelect(_)) => {
+                    for option in control.options() {
+                        if let Some(HtmlElementStore::HtmlOption(store)) =
+                            option.contents.html_element()
+                        {
+                            *store.selected.write().unwrap() = None;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_form(element: &ElementNodeArc) -> bool {
+    matches!(
+        element.contents.html_element(),
+        Some(HtmlElementStore::HtmlForm(_))
+    )
+}
+
+fn is_form_associated(element: &ElementNodeArc) -> bool {
+    matches!(
+        element.contents.html_element(),
+        Some(HtmlElementStore::HtmlButton(_))
+            | Some(HtmlElementStore::HtmlInput(_))
+            | Some(HtmlElementStore::HtmlSelect(_))
+            | Some(HtmlElementStore::HtmlTextArea(_))
+    )
+}
+
+fn is_labelable(element: &ElementNodeArc) -> bool {
+    match element.contents.html_element() {
+        Some(HtmlElementStore::HtmlInput(_)) => element.input_type() != "hidden",
+        Some(HtmlElementStore::HtmlButton(_))
+        | Some(HtmlElementStore::HtmlSelect(_))
+        | Some(HtmlElementStore::HtmlTextArea(_)) => true,
+        _ => false,
+    }
+}
+
+/// Finds the first element in tree order, at or below `root`, whose id is `id`
+pub(crate) fn find_element_by_id(root: &AnyNodeArc, id: &str) -> Option<ElementNodeArc> {
+    if let Ok(element) = TryInto::<ElementNodeArc>::try_into(root.clone()) {
+        if element.get_attribute("id".to_owned()).as_deref() == Some(id) {
+            return Some(element);
+        }
+    }
+
+    let mut found = None;
+    collect_descendant_elements(root, &mut |element| {
+        if found.is_none() && element.get_attribute("id".to_owned()).as_deref() == Some(id) {
+            found = Some(element.clone());
+        }
+    });
+    found
+}
+
+/// The form-associated elements at or below `root`, in tree order, each with its form owner.
+/// This finds every owner in a single pass over the tree, where calling `form` on each element
+/// would search the tree again for every `form` attribute.
+fn form_owners(root: &AnyNodeArc) -> Vec<(ElementNodeArc, Option<ElementNodeArc>)> {
+    /// A control, its nearest form ancestor and its `form` attribute
+    type Control = (ElementNodeArc, Option<ElementNodeArc>, Option<String>);
+
+    fn walk(
+        node: &AnyNodeArc,
+        form_ancestor: Option<&ElementNodeArc>,
+        ids: &mut HashMap<String, ElementNodeArc>,
+        controls: &mut Vec<Control>,
+    ) {
+        let element = TryInto::<ElementNodeArc>::try_into(node.clone()).ok();
+        if let Some(element) = &element {
+            if let Some(id) = element.get_attribute("id".to_owned()) {
+                ids.entry(id).or_insert_with(|| element.clone());
+            }
+            if is_form_associated(element) {
+                let form_id = element.get_attribute("form".to_owned());
+                controls.push((element.clone(), form_ancestor.cloned(), form_id));
+            }
+        }
+        let form_ancestor = element.as_ref().filter(|e| is_form(e)).or(form_ancestor);
+        for child in node.common.node_graph.static_child_nodes() {
+            walk(&child, form_ancestor, ids, controls);
+        }
+    }
+
+    let mut ids = HashMap::new();
+    let mut controls = Vec::new();
+    walk(root, None, &mut ids, &mut controls);
+    controls
+        .into_iter()
+        .map(|(control, form_ancestor, form_id)| {
+            let owner = match form_id {
+                Some(form_id) => ids.get(&form_id).filter(|e| is_form(e)).cloned(),
+                None => form_ancestor,
+            };
+            (control, owner)
+        })
+        .collect()
+}
+
+/// The form-associated elements whose form owner is `form`, in tree order
+fn associated_controls(form: &ElementNodeArc) -> Vec<ElementNodeArc> {
+    let root = match form.common.node_graph.root_node() {
+        Ok(root) => root,
+        Err(_) => return Vec::new(),
+    };
+
+    form_owners(&root)
+        .into_iter()
+        .filter(|(_, owner)| owner.as_ref() == Some(form))
+        .map(|(control, _)| control)
+        .collect()
+}
+
+/// The [default button](https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#default-button)
+/// of a form: its first submit button in tree order
+pub(crate) fn default_button(form: &ElementNodeArc) -> Option<ElementNodeArc> {
+    associated_controls(form)
+        .into_iter()
+        .find(|control| match control.contents.html_element() {
+            Some(HtmlElementStore::HtmlButton(_)) => control.input_type() == "submit",
+            Some(HtmlElementStore::HtmlInput(_)) => {
+                matches!(control.input_type().as_str(), "submit" | "image")
+            }
+            _ => false,
+        })
+}
+
+/// The other radio buttons in the same group as `input`: those with the same name and the same
+/// form owner, in the same tree.
+pub(crate) fn radio_group(input: &ElementNodeArc) -> Vec<ElementNodeArc> {
+    let name = match input.get_attribute("name".to_owned()) {
+        Some(name) if !name.is_empty() => name,
+        _ => return Vec::new(),
+    };
+    let root = match input.common.node_graph.root_node() {
+        Ok(root) => root,
+        Err(_) => return Vec::new(),
+    };
+    let controls = form_owners(&root);
+    let form = match controls.iter().find(|(control, _)| control == input) {
+        Some((_, form)) => form.clone(),
+        None => return Vec::new(),
+    };
+
+    controls
+        .into_iter()
+        .filter(|(element, owner)| {
+            element != input
+                && *owner == form
+                && element.input_type() == "radio"
+                && matches!(
+                    element.contents.html_element(),
+                    Some(HtmlElementStore::HtmlInput(_))
+                )
+                && element.get_attribute("name".to_owned()).as_deref() == Some(name.as_str())
+        })
+        .map(|(element, _)| element)
+        .collect()
+}
+
+/// Constructs the entry list of a form, per the
+/// [spec](https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#constructing-the-form-data-set)
+fn form_data(form: &ElementNodeArc, submitter: Option<&ElementNodeArc>) -> Vec<(String, String)> {
+    let mut entries = Vec::new();
+    for control in associated_controls(form) {
+        let name = match control.get_attribute("name".to_owned()) {
+            Some(name) if !name.is_empty() => name,
+            _ => continue,
+        };
+        if control.disabled() {
+            continue;
+        }
+
+        match control.contents.html_element() {
+            Some(HtmlElementStore::HtmlButton(_)) if submitter == Some(&control) => {
+                let value = control
+                    .get_attribute("value".to_owned())
+                    .unwrap_or_default();
+                entries.push((name, value));
+            }
+            Some(HtmlElementStore::HtmlInput(_)) => match control.input_type().as_str() {
+                "checkbox" | "radio" if control.checked().unwrap_or(false) => {
+                    let value = control
+                        .get_attribute("value".to_owned())
+                        .unwrap_or_else(|| "on".to_owned());
+                    entries.push((name, value));
+                }
+                "submit" | "image" if submitter == Some(&control) => {
+                    entries.push((name, control.value().unwrap_or_default()));
+                }
+                "checkbox" | "radio" | "submit" | "image" | "button" | "reset" | "file" => {}
+                _ => entries.push((name, control.value().unwrap_or_default())),
+            },
+            Some(HtmlElementStore::HtmlSelect(_)) => {
+                for option in control.options() {
+                    if option.selected().unwrap_or(false) && !option.disabled() {
+                        entries.push((name.clone(), option.value().unwrap_or_default()));
+                    }
+                }
+            }
+            Some(HtmlElementStore::HtmlTextArea(_)) => {
+                entries.push((name, control.value().unwrap_or_default()))
+            }
+            _ => {}
+        }
+    }
+    entries
+}
+
+/// Serializes name-value pairs per the `application/x-www-form-urlencoded` serializer
+pub(crate) fn urlencode(entries: &[(String, String)]) -> String {
+    fn encode(input: &str) -> String {
+        let mut output = String::new();
+        for byte in input.bytes() {
+            match byte {
+                b'*' | b'-' | b'.' | b'_' => output.push(byte as char),
+                _ if byte.is_ascii_alphanumeric() => output.push(byte as char),
+                b' ' => output.push('+'),
+                _ => output.push_str(&format!("%{:02X}", byte)),
+            }
+        }
+        output
+    }
+
+    entries
+        .iter()
+        .map(|(name, value)| format!("{}={}", encode(name), encode(value)))
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+/// Submits `form`, recording the resulting navigation request with the sandbox
+fn submit_form(form: &ElementNodeArc, submitter: Option<&ElementNodeArc>) -> Result<(), DomError> {
+    let context = form
+        .get_context()
+        .upgrade()
+        .ok_or(DomError::SandboxDropped)?;
+
+    let submitter_attribute = |name: &str| submitter.and_then(|s| s.get_attribute(name.to_owned()));
+    let action = submitter_attribute("formaction")
+        .or_else(|| form.get_attribute("action".to_owned()))
+        .unwrap_or_default();
+    let method = submitter_attribute("formmethod")
+        .or_else(|| form.get_attribute("method".to_owned()))
+        .map(|method| method.to_ascii_uppercase())
+        .filter(|method| method == "POST")
+        .unwrap_or_else(|| "GET".to_owned());
+    let target =
+        submitter_attribute("formtarget").or_else(|| form.get_attribute("target".to_owned()));
+
+    // An empty action submits to the document itself, and an invalid one aborts submission
+    let document_url = context.window().history().document_url()?;
+    let mut url = if action.is_empty() {
+        document_url
+    } else {
+        match document_url.join(&action) {
+            Ok(url) => url,
+            Err(_) => return Ok(()),
+        }
+    };
+
+    let query = urlencode(&form_data(form, submitter));
+    let body = if method == "POST" {
+        Some(query)
+    } else {
+        url.set_query(Some(&query));
+        None
+    };
+
+    context.request_navigation(NavigationRequest {
+        url: url.to_string(),
+        method,
+        body,
+        target,
+        source: NavigationSource::Form,
+    });
+    Ok(())
+}
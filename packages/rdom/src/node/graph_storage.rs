@@ -1,5 +1,9 @@
+use super::contents::NodeContentsArc;
+use super::quirks;
 use crate::internal_prelude::*;
+use crate::mutation_observer::{queue_mutation_record, MutationRecord};
 use crate::node_list::{NodeList, NodeListStorage, Query};
+use crate::range;
 use std::sync::RwLock;
 
 /// NodeGraphStorage contains all the data connected
@@ -8,7 +12,7 @@ pub struct NodeGraphStorage {
     /// Reference back up to the common Node
     pub(crate) node: AnyNodeWeak,
 
-    parent_node: Option<AnyNodeWeak>,
+    parent_node: RwLock<Option<AnyNodeWeak>>,
     left_sibling: Option<AnyNodeWeak>,
     right_sibling: Option<AnyNodeWeak>,
     child_nodes: RwLock<Vec<AnyNodeArc>>,
@@ -19,7 +23,7 @@ impl NodeGraphStorage {
     pub fn new(node: AnyNodeWeak) -> NodeGraphStorage {
         NodeGraphStorage {
             node,
-            parent_node: None,
+            parent_node: RwLock::new(None),
             left_sibling: None,
             right_sibling: None,
             child_nodes: RwLock::new(Vec::new()),
@@ -40,9 +44,210 @@ impl NodeGraphStorage {
         (*lock).last().cloned()
     }
 
+    pub(crate) fn parent_node(&self) -> Option<AnyNodeArc> {
+        let lock = self.parent_node.read().unwrap();
+        (*lock).as_ref().and_then(|parent| parent.upgrade())
+    }
+
+    /// The position of the node among its parent's children
+    pub(crate) fn index(&self) -> Option<usize> {
+        let parent = self.parent_node()?;
+        let siblings = parent.common.node_graph.child_nodes.read().unwrap();
+        siblings.iter().position(|sibling| {
+            std::ptr::eq(Arc::as_ptr(&sibling.common), self.node.common.as_ptr())
+        })
+    }
+
+    pub(crate) fn previous_sibling(&self) -> Option<AnyNodeArc> {
+        let index = self.index()?.checked_sub(1)?;
+        let parent = self.parent_node()?;
+        let siblings = parent.common.node_graph.child_nodes.read().unwrap();
+        siblings.get(index).cloned()
+    }
+
+    pub(crate) fn next_sibling(&self) -> Option<AnyNodeArc> {
+        let index = self.index()? + 1;
+        let parent = self.parent_node()?;
+        let siblings = parent.common.node_graph.child_nodes.read().unwrap();
+        siblings.get(index).cloned()
+    }
+
+    /// The descendants of the node, in tree order
+    pub(crate) fn descendants(&self) -> Vec<AnyNodeArc> {
+        let mut descendants = Vec::new();
+        for child in self.static_child_nodes() {
+            descendants.push(child.clone());
+            descendants.extend(child.common.node_graph.descendants());
+        }
+        descendants
+    }
+
+    /// The number of children of the node
+    pub(crate) fn child_count(&self) -> usize {
+        self.child_nodes.read().unwrap().len()
+    }
+
+    /// The root of the tree the node is in (the node itself if it has no parent)
+    pub(crate) fn root_node(&self) -> Result<AnyNodeArc, DomError> {
+        let mut node = self.self_arc()?;
+        while let Some(parent) = node.common.node_graph.parent_node() {
+            node = parent;
+        }
+        Ok(node)
+    }
+
     pub(crate) fn append_child(&self, other: AnyNodeArc) {
-        let mut lock = self.child_nodes.write().unwrap();
-        (*lock).push(other);
+        self.insert_before(other, None)
+    }
+
+    /// Inserts `other` before `reference`, one of the node's children, or at the end if there
+    /// is no reference. `other` is first removed from its old parent.
+    pub(crate) fn insert_before(&self, other: AnyNodeArc, reference: Option<&AnyNodeArc>) {
+        self.splice(None, vec![other], reference)
+    }
+
+    /// Replaces `child`, one of the node's children, with `other`
+    pub(crate) fn replace_child(&self, other: AnyNodeArc, child: &AnyNodeArc) {
+        if other.ptr_eq(child) {
+            return;
+        }
+        let reference = child.common.node_graph.next_sibling();
+        self.splice(Some(child), vec![other], reference.as_ref())
+    }
+
+    /// Removes `child`, if given, and inserts `nodes` before `reference`, or at the end if there
+    /// is no reference, queueing a single mutation record. The nodes are first removed from
+    /// their old parents.
+    pub(crate) fn splice(
+        &self,
+        child: Option<&AnyNodeArc>,
+        nodes: Vec<AnyNodeArc>,
+        reference: Option<&AnyNodeArc>,
+    ) {
+        // The reference may not be one of the nodes, which are about to move
+        let mut reference = reference.cloned();
+        while let Some(node) = reference
+            .clone()
+            .filter(|reference| nodes.iter().any(|node| node.ptr_eq(reference)))
+        {
+            reference = node.common.node_graph.next_sibling();
+        }
+
+        for node in &nodes {
+            if let Some(old_parent) = node.common.node_graph.parent_node() {
+                old_parent.common.node_graph.remove_child(node);
+            }
+        }
+
+        let (removed, had_element, index, previous_sibling, next_sibling) = {
+            let mut lock = self.child_nodes.write().unwrap();
+            let removed = child.and_then(|child| {
+                let index = lock.iter().position(|node| node.ptr_eq(child))?;
+                Some((lock.remove(index), index))
+            });
+            let had_element = lock
+                .iter()
+                .any(|node| matches!(node.contents, NodeContentsArc::Element(_)));
+            let index = reference
+                .as_ref()
+                .and_then(|reference| lock.iter().position(|node| node.ptr_eq(reference)))
+                .unwrap_or(lock.len());
+            for (offset, node) in nodes.iter().enumerate() {
+                lock.insert(index + offset, node.clone());
+            }
+            let previous_sibling = index.checked_sub(1).map(|i| lock[i].clone());
+            let next_sibling = lock.get(index + nodes.len()).cloned();
+            (removed, had_element, index, previous_sibling, next_sibling)
+        };
+        if let Some((child, _)) = &removed {
+            *child.common.node_graph.parent_node.write().unwrap() = None;
+        }
+        for node in &nodes {
+            *node.common.node_graph.parent_node.write().unwrap() = Some(self.node.clone());
+        }
+        if removed.is_none() && nodes.is_empty() {
+            return;
+        }
+
+        if let Some(target) = self.node.upgrade() {
+            if let Some((child, index)) = &removed {
+                range::child_removed(&target, child, *index);
+            }
+            quirks::children_inserted(&target, &nodes, had_element);
+            for offset in 0..nodes.len() {
+                range::child_inserted(&target, index + offset);
+            }
+            queue_mutation_record(MutationRecord::child_list(
+                &target,
+                nodes,
+                removed.into_iter().map(|(child, _)| child).collect(),
+                previous_sibling,
+                next_sibling,
+            ));
+        }
+    }
+
+    pub(crate) fn remove_child(&self, child: &AnyNodeArc) {
+        let (index, previous_sibling, next_sibling) = {
+            let mut lock = self.child_nodes.write().unwrap();
+            let index = match (*lock)
+                .iter()
+                .position(|node| Arc::ptr_eq(&node.common, &child.common))
+            {
+                Some(index) => index,
+                None => return,
+            };
+            (*lock).remove(index);
+            let previous_sibling = index.checked_sub(1).map(|i| (*lock)[i].clone());
+            (index, previous_sibling, (*lock).get(index).cloned())
+        };
+        *child.common.node_graph.parent_node.write().unwrap() = None;
+
+        if let Some(target) = self.node.upgrade() {
+            range::child_removed(&target, child, index);
+            queue_mutation_record(MutationRecord::child_list(
+                &target,
+                Vec::new(),
+                vec![child.clone()],
+                previous_sibling,
+                next_sibling,
+            ));
+        }
+    }
+
+    /// [Replaces all](https://dom.spec.whatwg.org/#concept-node-replace-all) the children of
+    /// the node with `nodes`, queueing a single mutation record. The nodes are first removed
+    /// from their old parents.
+    pub(crate) fn replace_all(&self, nodes: Vec<AnyNodeArc>) {
+        for node in &nodes {
+            if let Some(old_parent) = node.common.node_graph.parent_node() {
+                old_parent.common.node_graph.remove_child(node);
+            }
+        }
+        let removed = std::mem::replace(&mut *self.child_nodes.write().unwrap(), nodes.clone());
+        for child in &removed {
+            *child.common.node_graph.parent_node.write().unwrap() = None;
+        }
+        for node in &nodes {
+            *node.common.node_graph.parent_node.write().unwrap() = Some(self.node.clone());
+        }
+        if removed.is_empty() && nodes.is_empty() {
+            return;
+        }
+
+        if let Some(target) = self.node.upgrade() {
+            for child in &removed {
+                range::child_removed(&target, child, 0);
+            }
+            // Every child was removed, so none of the old ones is a document element
+            quirks::children_inserted(&target, &nodes, false);
+            for index in 0..nodes.len() {
+                range::child_inserted(&target, index);
+            }
+            queue_mutation_record(MutationRecord::child_list(
+                &target, nodes, removed, None, None,
+            ));
+        }
     }
 
     pub(crate) fn static_child_nodes(&self) -> Vec<AnyNodeArc> {
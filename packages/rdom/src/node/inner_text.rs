@@ -0,0 +1,177 @@
+//! An approximation of [innerText](https://html.spec.whatwg.org/multipage/dom.html#the-innertext-idl-attribute).
+//! There is no layout, so elements are assumed to have their default display, whitespace is
+//! collapsed as in normal flow, and elements which are not in a document are laid out as if
+//! they were.
+
+use super::concrete::ElementNodeArc;
+use super::contents::NodeContentsArc;
+use super::element::descendant_text;
+use crate::internal_prelude::*;
+
+use std::convert::TryFrom;
+
+/// Elements which are not rendered by default
+const HIDDEN_ELEMENTS: &[&str] = &["head", "noscript", "script", "style", "template", "title"];
+
+/// Elements which are displayed as blocks by default, and so are on their own lines
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "body",
+    "dd",
+    "details",
+    "dialog",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "html",
+    "legend",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "pre",
+    "section",
+    "summary",
+    "table",
+    "ul",
+];
+
+fn is_hidden(element: &ElementNodeArc, tag_name: &str) -> bool {
+    HIDDEN_ELEMENTS.contains(&tag_name) || element.has_attribute("hidden".to_owned())
+}
+
+/// The text being built, with the whitespace and line breaks which only appear if more text
+/// follows
+#[derive(Default)]
+struct Output {
+    text: String,
+    space: bool,
+    line_breaks: usize,
+}
+
+impl Output {
+    fn push(&mut self, c: char) {
+        if !self.text.is_empty() {
+            if self.line_breaks > 0 {
+                self.text
+                    .extend(std::iter::repeat_n('\n', self.line_breaks));
+            } else if self.space && !self.text.ends_with('\n') {
+                self.text.push(' ');
+            }
+        }
+        self.space = false;
+        self.line_breaks = 0;
+        self.text.push(c);
+    }
+
+    /// Adds text whose runs of whitespace collapse into a single space
+    fn collapsible_text(&mut self, text: &str) {
+        for c in text.chars() {
+            if c.is_ascii_whitespace() {
+                self.space = true;
+            } else {
+                self.push(c);
+            }
+        }
+    }
+
+    /// Adds text whose whitespace is kept
+    fn preserved_text(&mut self, text: &str) {
+        text.chars().for_each(|c| self.push(c));
+    }
+
+    /// Requires `count` line breaks between the text so far and the next text
+    fn line_breaks(&mut self, count: usize) {
+        self.line_breaks = self.line_breaks.max(count);
+    }
+}
+
+fn collect_node(node: &AnyNodeArc, output: &mut Output, preserve: bool) {
+    match &node.contents {
+        NodeContentsArc::Text(text) if preserve => {
+            output.preserved_text(&text.character_data.data())
+        }
+        NodeContentsArc::Text(text) => output.collapsible_text(&text.character_data.data()),
+        NodeContentsArc::Element(_) => {
+            if let Ok(element) = ElementNodeArc::try_from(node.clone()) {
+                collect_element(&element, output, preserve);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn collect_element(element: &ElementNodeArc, output: &mut Output, preserve: bool) {
+    let tag_name = element.contents.tag_name().to_ascii_lowercase();
+    if is_hidden(element, &tag_name) {
+        return;
+    }
+    if tag_name == "br" {
+        output.preserved_text("\n");
+        return;
+    }
+
+    let line_breaks = match tag_name.as_ref() {
+        "p" => 2,
+        tag_name if BLOCK_ELEMENTS.contains(&tag_name) => 1,
+        _ => 0,
+    };
+    output.line_breaks(line_breaks);
+    let preserve = preserve || tag_name == "pre";
+    for child in element.common.node_graph.static_child_nodes() {
+        collect_node(&child, output, preserve);
+    }
+
+    // Table cells are separated by tabs, and rows by line feeds
+    let next_tag_name = element
+        .common
+        .node_graph
+        .next_sibling()
+        .and_then(|sibling| ElementNodeArc::try_from(sibling).ok())
+        .map(|sibling| sibling.contents.tag_name().to_ascii_lowercase());
+    match (tag_name.as_ref(), next_tag_name.as_deref()) {
+        ("td" | "th", Some("td" | "th")) => output.preserved_text("\t"),
+        ("tr", Some("tr")) => output.preserved_text("\n"),
+        _ => {}
+    }
+    output.line_breaks(line_breaks);
+}
+
+/// Gives the text of `element` as it would be rendered, or its descendant text if it is not
+/// rendered
+pub(crate) fn inner_text(element: &ElementNodeArc) -> String {
+    let mut ancestor = Some(element.clone());
+    while let Some(current) = ancestor {
+        if is_hidden(&current, &current.contents.tag_name().to_ascii_lowercase()) {
+            return descendant_text(&element.clone().into());
+        }
+        ancestor = current
+            .common
+            .node_graph
+            .parent_node()
+            .and_then(|parent| ElementNodeArc::try_from(parent).ok());
+    }
+
+    let mut output = Output::default();
+    let preserve = element.contents.tag_name().eq_ignore_ascii_case("pre");
+    for child in element.common.node_graph.static_child_nodes() {
+        collect_node(&child, &mut output, preserve);
+    }
+    output.text
+}
@@ -1,23 +1,42 @@
 //! Types representing references to DOM nodes.
 
+use crate::behavior::child_node_prelude::ChildNodeBehaviorStorage;
 use crate::behavior::sandbox_member::SandboxMemberBehavior;
+use crate::mutation_observer::RegisteredObserver;
 use crate::node_list::NodeList;
-use crate::proxy_node_behavior;
+use crate::range;
 use crate::selector::Selector;
 use crate::{behavior::parent_node_prelude::ParentNodeBehaviorStorage, internal_prelude::*};
+use crate::{impl_event_target, proxy_event_target_behavior, proxy_node_behavior};
 
-use concrete::ElementNodeArc;
-use contents::{NodeContentsArc, NodeContentsWeak};
+use concrete::{
+    AttributeNodeArc, ConcreteNodeArc, DocumentNodeArc, DocumentNodeWeak, ElementNodeArc,
+    TextNodeArc,
+};
+use contents::{AttributeStore, NodeContentsArc, NodeContentsWeak, TextStore};
+use element::HTML_NAMESPACE;
 use graph_storage::NodeGraphStorage;
 
+pub(crate) mod activation;
+pub(crate) mod adoption;
 pub mod concrete;
 pub mod contents;
 pub mod element;
+pub(crate) mod focus;
+pub(crate) mod form;
 pub(crate) mod graph_storage;
+pub(crate) mod inner_text;
+pub mod quirks;
 
 pub(crate) use crate::behavior::node::NodeBehavior;
 
+crate::use_behaviors!(event_target);
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::RwLock;
 
 /// Marker trait implemented by all node storage classes.
 pub trait AnyNodeStore {}
@@ -36,9 +55,42 @@ pub struct NodeCommon {
 
     pub(crate) parent_node_behavior: ParentNodeBehaviorStorage,
 
+    pub(crate) child_node_behavior: ChildNodeBehaviorStorage,
+
+    pub(crate) event_target_behavior: EventTargetBehaviorStorage,
+
+    pub(crate) registered_observers: RwLock<Vec<RegisteredObserver>>,
+
+    /// The document the node belongs to, once a document has created or adopted it
+    owner_document: RwLock<Option<DocumentNodeWeak>>,
+
     // just a context without behavior wrapper for now
+    /// Context, pointing to the Sandbox. It changes when another sandbox's document adopts the
+    /// node.
+    context: RwLock<Weak<Sandbox>>,
+}
+
+impl NodeCommon {
     /// Context, pointing to the Sandbox
-    pub context: Weak<Sandbox>,
+    pub fn context(&self) -> Weak<Sandbox> {
+        self.context.read().unwrap().clone()
+    }
+
+    pub(crate) fn set_context(&self, context: Weak<Sandbox>) {
+        *self.context.write().unwrap() = context;
+    }
+
+    pub(crate) fn owner_document(&self) -> Option<DocumentNodeArc> {
+        self.owner_document
+            .read()
+            .unwrap()
+            .as_ref()
+            .and_then(|document| document.upgrade())
+    }
+
+    pub(crate) fn set_owner_document(&self, document: &DocumentNodeArc) {
+        *self.owner_document.write().unwrap() = Some(document.downgrade());
+    }
 }
 
 // The tree structure is that you have common
@@ -50,7 +102,7 @@ pub struct NodeCommon {
 // With this we would actually probably not even need nice
 
 /// a strong reference to any node (abstract nonspecific type)
-#[sourcegen::sourcegen(generator = "behave", script = "SandboxMember common.context")]
+#[sourcegen::sourcegen(generator = "behave", script = "SandboxMember common.context()")]
 // Generated. All manual edits to the block annotated with #[sourcegen...] will be discarded.
 #[derive(Clone)]
 pub struct AnyNodeArc {
@@ -62,7 +114,7 @@ pub struct AnyNodeArc {
 impl AnyNodeArc {
     /// gets `Weak<Sandbox>` to the `Sandbox` that it is in
     pub fn get_context(&self) -> Weak<Sandbox> {
-        self.common.context.clone()
+        self.common.context().clone()
     }
 }
 
@@ -99,7 +151,7 @@ impl fmt::Debug for AnyNodeWeak {
 }
 
 impl AnyNodeWeak {
-    fn upgrade(&self) -> Option<AnyNodeArc> {
+    pub(crate) fn upgrade(&self) -> Option<AnyNodeArc> {
         Some(AnyNodeArc {
             common: self.common.upgrade()?,
             contents: self.contents.upgrade()?,
@@ -108,31 +160,151 @@ impl AnyNodeWeak {
 }
 
 impl AnyNodeArc {
-    fn downgrade(&self) -> AnyNodeWeak {
+    pub(crate) fn downgrade(&self) -> AnyNodeWeak {
         AnyNodeWeak {
             common: Arc::downgrade(&self.common),
             contents: self.contents.downgrade(),
         }
     }
+
+    /// Checks whether two references are to the same node
+    pub(crate) fn ptr_eq(&self, other: &AnyNodeArc) -> bool {
+        Arc::ptr_eq(&self.common, &other.common)
+    }
 }
 
-impl AnyNodeArc {
-    pub(crate) fn new(context: Weak<Sandbox>, contents: NodeContentsArc) -> AnyNodeArc {
-        let common = Arc::new_cyclic(|construction_weak| NodeCommon {
-            node_graph: NodeGraphStorage::new(AnyNodeWeak {
-                common: construction_weak.clone(),
-                contents: contents.downgrade(),
-            }),
-            parent_node_behavior: ParentNodeBehaviorStorage::new(construction_weak.clone()),
-            context,
-        });
+impl PartialEq for AnyNodeArc {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr_eq(other)
+    }
+}
+
+impl Eq for AnyNodeArc {}
 
-        AnyNodeArc { contents, common }
+/// Nodes hash by identity, so they are safe to use as keys although their contents can change.
+/// Clippy's `mutable_key_type` lint does not know this, and has to be allowed where they are.
+impl Hash for AnyNodeArc {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.common).hash(state)
     }
+}
 
+/// An argument of methods like `before` and `append`, which insert nodes or text
+#[derive(Clone)]
+pub enum NodeOrString {
+    /// A node to insert. A document fragment is replaced by its children.
+    Node(AnyNodeArc),
+    /// Text to insert as a new text node
+    String(String),
+}
+
+impl From<AnyNodeArc> for NodeOrString {
+    fn from(node: AnyNodeArc) -> NodeOrString {
+        NodeOrString::Node(node)
+    }
+}
+
+impl<S: AnyNodeStore> From<ConcreteNodeArc<S>> for NodeOrString
+where
+    AnyNodeArc: From<ConcreteNodeArc<S>>,
+{
+    fn from(node: ConcreteNodeArc<S>) -> NodeOrString {
+        NodeOrString::Node(node.into())
+    }
+}
+
+impl From<String> for NodeOrString {
+    fn from(text: String) -> NodeOrString {
+        NodeOrString::String(text)
+    }
+}
+
+impl From<&str> for NodeOrString {
+    fn from(text: &str) -> NodeOrString {
+        NodeOrString::String(text.to_owned())
+    }
+}
+
+/// [Node.DOCUMENT_POSITION_DISCONNECTED](https://developer.mozilla.org/en-US/docs/Web/API/Node/compareDocumentPosition):
+/// the nodes are in different trees
+pub const DOCUMENT_POSITION_DISCONNECTED: u16 = 0x01;
+/// The other node precedes the node
+pub const DOCUMENT_POSITION_PRECEDING: u16 = 0x02;
+/// The other node follows the node
+pub const DOCUMENT_POSITION_FOLLOWING: u16 = 0x04;
+/// The other node is an ancestor of the node
+pub const DOCUMENT_POSITION_CONTAINS: u16 = 0x08;
+/// The other node is a descendant of the node
+pub const DOCUMENT_POSITION_CONTAINED_BY: u16 = 0x10;
+/// The order is arbitrary, e.g. between disconnected nodes
+pub const DOCUMENT_POSITION_IMPLEMENTATION_SPECIFIC: u16 = 0x20;
+
+/// The element an attribute node belongs to, if `node` is an attribute
+fn owner_element(node: &AnyNodeArc) -> Option<Option<AnyNodeArc>> {
+    match &node.contents {
+        NodeContentsArc::Attribute(attribute) => Some(
+            attribute
+                .owner_element()
+                .and_then(|owner| owner.upgrade())
+                .map(AnyNodeArc::from),
+        ),
+        _ => None,
+    }
+}
+
+/// Checks whether two nodes [equal](https://dom.spec.whatwg.org/#concept-node-equals): they
+/// have the same type, names, attributes, data and children
+fn nodes_equal(a: &AnyNodeArc, b: &AnyNodeArc) -> bool {
+    let attributes_equal = |a: &AttributeStore, b: &AttributeStore| {
+        a.namespace_uri() == b.namespace_uri()
+            && a.local_name() == b.local_name()
+            && a.value() == b.value()
+    };
+    let same_contents = match (&a.contents, &b.contents) {
+        (NodeContentsArc::Element(a), NodeContentsArc::Element(b)) => {
+            let (a_attributes, b_attributes) = (a.attributes(), b.attributes());
+            a.namespace_uri() == b.namespace_uri()
+                && a.tag_name() == b.tag_name()
+                && a_attributes.len() == b_attributes.len()
+                && a_attributes.iter().all(|a| {
+                    b_attributes
+                        .iter()
+                        .any(|b| attributes_equal(&a.contents, &b.contents))
+                })
+        }
+        (NodeContentsArc::Attribute(a), NodeContentsArc::Attribute(b)) => attributes_equal(a, b),
+        (NodeContentsArc::DocumentType(a), NodeContentsArc::DocumentType(b)) => {
+            a.name() == b.name() && a.public_id() == b.public_id() && a.system_id() == b.system_id()
+        }
+        (NodeContentsArc::ProcessingInstruction(a), NodeContentsArc::ProcessingInstruction(b)) => {
+            a.target() == b.target() && a.data() == b.data()
+        }
+        (a, b) => {
+            a.to_node_type() == b.to_node_type()
+                && a.character_data().map(|data| data.data())
+                    == b.character_data().map(|data| data.data())
+        }
+    };
+
+    let (a_children, b_children) = (
+        a.common.node_graph.static_child_nodes(),
+        b.common.node_graph.static_child_nodes(),
+    );
+    same_contents
+        && a_children.len() == b_children.len()
+        && a_children
+            .iter()
+            .zip(&b_children)
+            .all(|(a, b)| nodes_equal(a, b))
+}
+
+impl AnyNodeArc {
     proxy_node_behavior!();
+    proxy_event_target_behavior!();
 }
 
+impl_event_target!(AnyNodeArc, common.event_target_behavior);
+
 impl NodeBehavior for AnyNodeArc {
     fn first_child(&self) -> Option<AnyNodeArc> {
         self.common.node_graph.first_child()
@@ -151,15 +323,223 @@ impl NodeBehavior for AnyNodeArc {
     }
 
     fn clone_node(&self) -> AnyNodeArc {
-        let contents = self.contents.clone();
-        AnyNodeArc::new(self.get_context(), contents)
+        match self.owner_document() {
+            Some(document) => adoption::clone_node(self, &document, false),
+            // Documents, and nodes whose sandbox is gone, have no document to clone into
+            None => adoption::copy_node(self, self.get_context()),
+        }
     }
 
     fn node_type(&self) -> isize {
         self.contents.to_node_type().get_node_number()
     }
 
+    fn owner_document(&self) -> Option<DocumentNodeArc> {
+        if let NodeContentsArc::Document(_) = self.contents {
+            return None;
+        }
+        // Nodes built without a document belong to the document of their sandbox's window
+        self.common
+            .owner_document()
+            .or_else(|| Some(self.get_context().upgrade()?.window().document()))
+    }
+
     fn query_selector(&self, selector: &Selector) -> Result<Option<ElementNodeArc>, DomError> {
         self.common.parent_node_behavior.query_selector(selector)
     }
+
+    fn normalize(&self) {
+        let is_text = |node: &AnyNodeArc| matches!(node.contents, NodeContentsArc::Text(_));
+        for node in self.common.node_graph.descendants() {
+            // Nodes merged into an earlier one are no longer in the subtree
+            if !is_text(&node) || !range::is_inclusive_ancestor(self, &node) {
+                continue;
+            }
+            let parent = match node.common.node_graph.parent_node() {
+                Some(parent) => parent,
+                None => continue,
+            };
+            let character_data = match node.contents.character_data() {
+                Some(character_data) => character_data,
+                None => continue,
+            };
+            let mut length = character_data.length();
+            if length == 0 {
+                parent.common.node_graph.remove_child(&node);
+                continue;
+            }
+
+            let mut merged = Vec::new();
+            let mut sibling = node.common.node_graph.next_sibling();
+            while let Some(current) = sibling.filter(is_text) {
+                sibling = current.common.node_graph.next_sibling();
+                merged.push(current);
+            }
+            if merged.is_empty() {
+                continue;
+            }
+            let data: String = merged
+                .iter()
+                .filter_map(|current| current.contents.character_data())
+                .map(|character_data| character_data.data())
+                .collect();
+            // Appending at the end of the data cannot be out of range
+            let _ = character_data.replace_data(&node, length, 0, data);
+
+            for current in &merged {
+                range::text_merged(&node, current, length);
+                length += range::node_length(current);
+            }
+            for current in &merged {
+                parent.common.node_graph.remove_child(current);
+            }
+        }
+    }
+
+    fn is_same_node(&self, other: &AnyNodeArc) -> bool {
+        self.ptr_eq(other)
+    }
+
+    fn is_equal_node(&self, other: &AnyNodeArc) -> bool {
+        nodes_equal(self, other)
+    }
+
+    fn compare_document_position(&self, other: &AnyNodeArc) -> u16 {
+        if self.ptr_eq(other) {
+            return 0;
+        }
+        let other_owner = owner_element(other);
+        let own_owner = owner_element(self);
+
+        // Attributes of the same element are ordered by their position in it
+        if let (Some(Some(node1)), Some(Some(node2))) = (&other_owner, &own_owner) {
+            if node1.ptr_eq(node2) {
+                if let NodeContentsArc::Element(element) = &node2.contents {
+                    for attribute in element.attributes() {
+                        let attribute: AnyNodeArc = attribute.into();
+                        if attribute.ptr_eq(other) {
+                            return DOCUMENT_POSITION_IMPLEMENTATION_SPECIFIC
+                                | DOCUMENT_POSITION_PRECEDING;
+                        }
+                        if attribute.ptr_eq(self) {
+                            return DOCUMENT_POSITION_IMPLEMENTATION_SPECIFIC
+                                | DOCUMENT_POSITION_FOLLOWING;
+                        }
+                    }
+                }
+            }
+        }
+
+        // An attribute is compared by its element, unless it has none
+        let node1 = other_owner
+            .clone()
+            .flatten()
+            .unwrap_or_else(|| other.clone());
+        let node2 = own_owner.clone().flatten().unwrap_or_else(|| self.clone());
+        let order = match range::tree_order(&node1, &node2) {
+            Some(order) => order,
+            None => {
+                // Nodes in different trees are ordered consistently by the addresses of their
+                // roots
+                let root = |node: &AnyNodeArc| match node.common.node_graph.root_node() {
+                    Ok(root) => Arc::as_ptr(&root.common),
+                    Err(_) => Arc::as_ptr(&node.common),
+                };
+                let position = if root(&node1) < root(&node2) {
+                    DOCUMENT_POSITION_PRECEDING
+                } else {
+                    DOCUMENT_POSITION_FOLLOWING
+                };
+                return DOCUMENT_POSITION_DISCONNECTED
+                    | DOCUMENT_POSITION_IMPLEMENTATION_SPECIFIC
+                    | position;
+            }
+        };
+
+        let other_is_attribute = other_owner.is_some();
+        let self_is_attribute = own_owner.is_some();
+        let same_node = node1.ptr_eq(&node2);
+        if (!other_is_attribute && !same_node && range::is_inclusive_ancestor(&node1, &node2))
+            || (same_node && self_is_attribute)
+        {
+            DOCUMENT_POSITION_CONTAINS | DOCUMENT_POSITION_PRECEDING
+        } else if (!self_is_attribute && !same_node && range::is_inclusive_ancestor(&node2, &node1))
+            || (same_node && other_is_attribute)
+        {
+            DOCUMENT_POSITION_CONTAINED_BY | DOCUMENT_POSITION_FOLLOWING
+        } else if order == Ordering::Less {
+            DOCUMENT_POSITION_PRECEDING
+        } else {
+            DOCUMENT_POSITION_FOLLOWING
+        }
+    }
+
+    fn node_name(&self) -> String {
+        match &self.contents {
+            NodeContentsArc::Element(element) => {
+                let is_html_document = self
+                    .owner_document()
+                    .is_none_or(|document| document.is_html_document());
+                if is_html_document && element.namespace_uri().as_deref() == Some(HTML_NAMESPACE) {
+                    element.tag_name().to_ascii_uppercase()
+                } else {
+                    element.tag_name()
+                }
+            }
+            NodeContentsArc::Attribute(attribute) => attribute.name(),
+            NodeContentsArc::Text(_) => "#text".to_owned(),
+            NodeContentsArc::CDataSection(_) => "#cdata-section".to_owned(),
+            NodeContentsArc::ProcessingInstruction(instruction) => instruction.target(),
+            NodeContentsArc::Comment(_) => "#comment".to_owned(),
+            NodeContentsArc::Document(_) => "#document".to_owned(),
+            NodeContentsArc::DocumentType(doctype) => doctype.name(),
+            NodeContentsArc::DocumentFragment(_) => "#document-fragment".to_owned(),
+        }
+    }
+
+    fn node_value(&self) -> Option<String> {
+        match &self.contents {
+            NodeContentsArc::Attribute(attribute) => Some(attribute.value()),
+            contents => contents.character_data().map(|data| data.data()),
+        }
+    }
+
+    fn set_node_value(&self, value: String) {
+        if let Ok(attribute) = AttributeNodeArc::try_from(self.clone()) {
+            attribute.set_value(value);
+        } else if let Some(character_data) = self.contents.character_data() {
+            let length = character_data.length();
+            // Replacing from offset 0 cannot be out of range
+            let _ = character_data.replace_data(self, 0, length, value);
+        }
+    }
+
+    fn text_content(&self) -> Option<String> {
+        match &self.contents {
+            NodeContentsArc::Element(_) | NodeContentsArc::DocumentFragment(_) => {
+                Some(element::descendant_text(self))
+            }
+            NodeContentsArc::Document(_) | NodeContentsArc::DocumentType(_) => None,
+            _ => self.node_value(),
+        }
+    }
+
+    fn set_text_content(&self, text: String) {
+        match &self.contents {
+            NodeContentsArc::Element(_) | NodeContentsArc::DocumentFragment(_) => {
+                let node = (!text.is_empty()).then(|| {
+                    let node = TextNodeArc::new(self.get_context(), Arc::new(TextStore::new(text)));
+                    if let Some(document) = self.owner_document() {
+                        node.common.set_owner_document(&document);
+                    }
+                    node.into()
+                });
+                self.common
+                    .node_graph
+                    .replace_all(node.into_iter().collect());
+            }
+            NodeContentsArc::Document(_) | NodeContentsArc::DocumentType(_) => {}
+            _ => self.set_node_value(text),
+        }
+    }
 }
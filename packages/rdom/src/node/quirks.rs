@@ -0,0 +1,162 @@
+//! Determination of a document's [mode](https://dom.spec.whatwg.org/#concept-document-mode)
+//! from its doctype, as the HTML parser does in the
+//! ["initial" insertion mode](https://html.spec.whatwg.org/multipage/parsing.html#the-initial-insertion-mode).
+
+use super::concrete::DocumentNodeArc;
+use super::contents::{DocumentTypeStore, NodeContentsArc};
+use crate::internal_prelude::*;
+
+use std::convert::TryFrom;
+
+/// The [mode](https://dom.spec.whatwg.org/#concept-document-mode) of a document, which makes
+/// some features behave as in old browsers
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum QuirksMode {
+    /// Standards mode
+    #[default]
+    NoQuirks,
+    /// Quirks mode, e.g. for documents with a legacy or unknown doctype
+    Quirks,
+    /// Almost standards mode, for some transitional doctypes
+    LimitedQuirks,
+}
+
+/// Public identifiers which put a document in quirks mode when they are matched exactly
+const QUIRKY_PUBLIC_IDS: &[&str] = &[
+    "-//W3O//DTD W3 HTML Strict 3.0//EN//",
+    "-/W3C/DTD HTML 4.0 Transitional/EN",
+    "HTML",
+];
+
+/// Public identifier prefixes which put a document in quirks mode
+const QUIRKY_PUBLIC_ID_PREFIXES: &[&str] = &[
+    "+//Silmaril//dtd html Pro v0r11 19970101//",
+    "-//AS//DTD HTML 3.0 asWedit + extensions//",
+    "-//AdvaSoft Ltd//DTD HTML 3.0 asWedit + extensions//",
+    "-//IETF//DTD HTML 2.0 Level 1//",
+    "-//IETF//DTD HTML 2.0 Level 2//",
+    "-//IETF//DTD HTML 2.0 Strict Level 1//",
+    "-//IETF//DTD HTML 2.0 Strict Level 2//",
+    "-//IETF//DTD HTML 2.0 Strict//",
+    "-//IETF//DTD HTML 2.0//",
+    "-//IETF//DTD HTML 2.1E//",
+    "-//IETF//DTD HTML 3.0//",
+    "-//IETF//DTD HTML 3.2 Final//",
+    "-//IETF//DTD HTML 3.2//",
+    "-//IETF//DTD HTML 3//",
+    "-//IETF//DTD HTML Level 0//",
+    "-//IETF//DTD HTML Level 1//",
+    "-//IETF//DTD HTML Level 2//",
+    "-//IETF//DTD HTML Level 3//",
+    "-//IETF//DTD HTML Strict Level 0//",
+    "-//IETF//DTD HTML Strict Level 1//",
+    "-//IETF//DTD HTML Strict Level 2//",
+    "-//IETF//DTD HTML Strict Level 3//",
+    "-//IETF//DTD HTML Strict//",
+    "-//IETF//DTD HTML//",
+    "-//Metrius//DTD Metrius Presentational//",
+    "-//Microsoft//DTD Internet Explorer 2.0 HTML Strict//",
+    "-//Microsoft//DTD Internet Explorer 2.0 HTML//",
+    "-//Microsoft//DTD Internet Explorer 2.0 Tables//",
+    "-//Microsoft//DTD Internet Explorer 3.0 HTML Strict//",
+    "-//Microsoft//DTD Internet Explorer 3.0 HTML//",
+    "-//Microsoft//DTD Internet Explorer 3.0 Tables//",
+    "-//Netscape Comm. Corp.//DTD HTML//",
+    "-//Netscape Comm. Corp.//DTD Strict HTML//",
+    "-//O'Reilly and Associates//DTD HTML 2.0//",
+    "-//O'Reilly and Associates//DTD HTML Extended 1.0//",
+    "-//O'Reilly and Associates//DTD HTML Extended Relaxed 1.0//",
+    "-//SQ//DTD HTML 2.0 HoTMetaL + extensions//",
+    "-//SoftQuad Software//DTD HoTMetaL PRO 6.0::19990601::extensions to HTML 4.0//",
+    "-//SoftQuad//DTD HoTMetaL PRO 4.0::19971010::extensions to HTML 4.0//",
+    "-//Spyglass//DTD HTML 2.0 Extended//",
+    "-//Sun Microsystems Corp.//DTD HotJava HTML//",
+    "-//Sun Microsystems Corp.//DTD HotJava Strict HTML//",
+    "-//W3C//DTD HTML 3 1995-03-24//",
+    "-//W3C//DTD HTML 3.2 Draft//",
+    "-//W3C//DTD HTML 3.2 Final//",
+    "-//W3C//DTD HTML 3.2//",
+    "-//W3C//DTD HTML 3.2S Draft//",
+    "-//W3C//DTD HTML 4.0 Frameset//",
+    "-//W3C//DTD HTML 4.0 Transitional//",
+    "-//W3C//DTD HTML Experimental 19960712//",
+    "-//W3C//DTD HTML Experimental 970421//",
+    "-//W3C//DTD W3 HTML//",
+    "-//W3O//DTD W3 HTML 3.0//",
+    "-//WebTechs//DTD Mozilla HTML 2.0//",
+    "-//WebTechs//DTD Mozilla HTML//",
+];
+
+/// Public identifier prefixes which put a document in limited-quirks mode
+const LIMITED_QUIRKY_PUBLIC_ID_PREFIXES: &[&str] = &[
+    "-//W3C//DTD XHTML 1.0 Frameset//",
+    "-//W3C//DTD XHTML 1.0 Transitional//",
+];
+
+/// Public identifier prefixes which put a document in quirks mode without a system
+/// identifier, and in limited-quirks mode with one
+const HTML_401_PUBLIC_ID_PREFIXES: &[&str] = &[
+    "-//W3C//DTD HTML 4.01 Frameset//",
+    "-//W3C//DTD HTML 4.01 Transitional//",
+];
+
+fn starts_with_ignore_case(value: &str, prefix: &str) -> bool {
+    value
+        .get(..prefix.len())
+        .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+}
+
+impl QuirksMode {
+    /// Gives the mode of an HTML document with `doctype`. Empty identifiers are treated as
+    /// missing.
+    pub(crate) fn for_doctype(doctype: &DocumentTypeStore) -> QuirksMode {
+        let public_id = doctype.public_id();
+        let system_id = doctype.system_id();
+        let public_id_starts_with = |prefixes: &[&str]| {
+            prefixes
+                .iter()
+                .any(|p| starts_with_ignore_case(&public_id, p))
+        };
+
+        if !doctype.name().eq_ignore_ascii_case("html")
+            || QUIRKY_PUBLIC_IDS
+                .iter()
+                .any(|id| public_id.eq_ignore_ascii_case(id))
+            || system_id
+                .eq_ignore_ascii_case("http://www.ibm.com/data/dtd/v11/ibmxhtml1-transitional.dtd")
+            || public_id_starts_with(QUIRKY_PUBLIC_ID_PREFIXES)
+            || (system_id.is_empty() && public_id_starts_with(HTML_401_PUBLIC_ID_PREFIXES))
+        {
+            QuirksMode::Quirks
+        } else if public_id_starts_with(LIMITED_QUIRKY_PUBLIC_ID_PREFIXES)
+            || public_id_starts_with(HTML_401_PUBLIC_ID_PREFIXES)
+        {
+            QuirksMode::LimitedQuirks
+        } else {
+            QuirksMode::NoQuirks
+        }
+    }
+}
+
+/// Sets the mode of the document `node` when a doctype is among the `nodes` inserted into it.
+/// Documents are not parsed, so this stands in for the parser reading the doctype: it only
+/// applies to HTML documents, and only before the document element. `had_element` tells whether
+/// the document had an element child before the insertion.
+pub(crate) fn children_inserted(node: &AnyNodeArc, nodes: &[AnyNodeArc], had_element: bool) {
+    let document = match DocumentNodeArc::try_from(node.clone()) {
+        Ok(document) => document,
+        Err(_) => return,
+    };
+    if had_element || !document.is_html_document() {
+        return;
+    }
+    for child in nodes {
+        match &child.contents {
+            NodeContentsArc::DocumentType(doctype) => {
+                *document.contents.mode.write().unwrap() = QuirksMode::for_doctype(doctype);
+            }
+            NodeContentsArc::Element(_) => return,
+            _ => {}
+        }
+    }
+}
@@ -2,6 +2,11 @@
 //! and associated metadata.
 
 use crate::internal_prelude::*;
+use crate::node::concrete::ElementNodeArc;
+use crate::node::contents::NodeContentsArc;
+use crate::node::element::HtmlElementStore;
+
+use std::convert::TryFrom;
 
 /// Represents a [NodeList](https://developer.mozilla.org/en-US/docs/Web/API/NodeList) structure,
 /// which may be either "live" or "static". Note that these are not strongly retained by the
@@ -53,11 +58,7 @@ impl NodeList {
     pub fn length(&self) -> usize {
         match &self.nodelist_storage {
             NodeListStorage::Static(list) => list.len(),
-            NodeListStorage::Live(query) => match query {
-                Query::ChildNodes { children_of } => {
-                    children_of.common.node_graph.static_child_nodes().len()
-                }
-            },
+            NodeListStorage::Live(query) => query.nodes().len(),
         }
     }
 
@@ -65,14 +66,7 @@ impl NodeList {
     pub fn item(&self, index: usize) -> Option<AnyNodeArc> {
         match &self.nodelist_storage {
             NodeListStorage::Static(list) => list.get(index).cloned(),
-            NodeListStorage::Live(query) => match query {
-                Query::ChildNodes { children_of } => children_of
-                    .common
-                    .node_graph
-                    .static_child_nodes()
-                    .get(index)
-                    .cloned(),
-            },
+            NodeListStorage::Live(query) => query.nodes().get(index).cloned(),
         }
     }
 
@@ -92,5 +86,76 @@ pub(crate) enum NodeListStorage {
 }
 
 pub(crate) enum Query {
-    ChildNodes { children_of: AnyNodeArc },
+    ChildNodes {
+        children_of: AnyNodeArc,
+    },
+    /// The child elements of a node
+    Children {
+        children_of: AnyNodeArc,
+    },
+    /// The descendant elements of a node which match a filter, in tree order
+    Elements {
+        root: AnyNodeArc,
+        filter: ElementFilter,
+    },
+}
+
+/// Which elements an [Query::Elements] list contains
+pub(crate) enum ElementFilter {
+    /// [Document.forms](https://developer.mozilla.org/en-US/docs/Web/API/Document/forms)
+    Forms,
+    /// [Document.images](https://developer.mozilla.org/en-US/docs/Web/API/Document/images)
+    Images,
+    /// [Document.links](https://developer.mozilla.org/en-US/docs/Web/API/Document/links): a and
+    /// area elements with an href attribute
+    Links,
+    /// [Document.scripts](https://developer.mozilla.org/en-US/docs/Web/API/Document/scripts)
+    Scripts,
+    /// Elements whose name attribute has the given value
+    Name(String),
+}
+
+impl ElementFilter {
+    fn matches(&self, element: &ElementNodeArc) -> bool {
+        let html_element = element.contents.html_element();
+        match self {
+            ElementFilter::Forms => matches!(html_element, Some(HtmlElementStore::HtmlForm(_))),
+            ElementFilter::Images => matches!(html_element, Some(HtmlElementStore::HtmlImage(_))),
+            ElementFilter::Links => {
+                let is_link = matches!(html_element, Some(HtmlElementStore::HtmlAnchor(_)))
+                    || element.contents.tag_name().eq_ignore_ascii_case("area");
+                is_link && element.get_attribute("href".to_owned()).is_some()
+            }
+            ElementFilter::Scripts => matches!(html_element, Some(HtmlElementStore::HtmlScript(_))),
+            ElementFilter::Name(name) => {
+                element.get_attribute("name".to_owned()).as_ref() == Some(name)
+            }
+        }
+    }
+}
+
+impl Query {
+    /// Gives the nodes currently matching the query
+    fn nodes(&self) -> Vec<AnyNodeArc> {
+        match self {
+            Query::ChildNodes { children_of } => children_of.common.node_graph.static_child_nodes(),
+            Query::Children { children_of } => children_of
+                .common
+                .node_graph
+                .static_child_nodes()
+                .into_iter()
+                .filter(|node| matches!(node.contents, NodeContentsArc::Element(_)))
+                .collect(),
+            Query::Elements { root, filter } => root
+                .common
+                .node_graph
+                .descendants()
+                .into_iter()
+                .filter(|node| match ElementNodeArc::try_from(node.clone()) {
+                    Ok(element) => filter.matches(&element),
+                    Err(_) => false,
+                })
+                .collect(),
+        }
+    }
 }
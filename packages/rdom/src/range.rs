@@ -0,0 +1,404 @@
+//! [Ranges](https://developer.mozilla.org/en-US/docs/Web/API/Range), which select the part of a
+//! node tree between two boundary points, and the tree order comparisons they rely on.
+
+use crate::internal_prelude::*;
+use crate::node::contents::NodeContentsArc;
+
+use std::cmp::Ordering;
+use std::sync::RwLock;
+
+/// The [length](https://dom.spec.whatwg.org/#concept-node-length) of a node: the number of
+/// UTF-16 code units of its data for text-like nodes, otherwise its number of children
+pub(crate) fn node_length(node: &AnyNodeArc) -> usize {
+    match &node.contents {
+        NodeContentsArc::DocumentType(_) => 0,
+        contents => match contents.character_data() {
+            Some(character_data) => character_data.length(),
+            None => node.common.node_graph.child_count(),
+        },
+    }
+}
+
+/// The node and its ancestors, starting at the root
+fn inclusive_ancestors(node: &AnyNodeArc) -> Vec<AnyNodeArc> {
+    let mut ancestors = vec![node.clone()];
+    while let Some(parent) = ancestors.last().unwrap().common.node_graph.parent_node() {
+        ancestors.push(parent);
+    }
+    ancestors.reverse();
+    ancestors
+}
+
+/// Checks whether `ancestor` is `node` or one of its ancestors
+pub(crate) fn is_inclusive_ancestor(ancestor: &AnyNodeArc, node: &AnyNodeArc) -> bool {
+    let mut current = Some(node.clone());
+    while let Some(node) = current {
+        if node.ptr_eq(ancestor) {
+            return true;
+        }
+        current = node.common.node_graph.parent_node();
+    }
+    false
+}
+
+/// Compares the positions of two nodes in [tree order](https://dom.spec.whatwg.org/#concept-tree-order).
+/// Gives none if they are in different trees.
+pub(crate) fn tree_order(a: &AnyNodeArc, b: &AnyNodeArc) -> Option<Ordering> {
+    let a_ancestors = inclusive_ancestors(a);
+    let b_ancestors = inclusive_ancestors(b);
+    if !a_ancestors[0].ptr_eq(&b_ancestors[0]) {
+        return None;
+    }
+
+    let common = a_ancestors
+        .iter()
+        .zip(&b_ancestors)
+        .take_while(|(a, b)| a.ptr_eq(b))
+        .count();
+    match (a_ancestors.get(common), b_ancestors.get(common)) {
+        (None, None) => Some(Ordering::Equal),
+        // An ancestor precedes its descendants
+        (None, Some(_)) => Some(Ordering::Less),
+        (Some(_), None) => Some(Ordering::Greater),
+        (Some(a_child), Some(b_child)) => Some(
+            a_child
+                .common
+                .node_graph
+                .index()
+                .cmp(&b_child.common.node_graph.index()),
+        ),
+    }
+}
+
+/// A [boundary point](https://dom.spec.whatwg.org/#concept-range-bp)
+#[derive(Clone)]
+struct BoundaryPoint {
+    node: AnyNodeArc,
+    offset: usize,
+}
+
+impl BoundaryPoint {
+    /// Compares the [positions](https://dom.spec.whatwg.org/#concept-range-bp-position) of two
+    /// boundary points in the same tree
+    fn position(&self, other: &BoundaryPoint) -> Ordering {
+        if self.node.ptr_eq(&other.node) {
+            return self.offset.cmp(&other.offset);
+        }
+        if tree_order(&self.node, &other.node) == Some(Ordering::Greater) {
+            return other.position(self).reverse();
+        }
+
+        // If this point's node contains the other's, the child of it which contains the other
+        // point decides
+        let mut child = other.node.clone();
+        while let Some(parent) = child.common.node_graph.parent_node() {
+            if parent.ptr_eq(&self.node) {
+                return match child.common.node_graph.index() {
+                    Some(index) if index < self.offset => Ordering::Greater,
+                    _ => Ordering::Less,
+                };
+            }
+            child = parent;
+        }
+        Ordering::Less
+    }
+}
+
+struct Boundaries {
+    start: BoundaryPoint,
+    end: BoundaryPoint,
+}
+
+/// Applies `update` to both boundary points of the live ranges in the sandbox of `node`
+fn update_live_ranges(node: &AnyNodeArc, update: impl Fn(&mut BoundaryPoint)) {
+    let sandbox = match node.get_context().upgrade() {
+        Some(sandbox) => sandbox,
+        None => return,
+    };
+    for range in sandbox.live_ranges() {
+        let mut boundaries = range.boundaries.write().unwrap();
+        update(&mut boundaries.start);
+        update(&mut boundaries.end);
+    }
+}
+
+/// Updates live ranges after `count` code units at `offset` in the data of `node` were
+/// [replaced](https://dom.spec.whatwg.org/#concept-cd-replace) with `inserted` code units
+pub(crate) fn data_replaced(node: &AnyNodeArc, offset: usize, count: usize, inserted: usize) {
+    update_live_ranges(node, |point| {
+        if !point.node.ptr_eq(node) {
+            return;
+        }
+        if point.offset > offset + count {
+            point.offset = point.offset + inserted - count;
+        } else if point.offset > offset {
+            point.offset = offset;
+        }
+    });
+}
+
+/// Updates live ranges after a child was [inserted](https://dom.spec.whatwg.org/#concept-node-insert)
+/// into `parent` at `index`
+pub(crate) fn child_inserted(parent: &AnyNodeArc, index: usize) {
+    update_live_ranges(parent, |point| {
+        if point.node.ptr_eq(parent) && point.offset > index {
+            point.offset += 1;
+        }
+    });
+}
+
+/// Updates live ranges after `child` was [removed](https://dom.spec.whatwg.org/#concept-node-remove)
+/// from `parent`, where it was at `index`. Points inside it move to where it was.
+pub(crate) fn child_removed(parent: &AnyNodeArc, child: &AnyNodeArc, index: usize) {
+    update_live_ranges(parent, |point| {
+        if is_inclusive_ancestor(child, &point.node) {
+            point.node = parent.clone();
+            point.offset = index;
+        } else if point.node.ptr_eq(parent) && point.offset > index {
+            point.offset -= 1;
+        }
+    });
+}
+
+/// Updates live ranges after the text node `node` was [split](https://dom.spec.whatwg.org/#concept-text-split)
+/// at `offset`, and `new_node` inserted after it in `parent`. Points after the offset move to
+/// the new node.
+pub(crate) fn text_split(
+    node: &AnyNodeArc,
+    new_node: &AnyNodeArc,
+    parent: &AnyNodeArc,
+    offset: usize,
+) {
+    let after_node = node.common.node_graph.index().map(|index| index + 1);
+    update_live_ranges(node, |point| {
+        if point.node.ptr_eq(node) && point.offset > offset {
+            point.node = new_node.clone();
+            point.offset -= offset;
+        } else if point.node.ptr_eq(parent) && Some(point.offset) == after_node {
+            point.offset += 1;
+        }
+    });
+}
+
+/// Updates live ranges before the text node `merged` is [normalized](https://dom.spec.whatwg.org/#dom-node-normalize)
+/// into `node`, its previous text sibling, whose data it now follows from `length`
+pub(crate) fn text_merged(node: &AnyNodeArc, merged: &AnyNodeArc, length: usize) {
+    let parent = merged.common.node_graph.parent_node();
+    let index = merged.common.node_graph.index();
+    update_live_ranges(node, |point| {
+        if point.node.ptr_eq(merged) {
+            point.node = node.clone();
+            point.offset += length;
+        } else if parent
+            .as_ref()
+            .is_some_and(|parent| point.node.ptr_eq(parent))
+            && Some(point.offset) == index
+        {
+            point.node = node.clone();
+            point.offset = length;
+        }
+    });
+}
+
+/// A [Range](https://developer.mozilla.org/en-US/docs/Web/API/Range), as created by
+/// `Document::create_range`
+#[sourcegen::sourcegen(generator = "behave", script = "SandboxMember context")]
+// Generated. All manual edits to the block annotated with #[sourcegen...] will be discarded.
+pub struct Range {
+    /// SandboxMember implementation
+    context: Weak<Sandbox>,
+    /// The start and end of the range
+    boundaries: RwLock<Boundaries>,
+}
+
+#[sourcegen::generated]
+impl Range {
+    /// gets `Weak<Sandbox>` to the `Sandbox` that it is in
+    pub fn get_context(&self) -> Weak<Sandbox> {
+        self.context.clone()
+    }
+}
+
+#[sourcegen::generated]
+impl SandboxMemberBehavior for Range {
+    fn get_context(&self) -> Weak<Sandbox> {
+        self.get_context()
+    }
+}
+
+impl Range {
+    /// Creates a range collapsed at the start of `node`
+    pub(crate) fn new(context: Weak<Sandbox>, node: AnyNodeArc) -> Arc<Range> {
+        let point = BoundaryPoint { node, offset: 0 };
+        let range = Arc::new(Range {
+            context: context.clone(),
+            boundaries: RwLock::new(Boundaries {
+                start: point.clone(),
+                end: point,
+            }),
+        });
+        if let Some(sandbox) = context.upgrade() {
+            sandbox.register_range(&range);
+        }
+        range
+    }
+
+    /// [Range.startContainer](https://developer.mozilla.org/en-US/docs/Web/API/Range/startContainer)
+    pub fn start_container(&self) -> AnyNodeArc {
+        self.boundaries.read().unwrap().start.node.clone()
+    }
+
+    /// [Range.startOffset](https://developer.mozilla.org/en-US/docs/Web/API/Range/startOffset)
+    pub fn start_offset(&self) -> usize {
+        self.boundaries.read().unwrap().start.offset
+    }
+
+    /// [Range.endContainer](https://developer.mozilla.org/en-US/docs/Web/API/Range/endContainer)
+    pub fn end_container(&self) -> AnyNodeArc {
+        self.boundaries.read().unwrap().end.node.clone()
+    }
+
+    /// [Range.endOffset](https://developer.mozilla.org/en-US/docs/Web/API/Range/endOffset)
+    pub fn end_offset(&self) -> usize {
+        self.boundaries.read().unwrap().end.offset
+    }
+
+    /// [Range.collapsed](https://developer.mozilla.org/en-US/docs/Web/API/Range/collapsed):
+    /// whether the start and end are the same point
+    pub fn collapsed(&self) -> bool {
+        let boundaries = self.boundaries.read().unwrap();
+        boundaries.start.node.ptr_eq(&boundaries.end.node)
+            && boundaries.start.offset == boundaries.end.offset
+    }
+
+    /// [Range.commonAncestorContainer](https://developer.mozilla.org/en-US/docs/Web/API/Range/commonAncestorContainer):
+    /// the deepest node which contains both the start and the end
+    pub fn common_ancestor_container(&self) -> AnyNodeArc {
+        let boundaries = self.boundaries.read().unwrap();
+        let mut container = boundaries.start.node.clone();
+        while !is_inclusive_ancestor(&container, &boundaries.end.node) {
+            container = match container.common.node_graph.parent_node() {
+                Some(parent) => parent,
+                None => break,
+            };
+        }
+        container
+    }
+
+    /// Checks that a boundary point may be put at `offset` in `node`
+    fn boundary_point(node: &AnyNodeArc, offset: usize) -> Result<BoundaryPoint, DomError> {
+        if let NodeContentsArc::DocumentType(_) = node.contents {
+            return Err(DomError::InvalidNodeType);
+        }
+        if offset > node_length(node) {
+            return Err(DomError::Range);
+        }
+        Ok(BoundaryPoint {
+            node: node.clone(),
+            offset,
+        })
+    }
+
+    /// [Range.setStart](https://developer.mozilla.org/en-US/docs/Web/API/Range/setStart). If
+    /// the start would be after the end, or in another tree, the range is collapsed to it.
+    pub fn set_start(&self, node: &AnyNodeArc, offset: usize) -> Result<(), DomError> {
+        let point = Range::boundary_point(node, offset)?;
+        let mut boundaries = self.boundaries.write().unwrap();
+        if tree_order(&point.node, &boundaries.end.node).is_none()
+            || point.position(&boundaries.end) == Ordering::Greater
+        {
+            boundaries.end = point.clone();
+        }
+        boundaries.start = point;
+        Ok(())
+    }
+
+    /// [Range.setEnd](https://developer.mozilla.org/en-US/docs/Web/API/Range/setEnd). If the
+    /// end would be before the start, or in another tree, the range is collapsed to it.
+    pub fn set_end(&self, node: &AnyNodeArc, offset: usize) -> Result<(), DomError> {
+        let point = Range::boundary_point(node, offset)?;
+        let mut boundaries = self.boundaries.write().unwrap();
+        if tree_order(&point.node, &boundaries.start.node).is_none()
+            || point.position(&boundaries.start) == Ordering::Less
+        {
+            boundaries.start = point.clone();
+        }
+        boundaries.end = point;
+        Ok(())
+    }
+
+    /// The parent of `node` and its index in it, for setting a boundary next to `node`
+    fn position_in_parent(node: &AnyNodeArc) -> Result<(AnyNodeArc, usize), DomError> {
+        let parent = node
+            .common
+            .node_graph
+            .parent_node()
+            .ok_or(DomError::InvalidNodeType)?;
+        let index = node.common.node_graph.index().unwrap_or(0);
+        Ok((parent, index))
+    }
+
+    /// [Range.setStartBefore](https://developer.mozilla.org/en-US/docs/Web/API/Range/setStartBefore)
+    pub fn set_start_before(&self, node: &AnyNodeArc) -> Result<(), DomError> {
+        let (parent, index) = Range::position_in_parent(node)?;
+        self.set_start(&parent, index)
+    }
+
+    /// [Range.setStartAfter](https://developer.mozilla.org/en-US/docs/Web/API/Range/setStartAfter)
+    pub fn set_start_after(&self, node: &AnyNodeArc) -> Result<(), DomError> {
+        let (parent, index) = Range::position_in_parent(node)?;
+        self.set_start(&parent, index + 1)
+    }
+
+    /// [Range.setEndBefore](https://developer.mozilla.org/en-US/docs/Web/API/Range/setEndBefore)
+    pub fn set_end_before(&self, node: &AnyNodeArc) -> Result<(), DomError> {
+        let (parent, index) = Range::position_in_parent(node)?;
+        self.set_end(&parent, index)
+    }
+
+    /// [Range.setEndAfter](https://developer.mozilla.org/en-US/docs/Web/API/Range/setEndAfter)
+    pub fn set_end_after(&self, node: &AnyNodeArc) -> Result<(), DomError> {
+        let (parent, index) = Range::position_in_parent(node)?;
+        self.set_end(&parent, index + 1)
+    }
+
+    /// [Range.collapse](https://developer.mozilla.org/en-US/docs/Web/API/Range/collapse): moves
+    /// the end to the start, or the start to the end if `to_start` is false
+    pub fn collapse(&self, to_start: bool) {
+        let mut boundaries = self.boundaries.write().unwrap();
+        if to_start {
+            boundaries.end = boundaries.start.clone();
+        } else {
+            boundaries.start = boundaries.end.clone();
+        }
+    }
+
+    /// [Range.selectNode](https://developer.mozilla.org/en-US/docs/Web/API/Range/selectNode):
+    /// makes the range contain exactly `node`
+    pub fn select_node(&self, node: &AnyNodeArc) -> Result<(), DomError> {
+        let (parent, index) = Range::position_in_parent(node)?;
+        let mut boundaries = self.boundaries.write().unwrap();
+        boundaries.start = BoundaryPoint {
+            node: parent.clone(),
+            offset: index,
+        };
+        boundaries.end = BoundaryPoint {
+            node: parent,
+            offset: index + 1,
+        };
+        Ok(())
+    }
+
+    /// [Range.selectNodeContents](https://developer.mozilla.org/en-US/docs/Web/API/Range/selectNodeContents):
+    /// makes the range contain the contents of `node`
+    pub fn select_node_contents(&self, node: &AnyNodeArc) -> Result<(), DomError> {
+        let end = Range::boundary_point(node, node_length(node))?;
+        let mut boundaries = self.boundaries.write().unwrap();
+        boundaries.start = BoundaryPoint {
+            node: node.clone(),
+            offset: 0,
+        };
+        boundaries.end = end;
+        Ok(())
+    }
+}
@@ -1,13 +1,26 @@
 //! A sandbox represents a virtual browser tab. It contains a document and a window,
-//! as well as some configuration information for screen dimensions.
+//! as well as some configuration information for screen dimensions, and runs the tab's event
+//! loop on a virtual clock.
 
 use std::marker::PhantomData;
 
 use crate::internal_prelude::*;
 
-use crate::config::ScreenMetrics;
+use crate::config::{SandboxConfig, ScreenMetrics, UserPreferences};
+use crate::console::ConsoleEntry;
+use crate::event_loop::{EventLoop, Task, TaskHandle};
+use crate::fetch::RequestHandler;
+use crate::mutation_observer::MutationObserver;
+use crate::navigation::NavigationRequest;
 use crate::node::Buildable;
-use crate::window::Window;
+use crate::range::Range;
+use crate::window::{DomHighResTimeStamp, Window};
+
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime};
+
+/// The time between two frames rendered by `Sandbox::tick_frame`: one frame at 60Hz
+pub const FRAME_INTERVAL: Duration = Duration::from_nanos(1_000_000_000 / 60);
 
 /// A Builder<R> is a machine which can be used to build nodes of reference type R.
 pub struct Builder<R: Buildable> {
@@ -16,21 +29,33 @@ pub struct Builder<R: Buildable> {
 }
 
 /// A sandbox represents a virtual browser tab. It contains a document and a window,
-/// as well as some configuration information for screen dimensions.
+/// configured by a [SandboxConfig].
 #[derive(Clone)]
 pub struct Sandbox {
-    screen_metrics: ScreenMetrics,
     window: Arc<Window>,
+    navigation_requests: Arc<RwLock<Vec<NavigationRequest>>>,
+    pending_mutation_observers: Arc<RwLock<Vec<Arc<MutationObserver>>>>,
+    event_loop: Arc<EventLoop>,
+    request_handler: Arc<RwLock<Option<Arc<dyn RequestHandler>>>>,
+    console_entries: Arc<RwLock<Vec<ConsoleEntry>>>,
+    live_ranges: Arc<RwLock<Vec<Weak<Range>>>>,
 }
 
 impl Sandbox {
-    /// Creates a new sandbox (enclosure of a single DOM context)
-    pub fn new(screen_metrics: ScreenMetrics) -> Arc<Sandbox> {
+    /// Creates a new sandbox (enclosure of a single DOM context). A [ScreenMetrics] converts
+    /// into a configuration with defaults for everything else.
+    pub fn new(config: impl Into<SandboxConfig>) -> Arc<Sandbox> {
+        let config = config.into();
         Arc::new_cyclic(|sandbox_weak| -> Sandbox {
-            let win = Window::new(sandbox_weak.clone());
+            let win = Window::new(sandbox_weak.clone(), config);
             Sandbox {
-                screen_metrics,
                 window: win,
+                navigation_requests: Default::default(),
+                pending_mutation_observers: Default::default(),
+                event_loop: Default::default(),
+                request_handler: Default::default(),
+                console_entries: Default::default(),
+                live_ranges: Default::default(),
             }
         })
     }
@@ -42,6 +67,36 @@ impl Sandbox {
         self.window.clone()
     }
 
+    /// Changes the size of the viewport, screen or pixel ratio. Fires a resize event at the
+    /// window if the size of the viewport changed.
+    pub fn resize(&self, screen_metrics: ScreenMetrics) -> Result<(), DomError> {
+        self.window.set_screen_metrics(screen_metrics)?;
+        self.perform_microtask_checkpoint();
+        Ok(())
+    }
+
+    /// Changes the preferences of the simulated user, firing change events at media query lists
+    /// whose result changed
+    pub fn set_user_preferences(&self, user_preferences: UserPreferences) -> Result<(), DomError> {
+        self.window.set_user_preferences(user_preferences)?;
+        self.perform_microtask_checkpoint();
+        Ok(())
+    }
+
+    /// Takes the browser online or offline, firing an online or offline event at the window if
+    /// that changes its status
+    pub fn set_online(&self, online: bool) -> Result<(), DomError> {
+        self.window.set_online(online)?;
+        self.perform_microtask_checkpoint();
+        Ok(())
+    }
+
+    /// Gives the sandbox's configuration, including any changes made since it was created, e.g.
+    /// by [resize](Sandbox::resize)
+    pub fn config(&self) -> SandboxConfig {
+        self.window.config()
+    }
+
     /// Creates a builder for a specific type of node reference
     pub fn builder<T: Buildable>(self: &Arc<Self>) -> Builder<T> {
         Builder {
@@ -49,4 +104,121 @@ impl Sandbox {
             _phantom: PhantomData,
         }
     }
+
+    /// Gives the navigations requested by the document so far, oldest first
+    pub fn navigation_requests(&self) -> Vec<NavigationRequest> {
+        self.navigation_requests.read().unwrap().clone()
+    }
+
+    pub(crate) fn request_navigation(&self, request: NavigationRequest) {
+        self.navigation_requests.write().unwrap().push(request);
+    }
+
+    /// Gives the messages written to the window's console so far, oldest first
+    pub fn console_entries(&self) -> Vec<ConsoleEntry> {
+        self.console_entries.read().unwrap().clone()
+    }
+
+    pub(crate) fn record_console_entry(&self, entry: ConsoleEntry) {
+        self.console_entries.write().unwrap().push(entry);
+    }
+
+    /// Makes `range` live, so that it is updated when the nodes it is in change
+    pub(crate) fn register_range(&self, range: &Arc<Range>) {
+        let mut ranges = self.live_ranges.write().unwrap();
+        ranges.retain(|range| range.strong_count() > 0);
+        ranges.push(Arc::downgrade(range));
+    }
+
+    /// Gives the ranges created in the sandbox which have not been dropped
+    pub(crate) fn live_ranges(&self) -> Vec<Arc<Range>> {
+        let ranges = self.live_ranges.read().unwrap();
+        ranges.iter().filter_map(Weak::upgrade).collect()
+    }
+
+    /// Sets the handler which answers the requests made with `Window::fetch`. Until one is set,
+    /// every request fails as if the network were down.
+    pub fn set_request_handler(&self, handler: Arc<dyn RequestHandler>) {
+        *self.request_handler.write().unwrap() = Some(handler);
+    }
+
+    pub(crate) fn request_handler(&self) -> Option<Arc<dyn RequestHandler>> {
+        self.request_handler.read().unwrap().clone()
+    }
+
+    /// The virtual time elapsed since the sandbox was created. It only moves forward when
+    /// [advance_time](Sandbox::advance_time) is called.
+    pub fn now(&self) -> Duration {
+        self.event_loop.now()
+    }
+
+    /// The simulated wall-clock time: the configured start time plus the virtual time elapsed
+    pub fn current_time(&self) -> SystemTime {
+        self.config().start_time() + self.now()
+    }
+
+    /// [queueMicrotask](https://developer.mozilla.org/en-US/docs/Web/API/queueMicrotask):
+    /// runs `callback` at the next microtask checkpoint
+    pub fn queue_microtask(&self, callback: impl FnOnce() + Send + 'static) {
+        self.event_loop.queue_microtask(Box::new(callback));
+    }
+
+    /// Queues `callback` to run as a task, after the tasks already queued
+    pub fn queue_task(&self, callback: impl FnOnce() + Send + 'static) {
+        self.event_loop
+            .schedule_task(Duration::ZERO, Box::new(callback));
+    }
+
+    pub(crate) fn schedule_task(&self, delay: Duration, callback: Task) -> TaskHandle {
+        self.event_loop.schedule_task(delay, callback)
+    }
+
+    pub(crate) fn cancel_task(&self, handle: TaskHandle) -> bool {
+        self.event_loop.cancel_task(handle)
+    }
+
+    /// Performs a [microtask checkpoint](https://html.spec.whatwg.org/multipage/webappapis.html#perform-a-microtask-checkpoint):
+    /// runs queued microtasks, such as mutation observer callbacks, until there are none left
+    pub fn perform_microtask_checkpoint(&self) {
+        self.event_loop.perform_microtask_checkpoint();
+    }
+
+    /// Runs every task which is due at the current virtual time, including tasks they queue,
+    /// performing a microtask checkpoint after each one. Does not move the clock.
+    pub fn run_until_idle(&self) {
+        self.event_loop.run_until(self.now());
+    }
+
+    /// Moves the virtual clock forward by `duration`, running each task which becomes due along
+    /// the way at the time it is scheduled for
+    pub fn advance_time(&self, duration: Duration) {
+        self.event_loop.run_until(self.now() + duration);
+    }
+
+    /// Renders a frame: moves the virtual clock forward by [FRAME_INTERVAL], running the tasks
+    /// which become due, then runs the window's animation frame callbacks with the new time
+    pub fn tick_frame(&self) {
+        self.advance_time(FRAME_INTERVAL);
+        let now: DomHighResTimeStamp = self.now().as_secs_f64() * 1000.0;
+        self.window.run_animation_frame_callbacks(self, now);
+    }
+
+    /// [Queues a mutation observer microtask](https://dom.spec.whatwg.org/#queue-a-mutation-observer-compound-microtask)
+    /// unless one is already queued, and marks `observer` as having records to deliver
+    pub(crate) fn queue_mutation_observer_microtask(&self, observer: Arc<MutationObserver>) {
+        let mut pending = self.pending_mutation_observers.write().unwrap();
+        if pending.iter().any(|queued| Arc::ptr_eq(queued, &observer)) {
+            return;
+        }
+        if pending.is_empty() {
+            let pending_handle = self.pending_mutation_observers.clone();
+            self.event_loop.queue_microtask(Box::new(move || {
+                let observers = std::mem::take(&mut *pending_handle.write().unwrap());
+                for observer in observers {
+                    observer.notify();
+                }
+            }));
+        }
+        pending.push(observer);
+    }
 }
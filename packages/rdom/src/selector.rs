@@ -1,25 +1,97 @@
 use crate::error::DomError;
-use crate::node::{concrete::ElementNodeArc, AnyNodeArc};
+use crate::node::{concrete::ElementNodeArc, quirks::QuirksMode, AnyNodeArc};
 use std::convert::{TryFrom, TryInto};
 
-pub struct Selector(String);
+/// A pseudo-class which a selector may require of an element
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum PseudoClass {
+    Focus,
+    FocusWithin,
+}
+
+impl TryFrom<&str> for PseudoClass {
+    type Error = DomError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_ascii_lowercase().as_ref() {
+            "focus" => Ok(PseudoClass::Focus),
+            "focus-within" => Ok(PseudoClass::FocusWithin),
+            _ => Err(DomError::InvalidQuerySelector),
+        }
+    }
+}
+
+/// A compound selector: an optional tag name followed by ids, classes and pseudo-classes, e.g.
+/// `button#save.primary:focus`
+pub struct Selector {
+    tag_name: Option<String>,
+    ids: Vec<String>,
+    classes: Vec<String>,
+    pseudo_classes: Vec<PseudoClass>,
+}
+
+/// Checks whether `name` may be used as an id or class name in a selector
+fn is_valid_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || !c.is_ascii())
+}
 
 impl TryFrom<String> for Selector {
     type Error = DomError;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        // validate string (only allow [A-Z] and [0-9])
-        let value = value.to_uppercase();
-        let valid = value
+        let mut parts = value.split(':');
+        let compound = parts.next().unwrap_or_default();
+        let pseudo_classes = parts
+            .map(PseudoClass::try_from)
+            .collect::<Result<Vec<PseudoClass>, DomError>>()?;
+
+        // The tag name runs until the first id or class
+        let tag_end = compound.find(['#', '.']).unwrap_or(compound.len());
+        let tag_name = compound[..tag_end].to_uppercase();
+        let mut ids = Vec::new();
+        let mut classes = Vec::new();
+        let mut rest = &compound[tag_end..];
+        while let Some(kind) = rest.chars().next() {
+            let end = rest[1..].find(['#', '.']).map_or(rest.len(), |end| end + 1);
+            let name = &rest[1..end];
+            if !is_valid_identifier(name) {
+                return Err(DomError::InvalidQuerySelector);
+            }
+            match kind {
+                '#' => ids.push(name.to_owned()),
+                _ => classes.push(name.to_owned()),
+            }
+            rest = &rest[end..];
+        }
+
+        // validate tag name (only allow [A-Z] and [0-9])
+        let valid = tag_name
             .as_bytes()
             .iter()
-            .all(|&v| (b'A'..=b'Z').contains(&v) || (b'0'..=b'9').contains(&v));
+            .all(|&v| v.is_ascii_uppercase() || v.is_ascii_digit());
 
-        if valid {
-            Ok(Selector(value))
-        } else {
-            Err(DomError::InvalidQuerySelector)
+        if !valid
+            || (tag_name.is_empty()
+                && ids.is_empty()
+                && classes.is_empty()
+                && pseudo_classes.is_empty())
+        {
+            return Err(DomError::InvalidQuerySelector);
         }
+
+        Ok(Selector {
+            tag_name: if tag_name.is_empty() {
+                None
+            } else {
+                Some(tag_name)
+            },
+            ids,
+            classes,
+            pseudo_classes,
+        })
     }
 }
 
@@ -46,6 +118,51 @@ impl Selector {
     }
 
     pub fn is_selected_element(&self, element: ElementNodeArc) -> bool {
-        element.contents.tag_name() == self.0
+        if let Some(tag_name) = &self.tag_name {
+            if element.contents.tag_name() != *tag_name {
+                return false;
+            }
+        }
+
+        if !self.ids.is_empty() || !self.classes.is_empty() {
+            // Ids and classes match case-insensitively in quirks mode documents
+            let quirks = element
+                .owner_document()
+                .is_some_and(|document| document.mode() == QuirksMode::Quirks);
+            let name_matches = |a: &str, b: &str| {
+                if quirks {
+                    a.eq_ignore_ascii_case(b)
+                } else {
+                    a == b
+                }
+            };
+
+            let id = element.get_attribute("id".to_owned()).unwrap_or_default();
+            if !self
+                .ids
+                .iter()
+                .all(|selector_id| name_matches(selector_id, &id))
+            {
+                return false;
+            }
+            let class = element
+                .get_attribute("class".to_owned())
+                .unwrap_or_default();
+            let has_class = |name: &String| {
+                class
+                    .split_ascii_whitespace()
+                    .any(|element_class| name_matches(name, element_class))
+            };
+            if !self.classes.iter().all(has_class) {
+                return false;
+            }
+        }
+
+        self.pseudo_classes
+            .iter()
+            .all(|pseudo_class| match pseudo_class {
+                PseudoClass::Focus => element.has_focus(),
+                PseudoClass::FocusWithin => element.has_focus_within(),
+            })
     }
 }
@@ -0,0 +1,404 @@
+//! [Web Storage](https://developer.mozilla.org/en-US/docs/Web/API/Web_Storage_API):
+//! `localStorage` and `sessionStorage`. Items are kept per origin in a [SharedStorage], which
+//! sandboxes share by using the same one in their configuration. Changes made through one
+//! sandbox fire storage events at the windows of the other sandboxes with the same origin.
+
+use crate::event::{Event, EventInit, EventKind, StorageEventData};
+use crate::internal_prelude::*;
+use crate::window::Window;
+
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Where a [SharedStorage] persists its items
+pub trait StorageBackend: Send + Sync {
+    /// Loads the items stored for `origin`
+    fn load(&self, origin: &str) -> Vec<(String, String)>;
+
+    /// Replaces the items stored for `origin`
+    fn save(&self, origin: &str, items: &[(String, String)]);
+}
+
+/// A backend which keeps items in memory, for as long as it is alive
+#[derive(Debug, Default)]
+pub struct MemoryStorageBackend {
+    origins: Mutex<HashMap<String, Vec<(String, String)>>>,
+}
+
+impl MemoryStorageBackend {
+    /// Creates an empty backend
+    pub fn new() -> MemoryStorageBackend {
+        Default::default()
+    }
+}
+
+impl StorageBackend for MemoryStorageBackend {
+    fn load(&self, origin: &str) -> Vec<(String, String)> {
+        let origins = self.origins.lock().unwrap();
+        origins.get(origin).cloned().unwrap_or_default()
+    }
+
+    fn save(&self, origin: &str, items: &[(String, String)]) {
+        let mut origins = self.origins.lock().unwrap();
+        origins.insert(origin.to_owned(), items.to_vec());
+    }
+}
+
+/// A backend which persists items in a JSON file, as an object mapping each origin to an array
+/// of its `[key, value]` pairs, so that items keep their order. A missing file is treated as
+/// empty; failures to read or write the file are logged.
+#[derive(Debug)]
+pub struct JsonFileStorageBackend {
+    path: PathBuf,
+    /// Serializes reads and writes of the file
+    lock: Mutex<()>,
+}
+
+impl JsonFileStorageBackend {
+    /// Creates a backend which uses the file at `path`
+    pub fn new(path: impl Into<PathBuf>) -> JsonFileStorageBackend {
+        JsonFileStorageBackend {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn read(&self) -> Map<String, Value> {
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(error) => {
+                if error.kind() != ErrorKind::NotFound {
+                    log::warn!("could not read {}: {}", self.path.display(), error);
+                }
+                return Map::new();
+            }
+        };
+        match serde_json::from_str(&text) {
+            Ok(Value::Object(origins)) => origins,
+            _ => {
+                log::warn!("{} is not a JSON object", self.path.display());
+                Map::new()
+            }
+        }
+    }
+}
+
+impl StorageBackend for JsonFileStorageBackend {
+    fn load(&self, origin: &str) -> Vec<(String, String)> {
+        let _guard = self.lock.lock().unwrap();
+        match self.read().remove(origin) {
+            Some(Value::Array(items)) => items
+                .into_iter()
+                .filter_map(|item| match item {
+                    Value::Array(pair) => match pair.as_slice() {
+                        [Value::String(key), Value::String(value)] => {
+                            Some((key.clone(), value.clone()))
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn save(&self, origin: &str, items: &[(String, String)]) {
+        let _guard = self.lock.lock().unwrap();
+        let mut origins = self.read();
+        let items = items
+            .iter()
+            .map(|(key, value)| Value::from(vec![key.clone(), value.clone()]))
+            .collect();
+        origins.insert(origin.to_owned(), Value::Array(items));
+
+        let text = Value::Object(origins).to_string();
+        if let Err(error) = fs::write(&self.path, text) {
+            log::warn!("could not write {}: {}", self.path.display(), error);
+        }
+    }
+}
+
+struct SharedStorageInner {
+    backend: Arc<dyn StorageBackend>,
+    /// The most an origin may store, in UTF-16 code units of keys and values
+    quota: usize,
+    /// Items of each origin which has been used, loaded from the backend on first use. The
+    /// backend is never called while this is locked, so slow backends do not block readers.
+    origins: Mutex<HashMap<String, Vec<(String, String)>>>,
+    /// Serializes saves to the backend
+    saving: Mutex<()>,
+    /// Storage objects using this storage, which receive its storage events
+    storages: Mutex<Vec<Weak<Storage>>>,
+}
+
+/// Storage for the items of every origin, which sandboxes using it share. Cloning it gives
+/// another handle to the same storage.
+#[derive(Clone)]
+pub struct SharedStorage(Arc<SharedStorageInner>);
+
+impl SharedStorage {
+    /// The default quota of an origin, in UTF-16 code units
+    pub const DEFAULT_QUOTA: usize = 5 * 1024 * 1024;
+
+    /// Creates storage persisted by `backend`, allowing each origin `quota` UTF-16 code units
+    pub fn new(backend: Arc<dyn StorageBackend>, quota: usize) -> SharedStorage {
+        SharedStorage(Arc::new(SharedStorageInner {
+            backend,
+            quota,
+            origins: Default::default(),
+            saving: Default::default(),
+            storages: Default::default(),
+        }))
+    }
+
+    /// The items stored for `origin`
+    pub fn items(&self, origin: &str) -> Vec<(String, String)> {
+        self.with_items(origin, |items| items.clone())
+    }
+
+    /// Runs `f` on the items of `origin`, loading them first if they have not been used yet
+    fn with_items<R>(&self, origin: &str, f: impl FnOnce(&mut Vec<(String, String)>) -> R) -> R {
+        if !self.0.origins.lock().unwrap().contains_key(origin) {
+            let loaded = self.0.backend.load(origin);
+            // Another thread may have loaded the items meanwhile, and perhaps changed them
+            self.0
+                .origins
+                .lock()
+                .unwrap()
+                .entry(origin.to_owned())
+                .or_insert(loaded);
+        }
+        let mut origins = self.0.origins.lock().unwrap();
+        f(origins.get_mut(origin).unwrap())
+    }
+
+    /// Saves the current items of `origin` to the backend. Saves happen one at a time, and each
+    /// takes the items as they are when it starts, so the last save has the latest items.
+    fn save(&self, origin: &str) {
+        let _guard = self.0.saving.lock().unwrap();
+        let items = self.items(origin);
+        self.0.backend.save(origin, &items);
+    }
+
+    fn register(&self, storage: &Arc<Storage>) {
+        let mut storages = self.0.storages.lock().unwrap();
+        storages.retain(|storage| storage.strong_count() > 0);
+        storages.push(Arc::downgrade(storage));
+    }
+}
+
+impl Default for SharedStorage {
+    /// Creates empty in-memory storage with the default quota
+    fn default() -> SharedStorage {
+        SharedStorage::new(Arc::new(MemoryStorageBackend::new()), Self::DEFAULT_QUOTA)
+    }
+}
+
+impl PartialEq for SharedStorage {
+    fn eq(&self, other: &SharedStorage) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for SharedStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedStorage")
+            .field("quota", &self.0.quota)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The number of UTF-16 code units in `s`
+fn utf16_len(s: &str) -> usize {
+    s.encode_utf16().count()
+}
+
+/// A [Storage](https://developer.mozilla.org/en-US/docs/Web/API/Storage) object, giving a
+/// window access to the items of its document's origin
+#[sourcegen::sourcegen(generator = "behave", script = "SandboxMember context")]
+// Generated. All manual edits to the block annotated with #[sourcegen...] will be discarded.
+pub struct Storage {
+    /// SandboxMember implementation
+    context: Weak<Sandbox>,
+    window: Weak<Window>,
+    origin: String,
+    shared: SharedStorage,
+}
+
+#[sourcegen::generated]
+impl Storage {
+    /// gets `Weak<Sandbox>` to the `Sandbox` that it is in
+    pub fn get_context(&self) -> Weak<Sandbox> {
+        self.context.clone()
+    }
+}
+
+#[sourcegen::generated]
+impl SandboxMemberBehavior for Storage {
+    fn get_context(&self) -> Weak<Sandbox> {
+        self.get_context()
+    }
+}
+
+impl Storage {
+    pub(crate) fn new(
+        context: Weak<Sandbox>,
+        window: Weak<Window>,
+        origin: String,
+        shared: SharedStorage,
+    ) -> Arc<Storage> {
+        let storage = Arc::new(Storage {
+            context,
+            window,
+            origin,
+            shared: shared.clone(),
+        });
+        shared.register(&storage);
+        storage
+    }
+
+    /// [Storage.length](https://developer.mozilla.org/en-US/docs/Web/API/Storage/length)
+    pub fn length(&self) -> usize {
+        self.shared.with_items(&self.origin, |items| items.len())
+    }
+
+    /// [Storage.key](https://developer.mozilla.org/en-US/docs/Web/API/Storage/key): the name of
+    /// the item at `index`
+    pub fn key(&self, index: usize) -> Option<String> {
+        self.shared.with_items(&self.origin, |items| {
+            items.get(index).map(|(key, _)| key.clone())
+        })
+    }
+
+    /// [Storage.getItem](https://developer.mozilla.org/en-US/docs/Web/API/Storage/getItem)
+    pub fn get_item(&self, key: String) -> Option<String> {
+        self.shared.with_items(&self.origin, |items| {
+            items
+                .iter()
+                .find(|(item_key, _)| *item_key == key)
+                .map(|(_, value)| value.clone())
+        })
+    }
+
+    /// [Storage.setItem](https://developer.mozilla.org/en-US/docs/Web/API/Storage/setItem).
+    /// Fails with `DomError::QuotaExceeded` if the origin's items would no longer fit in the
+    /// quota.
+    pub fn set_item(&self, key: String, value: String) -> Result<(), DomError> {
+        let quota = self.shared.0.quota;
+        let old_value = self.shared.with_items(&self.origin, |items| {
+            let position = items.iter().position(|(item_key, _)| *item_key == key);
+            let old_value = position.map(|position| items[position].1.clone());
+            if old_value.as_ref() == Some(&value) {
+                return Ok(None);
+            }
+
+            let usage: usize = items
+                .iter()
+                .map(|(key, value)| utf16_len(key) + utf16_len(value))
+                .sum();
+            let freed = old_value
+                .as_ref()
+                .map_or(0, |old_value| utf16_len(&key) + utf16_len(old_value));
+            if usage - freed + utf16_len(&key) + utf16_len(&value) > quota {
+                return Err(DomError::QuotaExceeded);
+            }
+
+            match position {
+                Some(position) => items[position].1 = value.clone(),
+                None => items.push((key.clone(), value.clone())),
+            }
+            Ok(Some(old_value))
+        })?;
+
+        if let Some(old_value) = old_value {
+            self.shared.save(&self.origin);
+            self.broadcast(Some(key), old_value, Some(value));
+        }
+        Ok(())
+    }
+
+    /// [Storage.removeItem](https://developer.mozilla.org/en-US/docs/Web/API/Storage/removeItem)
+    pub fn remove_item(&self, key: String) {
+        let old_value = self.shared.with_items(&self.origin, |items| {
+            let position = items.iter().position(|(item_key, _)| *item_key == key)?;
+            let (_, old_value) = items.remove(position);
+            Some(old_value)
+        });
+
+        if let Some(old_value) = old_value {
+            self.shared.save(&self.origin);
+            self.broadcast(Some(key), Some(old_value), None);
+        }
+    }
+
+    /// [Storage.clear](https://developer.mozilla.org/en-US/docs/Web/API/Storage/clear)
+    pub fn clear(&self) {
+        let cleared = self.shared.with_items(&self.origin, |items| {
+            if items.is_empty() {
+                return false;
+            }
+            items.clear();
+            true
+        });
+
+        if cleared {
+            self.shared.save(&self.origin);
+            self.broadcast(None, None, None);
+        }
+    }
+
+    /// [Broadcasts](https://html.spec.whatwg.org/multipage/webstorage.html#concept-storage-broadcast)
+    /// a change: queues a task firing a storage event at the window of every other storage
+    /// object with the same origin
+    fn broadcast(&self, key: Option<String>, old_value: Option<String>, new_value: Option<String>) {
+        let url = match self.window.upgrade() {
+            Some(window) => window.document().url(),
+            None => return,
+        };
+        let storages = self.shared.0.storages.lock().unwrap().clone();
+        for storage in storages.iter().filter_map(Weak::upgrade) {
+            if std::ptr::eq(storage.as_ref(), self) || storage.origin != self.origin {
+                continue;
+            }
+            let context = match storage.context.upgrade() {
+                Some(context) => context,
+                None => continue,
+            };
+
+            let data = StorageEventData {
+                key: key.clone(),
+                old_value: old_value.clone(),
+                new_value: new_value.clone(),
+                url: url.clone(),
+                storage_area: Some(storage.clone()),
+            };
+            let window = storage.window.clone();
+            context.queue_task(move || {
+                if let Some(window) = window.upgrade() {
+                    let event = Event::new(
+                        "storage".to_owned(),
+                        EventInit {
+                            kind: EventKind::Storage(data),
+                            ..Default::default()
+                        },
+                    );
+                    let _ = window.dispatch_event(&event);
+                }
+            });
+        }
+    }
+}
+
+impl fmt::Debug for Storage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Storage")
+            .field("origin", &self.origin)
+            .finish_non_exhaustive()
+    }
+}
@@ -1,7 +1,18 @@
+//! Unit tests for rdom
+
 #![cfg(test)]
 
 use std::convert::{TryFrom, TryInto};
-use std::sync::{Arc, Weak};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use crate::error::DomError;
+use crate::event::{
+    AddEventListenerOptions, Event, EventInit, EventKind, EventListener, EventPhase,
+};
+use crate::mutation_observer::{
+    MutationCallback, MutationObserver, MutationObserverInit, MutationRecord, MutationRecordType,
+};
 
 use crate::node::concrete::*;
 use crate::node::contents::{AttributeStore, CommentStore, NodeContentsWeak, NodeType, TextStore};
@@ -11,8 +22,9 @@ use crate::node::element::{
 use crate::node::AnyNodeArc;
 use crate::sandbox::Sandbox;
 use crate::selector::Selector;
+use crate::user_event;
 use crate::{
-    config::ScreenMetrics,
+    config::{ColorScheme, SandboxConfig, ScreenMetrics, UserPreferences},
     node::contents::{
         CDataSectionStore, DocumentFragmentStore, DocumentTypeStore, ProcessingInstructionStore,
     },
@@ -92,9 +104,7 @@ fn test_text_node() {
     let text = test_node_creation!(
         ConcreteNodeArc<TextStore>,
         NodeType::Text,
-        |_node_weak| TextStore {
-            data: "test".to_owned()
-        },
+        |_node_weak| TextStore::new("test".to_owned()),
         sbox
     );
 
@@ -141,9 +151,7 @@ fn test_comment_node() {
     let _com = test_node_creation!(
         ConcreteNodeArc<CommentStore>,
         NodeType::Comment,
-        |_node_weak| CommentStore {
-            data: "test".to_owned()
-        },
+        |_node_weak| CommentStore::new("test".to_owned()),
         sbox
     );
 }
@@ -198,7 +206,7 @@ fn tag_name() {
 
 #[test]
 fn selector() {
-    let sbox = Sandbox::new(Default::default());
+    let sbox = Sandbox::new(SandboxConfig::default());
     let sbox = Arc::downgrade(&sbox);
 
     let button = ElementNodeArc::new_cyclic(sbox.clone(), |node_weak| {
@@ -227,7 +235,7 @@ fn selector() {
 
 #[test]
 fn cyclic_elements() {
-    let sbox = Sandbox::new(Default::default());
+    let sbox = Sandbox::new(SandboxConfig::default());
     let sbox = Arc::downgrade(&sbox);
 
     let button = ElementNodeArc::new_cyclic(sbox.clone(), |node_weak| {
@@ -265,7 +273,7 @@ fn cyclic_elements() {
 
 #[test]
 fn query_selector() {
-    let sbox_strong = Sandbox::new(Default::default());
+    let sbox_strong = Sandbox::new(SandboxConfig::default());
     let sbox = Arc::downgrade(&sbox_strong);
 
     let button = ElementNodeArc::new_cyclic(sbox.clone(), |node_weak| {
@@ -301,7 +309,7 @@ fn query_selector() {
 
 #[test]
 fn query_selector_child() {
-    let sbox_strong = Sandbox::new(Default::default());
+    let sbox_strong = Sandbox::new(SandboxConfig::default());
     let sbox = Arc::downgrade(&sbox_strong);
 
     let button = ElementNodeArc::new_cyclic(sbox.clone(), |node_weak| {
@@ -333,3 +341,2515 @@ fn query_selector_child() {
     assert!(Arc::ptr_eq(&qbody.common, &body.common));
     assert!(Arc::ptr_eq(&qbutton.common, &button.common));
 }
+
+fn record_events(
+    target: &AnyNodeArc,
+    event_types: &[&str],
+    capture: bool,
+) -> Arc<Mutex<Vec<String>>> {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    for event_type in event_types {
+        let log = log.clone();
+        let listener: EventListener =
+            Arc::new(move |event: &Event| log.lock().unwrap().push(event.event_type()));
+        target.add_event_listener(
+            event_type.to_string(),
+            listener,
+            AddEventListenerOptions {
+                capture,
+                ..Default::default()
+            },
+        );
+    }
+    log
+}
+
+#[test]
+fn event_capture_and_bubble() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let doc = sbox.window().document();
+    let body = doc.create_element("body".to_owned()).unwrap();
+    let button = doc.create_element("button".to_owned()).unwrap();
+    doc.append_child(body.clone().into());
+    body.append_child(button.clone().into());
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let log_listener = |name: &'static str, log: &Arc<Mutex<Vec<String>>>| -> EventListener {
+        let log = log.clone();
+        Arc::new(move |event: &Event| {
+            let phase = match event.event_phase() {
+                EventPhase::Capturing => "capture",
+                EventPhase::AtTarget => "target",
+                EventPhase::Bubbling => "bubble",
+                EventPhase::None => "none",
+            };
+            log.lock().unwrap().push(format!("{} {}", name, phase));
+        })
+    };
+
+    let capture = AddEventListenerOptions {
+        capture: true,
+        ..Default::default()
+    };
+    sbox.window()
+        .add_event_listener("ping".to_owned(), log_listener("window", &log), capture);
+    doc.add_event_listener("ping".to_owned(), log_listener("document", &log), capture);
+    body.add_event_listener(
+        "ping".to_owned(),
+        log_listener("body", &log),
+        Default::default(),
+    );
+    button.add_event_listener(
+        "ping".to_owned(),
+        log_listener("button", &log),
+        Default::default(),
+    );
+
+    let ping = || {
+        Event::new(
+            "ping".to_owned(),
+            EventInit {
+                bubbles: true,
+                cancelable: true,
+                ..Default::default()
+            },
+        )
+    };
+    assert!(button.dispatch_event(&ping()).unwrap());
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            "window capture",
+            "document capture",
+            "button target",
+            "body bubble"
+        ]
+    );
+
+    let canceler: EventListener = Arc::new(|event: &Event| event.prevent_default());
+    body.add_event_listener("ping".to_owned(), canceler.clone(), Default::default());
+    assert!(!button.dispatch_event(&ping()).unwrap());
+
+    body.remove_event_listener("ping".to_owned(), &canceler, false);
+    assert!(button.dispatch_event(&ping()).unwrap());
+}
+
+#[test]
+fn user_click_event_order() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let doc = sbox.window().document();
+    let button = sbox.builder::<ElementNodeArc>().build_button();
+    doc.append_child(button.clone().into());
+
+    let log = record_events(
+        &doc.clone().into(),
+        &[
+            "pointerdown",
+            "mousedown",
+            "focus",
+            "pointerup",
+            "mouseup",
+            "click",
+        ],
+        true,
+    );
+
+    user_event::click(&button).unwrap();
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            "pointerdown",
+            "mousedown",
+            "focus",
+            "pointerup",
+            "mouseup",
+            "click"
+        ]
+    );
+
+    log.lock().unwrap().clear();
+    button
+        .set_attribute("disabled".to_owned(), "".to_owned())
+        .unwrap();
+    user_event::click(&button).unwrap();
+    assert_eq!(*log.lock().unwrap(), vec!["pointerdown", "pointerup"]);
+
+    // Canceling pointerdown suppresses the compatibility mouse events and the focus change
+    log.lock().unwrap().clear();
+    let other = sbox.builder::<ElementNodeArc>().build_button();
+    doc.append_child(other.clone().into());
+    let canceler: EventListener = Arc::new(|event: &Event| event.prevent_default());
+    other.add_event_listener("pointerdown".to_owned(), canceler, Default::default());
+    user_event::click(&other).unwrap();
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec!["pointerdown", "pointerup", "click"]
+    );
+    assert!(doc.active_element().unwrap() != other);
+}
+
+#[test]
+fn user_type_text() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let doc = sbox.window().document();
+    let input = doc.create_element("input".to_owned()).unwrap();
+    doc.append_child(input.clone().into());
+
+    let log = record_events(
+        &input.clone().into(),
+        &["keydown", "keypress", "beforeinput", "input", "keyup"],
+        false,
+    );
+
+    user_event::type_text(&input, "ab").unwrap();
+    assert_eq!(input.value().unwrap(), "ab");
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            "keydown",
+            "keypress",
+            "beforeinput",
+            "input",
+            "keyup",
+            "keydown",
+            "keypress",
+            "beforeinput",
+            "input",
+            "keyup"
+        ]
+    );
+
+    user_event::press_key(&input, "Backspace").unwrap();
+    assert_eq!(input.value().unwrap(), "a");
+
+    let reject_digits: EventListener = Arc::new(|event: &Event| {
+        if let EventKind::Input(data) = event.kind() {
+            if data
+                .data
+                .as_deref()
+                .is_some_and(|d| d.chars().all(|c| c.is_ascii_digit()))
+            {
+                event.prevent_default();
+            }
+        }
+    });
+    input.add_event_listener("beforeinput".to_owned(), reject_digits, Default::default());
+    user_event::type_text(&input, "1b2").unwrap();
+    assert_eq!(input.value().unwrap(), "ab");
+}
+
+#[test]
+fn user_tab_and_select_option() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let doc = sbox.window().document();
+    let builder = sbox.builder::<ElementNodeArc>();
+    let input = builder.build_input();
+    let hidden = builder.build_input();
+    hidden
+        .set_attribute("type".to_owned(), "hidden".to_owned())
+        .unwrap();
+    let select = builder.build_select();
+    for value in ["red", "green"].iter() {
+        let option = builder.build_option();
+        option
+            .set_attribute("value".to_owned(), value.to_string())
+            .unwrap();
+        select.append_child(option.into());
+    }
+    doc.append_child(input.clone().into());
+    doc.append_child(hidden.into());
+    doc.append_child(select.clone().into());
+
+    let focus_log = record_events(&select.clone().into(), &["focus", "blur"], false);
+
+    user_event::tab(&doc, false).unwrap();
+    user_event::tab(&doc, false).unwrap();
+    user_event::tab(&doc, true).unwrap();
+    assert_eq!(*focus_log.lock().unwrap(), vec!["focus", "blur"]);
+
+    let change_log = record_events(&select.clone().into(), &["input", "change"], false);
+    user_event::select_option(&select, &["green"]).unwrap();
+    assert_eq!(select.value().unwrap(), "green");
+    assert_eq!(*change_log.lock().unwrap(), vec!["input", "change"]);
+    assert!(user_event::select_option(&input, &["green"]).is_err());
+}
+
+#[test]
+fn checkbox_activation_and_rollback() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let doc = sbox.window().document();
+    let checkbox = doc.create_element("input".to_owned()).unwrap();
+    checkbox
+        .set_attribute("type".to_owned(), "checkbox".to_owned())
+        .unwrap();
+    let label = doc.create_element("label".to_owned()).unwrap();
+    label.append_child(checkbox.clone().into());
+    doc.append_child(label.clone().into());
+
+    let change_log = record_events(&checkbox.clone().into(), &["input", "change"], false);
+    let seen_checked = Arc::new(Mutex::new(Vec::new()));
+    let seen = seen_checked.clone();
+    let checkbox_ref = checkbox.clone();
+    let listener: EventListener = Arc::new(move |_: &Event| {
+        seen.lock().unwrap().push(checkbox_ref.checked().unwrap());
+    });
+    checkbox.add_event_listener("click".to_owned(), listener, Default::default());
+
+    user_event::click(&checkbox).unwrap();
+    assert!(checkbox.checked().unwrap());
+    assert_eq!(*seen_checked.lock().unwrap(), vec![true]);
+    assert_eq!(*change_log.lock().unwrap(), vec!["input", "change"]);
+
+    // Clicking the label forwards the click to its control
+    user_event::click(&label).unwrap();
+    assert!(!checkbox.checked().unwrap());
+
+    let canceler: EventListener = Arc::new(|event: &Event| event.prevent_default());
+    doc.add_event_listener("click".to_owned(), canceler, Default::default());
+    change_log.lock().unwrap().clear();
+    user_event::click(&checkbox).unwrap();
+    assert!(!checkbox.checked().unwrap());
+    assert_eq!(*seen_checked.lock().unwrap(), vec![true, false, true]);
+    assert!(change_log.lock().unwrap().is_empty());
+}
+
+#[test]
+fn radio_group_activation() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let doc = sbox.window().document();
+    let form = doc.create_element("form".to_owned()).unwrap();
+    doc.append_child(form.clone().into());
+
+    let radios: Vec<ElementNodeArc> = (0..3)
+        .map(|_| {
+            let radio = doc.create_element("input".to_owned()).unwrap();
+            radio
+                .set_attribute("type".to_owned(), "radio".to_owned())
+                .unwrap();
+            radio
+                .set_attribute("name".to_owned(), "size".to_owned())
+                .unwrap();
+            form.append_child(radio.clone().into());
+            radio
+        })
+        .collect();
+
+    user_event::click(&radios[0]).unwrap();
+    user_event::click(&radios[2]).unwrap();
+    let checked: Vec<bool> = radios.iter().map(|r| r.checked().unwrap()).collect();
+    assert_eq!(checked, vec![false, false, true]);
+
+    let canceler: EventListener = Arc::new(|event: &Event| event.prevent_default());
+    radios[1].add_event_listener("click".to_owned(), canceler, Default::default());
+    user_event::click(&radios[1]).unwrap();
+    let checked: Vec<bool> = radios.iter().map(|r| r.checked().unwrap()).collect();
+    assert_eq!(checked, vec![false, false, true]);
+
+    // A radio button outside the form joins its group through the form attribute
+    form.set_attribute("id".to_owned(), "sizes".to_owned())
+        .unwrap();
+    let outside = doc.create_element("input".to_owned()).unwrap();
+    for (name, value) in &[("type", "radio"), ("name", "size"), ("form", "sizes")] {
+        outside
+            .set_attribute(name.to_string(), value.to_string())
+            .unwrap();
+    }
+    doc.append_child(outside.clone().into());
+    user_event::click(&outside).unwrap();
+    assert!(outside.checked().unwrap());
+    assert!(!radios[2].checked().unwrap());
+}
+
+#[test]
+fn form_submission_and_links() {
+    use crate::navigation::NavigationSource;
+
+    let sbox = Sandbox::new(
+        SandboxConfig::builder()
+            .url("https://example.com/app/page".to_owned())
+            .build()
+            .unwrap(),
+    );
+    let doc = sbox.window().document();
+    let form = doc.create_element("form".to_owned()).unwrap();
+    form.set_attribute("action".to_owned(), "/search".to_owned())
+        .unwrap();
+    let input = doc.create_element("input".to_owned()).unwrap();
+    input
+        .set_attribute("name".to_owned(), "q".to_owned())
+        .unwrap();
+    // Invalid types fall back to the default, "text" for inputs and "submit" for buttons
+    input
+        .set_attribute("type".to_owned(), "".to_owned())
+        .unwrap();
+    let button = doc.create_element("button".to_owned()).unwrap();
+    button
+        .set_attribute("type".to_owned(), "bogus".to_owned())
+        .unwrap();
+    form.append_child(input.clone().into());
+    form.append_child(button.clone().into());
+    doc.append_child(form.clone().into());
+
+    user_event::type_text(&input, "rust dom").unwrap();
+    user_event::click(&button).unwrap();
+    let requests = sbox.navigation_requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].url, "https://example.com/search?q=rust+dom");
+    assert_eq!(requests[0].method, "GET");
+    assert_eq!(requests[0].source, NavigationSource::Form);
+
+    // Implicit submission through the Enter key
+    user_event::press_key(&input, "Enter").unwrap();
+    assert_eq!(sbox.navigation_requests().len(), 2);
+
+    // The action is resolved against the document URL, keeping its fragment
+    for (action, url) in [
+        (
+            "/search#results",
+            "https://example.com/search?q=rust+dom#results",
+        ),
+        ("", "https://example.com/app/page?q=rust+dom"),
+        ("results", "https://example.com/app/results?q=rust+dom"),
+    ] {
+        form.set_attribute("action".to_owned(), action.to_owned())
+            .unwrap();
+        user_event::click(&button).unwrap();
+        assert_eq!(sbox.navigation_requests().last().unwrap().url, url);
+    }
+    assert_eq!(sbox.navigation_requests().len(), 5);
+
+    let canceler: EventListener = Arc::new(|event: &Event| event.prevent_default());
+    form.add_event_listener("submit".to_owned(), canceler, Default::default());
+    user_event::click(&button).unwrap();
+    assert_eq!(sbox.navigation_requests().len(), 5);
+
+    let link = doc.create_element("a".to_owned()).unwrap();
+    link.set_attribute("href".to_owned(), "/about".to_owned())
+        .unwrap();
+    let span = doc.create_element("span".to_owned()).unwrap();
+    link.append_child(span.clone().into());
+    doc.append_child(link.into());
+    user_event::click(&span).unwrap();
+    let requests = sbox.navigation_requests();
+    assert_eq!(requests.len(), 6);
+    assert_eq!(requests[5].url, "https://example.com/about");
+    assert_eq!(requests[5].source, NavigationSource::Link);
+}
+
+#[test]
+fn focus_management() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let doc = sbox.window().document();
+    let html = doc.create_element("html".to_owned()).unwrap();
+    let body = doc.create_element("body".to_owned()).unwrap();
+    let form = doc.create_element("form".to_owned()).unwrap();
+    let first = doc.create_element("input".to_owned()).unwrap();
+    let second = doc.create_element("button".to_owned()).unwrap();
+    doc.append_child(html.clone().into());
+    html.append_child(body.clone().into());
+    body.append_child(form.clone().into());
+    form.append_child(first.clone().into());
+    form.append_child(second.clone().into());
+
+    assert!(doc.active_element().unwrap() == body);
+    assert!(!doc.has_focus());
+
+    let log = record_events(
+        &doc.clone().into(),
+        &["focus", "blur", "focusin", "focusout"],
+        true,
+    );
+    first.focus().unwrap();
+    second.focus().unwrap();
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec!["focus", "focusin", "blur", "focusout", "focus", "focusin"]
+    );
+    assert!(doc.active_element().unwrap() == second);
+
+    let focused = Selector::try_from("button:focus").unwrap();
+    let within = Selector::try_from(":focus-within").unwrap();
+    assert!(doc.query_selector(&focused).unwrap().unwrap() == second);
+    assert!(doc.query_selector(&within).unwrap().unwrap() == html);
+    assert!(Selector::try_from("button:hover").is_err());
+
+    second.blur().unwrap();
+    assert!(doc.active_element().unwrap() == body);
+    assert!(doc.query_selector(&focused).unwrap().is_none());
+
+    // Non-focusable elements ignore focus()
+    form.focus().unwrap();
+    assert!(!doc.has_focus());
+}
+
+#[test]
+fn sequential_navigation_order() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let doc = sbox.window().document();
+    let builder = sbox.builder::<ElementNodeArc>();
+
+    let plain = builder.build_button();
+    let second = builder.build_input();
+    second
+        .set_attribute("tabindex".to_owned(), "2".to_owned())
+        .unwrap();
+    let skipped = builder.build_button();
+    skipped
+        .set_attribute("tabindex".to_owned(), "-1".to_owned())
+        .unwrap();
+    let first = builder.build_unknown("div".to_owned());
+    first
+        .set_attribute("tabindex".to_owned(), "1".to_owned())
+        .unwrap();
+    let disabled = builder.build_button();
+    disabled
+        .set_attribute("disabled".to_owned(), "".to_owned())
+        .unwrap();
+    for element in [&plain, &second, &skipped, &first, &disabled].iter() {
+        doc.append_child((*element).clone().into());
+    }
+
+    let order = doc.sequential_navigation_order();
+    assert!(order == vec![first.clone(), second.clone(), plain.clone()]);
+
+    user_event::tab(&doc, false).unwrap();
+    assert!(doc.active_element().unwrap() == first);
+    user_event::tab(&doc, false).unwrap();
+    user_event::tab(&doc, false).unwrap();
+    assert!(doc.active_element().unwrap() == plain);
+    user_event::tab(&doc, true).unwrap();
+    assert!(doc.active_element().unwrap() == second);
+
+    // Elements with a negative tabindex can still be focused directly
+    skipped.focus().unwrap();
+    assert!(doc.active_element().unwrap() == skipped);
+
+    // Removing the focused element from the document drops focus
+    doc.common.node_graph.remove_child(&skipped.clone().into());
+    assert!(!doc.has_focus());
+}
+
+/// Creates an observer which logs a description of each record it is delivered
+fn record_mutations(sbox: &Arc<Sandbox>) -> (Arc<MutationObserver>, Arc<Mutex<Vec<String>>>) {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let log_handle = log.clone();
+    let callback: MutationCallback = Arc::new(
+        move |records: &[MutationRecord], _observer: &MutationObserver| {
+            for record in records {
+                log_handle.lock().unwrap().push(format!(
+                    "{:?} {} {}+{}- {:?}",
+                    record.record_type(),
+                    record.target().node_type(),
+                    record.added_nodes().length(),
+                    record.removed_nodes().length(),
+                    record.attribute_name().or_else(|| record.old_value()),
+                ));
+            }
+        },
+    );
+    (MutationObserver::new(sbox, callback), log)
+}
+
+#[test]
+fn mutation_observer_delivery() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let doc = sbox.window().document();
+    let body = doc.create_element("body".to_owned()).unwrap();
+    let div = doc.create_element("div".to_owned()).unwrap();
+    let text = doc.create_text_node("hello".to_owned()).unwrap();
+    body.append_child(div.clone().into());
+
+    let (observer, log) = record_mutations(&sbox);
+    observer
+        .observe(
+            &body.clone().into(),
+            MutationObserverInit {
+                child_list: true,
+                subtree: true,
+                character_data_old_value: Some(true),
+                attribute_filter: Some(vec!["id".to_owned()]),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    div.append_child(text.clone().into());
+    div.set_attribute("id".to_owned(), "x".to_owned()).unwrap();
+    div.set_attribute("class".to_owned(), "y".to_owned())
+        .unwrap();
+    text.set_data("world".to_owned());
+    assert!(log.lock().unwrap().is_empty());
+
+    sbox.perform_microtask_checkpoint();
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            "ChildList 1 1+0- None",
+            "Attributes 1 0+0- Some(\"id\")",
+            "CharacterData 3 0+0- Some(\"hello\")",
+        ]
+    );
+
+    // Records taken by take_records are not delivered
+    body.common.node_graph.remove_child(&div.clone().into());
+    let records = observer.take_records();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].record_type(), MutationRecordType::ChildList);
+    assert!(Arc::ptr_eq(
+        &records[0].removed_nodes().item(0).unwrap().common,
+        &div.common
+    ));
+
+    observer.disconnect();
+    body.append_child(div.into());
+    sbox.perform_microtask_checkpoint();
+    assert_eq!(log.lock().unwrap().len(), 3);
+}
+
+#[test]
+fn mutation_observer_options() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let doc = sbox.window().document();
+    let div = doc.create_element("div".to_owned()).unwrap();
+    let (observer, _log) = record_mutations(&sbox);
+
+    assert!(observer
+        .observe(&div.clone().into(), Default::default())
+        .is_err());
+    assert!(observer
+        .observe(
+            &div.clone().into(),
+            MutationObserverInit {
+                attributes: Some(false),
+                attribute_old_value: Some(true),
+                ..Default::default()
+            }
+        )
+        .is_err());
+
+    observer
+        .observe(
+            &div.clone().into(),
+            MutationObserverInit {
+                attribute_old_value: Some(true),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    div.set_attribute("title".to_owned(), "a".to_owned())
+        .unwrap();
+    div.set_attribute("title".to_owned(), "b".to_owned())
+        .unwrap();
+    div.remove_attribute("title".to_owned()).unwrap();
+    let old_values: Vec<Option<String>> = observer
+        .take_records()
+        .iter()
+        .map(|record| record.old_value())
+        .collect();
+    assert_eq!(
+        old_values,
+        vec![None, Some("a".to_owned()), Some("b".to_owned())]
+    );
+}
+
+#[test]
+fn event_loop_ordering() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let log: Arc<Mutex<Vec<String>>> = Default::default();
+
+    let log_handle = log.clone();
+    let sbox_handle = Arc::downgrade(&sbox);
+    sbox.queue_task(move || {
+        log_handle.lock().unwrap().push("task 1".to_owned());
+        let inner_log = log_handle.clone();
+        sbox_handle
+            .upgrade()
+            .unwrap()
+            .queue_microtask(move || inner_log.lock().unwrap().push("microtask".to_owned()));
+    });
+    let log_handle = log.clone();
+    sbox.queue_task(move || log_handle.lock().unwrap().push("task 2".to_owned()));
+    let log_handle = log.clone();
+    sbox.schedule_task(
+        Duration::from_millis(50),
+        Box::new(move || log_handle.lock().unwrap().push("delayed".to_owned())),
+    );
+
+    assert!(log.lock().unwrap().is_empty());
+    sbox.run_until_idle();
+    assert_eq!(*log.lock().unwrap(), vec!["task 1", "microtask", "task 2"]);
+
+    sbox.advance_time(Duration::from_millis(49));
+    assert_eq!(log.lock().unwrap().len(), 3);
+    sbox.advance_time(Duration::from_millis(1));
+    assert_eq!(log.lock().unwrap().last().unwrap(), "delayed");
+    assert_eq!(sbox.now(), Duration::from_millis(50));
+
+    // A panicking microtask does not stop later checkpoints from running
+    sbox.queue_microtask(|| panic!("microtask failed"));
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        sbox.perform_microtask_checkpoint()
+    }));
+    assert!(result.is_err());
+    let log_handle = log.clone();
+    sbox.queue_microtask(move || log_handle.lock().unwrap().push("recovered".to_owned()));
+    sbox.perform_microtask_checkpoint();
+    assert_eq!(log.lock().unwrap().last().unwrap(), "recovered");
+}
+
+#[test]
+fn mutation_observer_microtask() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let doc = sbox.window().document();
+    let button = doc.create_element("button".to_owned()).unwrap();
+    let (observer, log) = record_mutations(&sbox);
+    observer
+        .observe(
+            &button.clone().into(),
+            MutationObserverInit {
+                attributes: Some(true),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    // Records queued by a listener are delivered as soon as the user event has been dispatched
+    let target = button.clone();
+    let listener: EventListener = Arc::new(move |_event: &Event| {
+        target
+            .set_attribute("aria-pressed".to_owned(), "true".to_owned())
+            .unwrap();
+    });
+    button.add_event_listener("mousedown".to_owned(), listener, Default::default());
+    let seen_by_mouseup = Arc::new(Mutex::new(0));
+    let (seen_handle, log_handle) = (seen_by_mouseup.clone(), log.clone());
+    let listener: EventListener = Arc::new(move |_event: &Event| {
+        *seen_handle.lock().unwrap() = log_handle.lock().unwrap().len();
+    });
+    button.add_event_listener("mouseup".to_owned(), listener, Default::default());
+
+    user_event::click(&button).unwrap();
+    assert_eq!(*seen_by_mouseup.lock().unwrap(), 1);
+}
+
+#[test]
+fn window_timers() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let window = sbox.window();
+    let log: Arc<Mutex<Vec<String>>> = Default::default();
+
+    // A debounced handler only runs once the input has been quiet for the whole delay
+    let mut pending = None;
+    for _keystroke in 0..3 {
+        sbox.advance_time(Duration::from_millis(100));
+        if let Some(id) = pending {
+            window.clear_timeout(id);
+        }
+        let log_handle = log.clone();
+        pending = Some(
+            window
+                .set_timeout(
+                    move || log_handle.lock().unwrap().push("debounced".to_owned()),
+                    Duration::from_millis(300),
+                )
+                .unwrap(),
+        );
+    }
+    sbox.advance_time(Duration::from_millis(299));
+    assert!(log.lock().unwrap().is_empty());
+    sbox.advance_time(Duration::from_millis(1));
+    assert_eq!(*log.lock().unwrap(), vec!["debounced"]);
+
+    // An interval which clears itself on its third run
+    let count = Arc::new(Mutex::new(0));
+    let interval_id = Arc::new(Mutex::new(0));
+    let (count_handle, id_handle, window_handle) =
+        (count.clone(), interval_id.clone(), Arc::downgrade(&window));
+    *interval_id.lock().unwrap() = window
+        .set_interval(
+            move || {
+                let mut count = count_handle.lock().unwrap();
+                *count += 1;
+                if *count == 3 {
+                    let window = window_handle.upgrade().unwrap();
+                    window.clear_interval(*id_handle.lock().unwrap());
+                }
+            },
+            Duration::from_millis(10),
+        )
+        .unwrap();
+    sbox.advance_time(Duration::from_millis(100));
+    assert_eq!(*count.lock().unwrap(), 3);
+}
+
+#[test]
+fn nested_timers_are_clamped() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let run_times: Arc<Mutex<Vec<Duration>>> = Default::default();
+
+    fn schedule(sbox: Weak<Sandbox>, run_times: Arc<Mutex<Vec<Duration>>>) {
+        let window = sbox.upgrade().unwrap().window();
+        window
+            .set_timeout(
+                move || {
+                    let now = sbox.upgrade().unwrap().now();
+                    run_times.lock().unwrap().push(now);
+                    if run_times.lock().unwrap().len() < 8 {
+                        schedule(sbox, run_times);
+                    }
+                },
+                Duration::ZERO,
+            )
+            .unwrap();
+    }
+    schedule(Arc::downgrade(&sbox), run_times.clone());
+    sbox.advance_time(Duration::from_millis(100));
+
+    let millis: Vec<u128> = run_times
+        .lock()
+        .unwrap()
+        .iter()
+        .map(Duration::as_millis)
+        .collect();
+    // The seventh timer in the chain is the first created at a nesting level above 5
+    assert_eq!(millis, vec![0, 0, 0, 0, 0, 0, 4, 8]);
+}
+
+#[test]
+fn animation_frames() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let window = sbox.window();
+    let log: Arc<Mutex<Vec<String>>> = Default::default();
+
+    let (log_handle, window_handle) = (log.clone(), Arc::downgrade(&window));
+    window.request_animation_frame(move |timestamp| {
+        log_handle
+            .lock()
+            .unwrap()
+            .push(format!("first {:.1}", timestamp));
+        // Requested during a frame, so it runs in the next one
+        let inner_log = log_handle.clone();
+        window_handle
+            .upgrade()
+            .unwrap()
+            .request_animation_frame(move |timestamp| {
+                inner_log
+                    .lock()
+                    .unwrap()
+                    .push(format!("next {:.1}", timestamp));
+            });
+    });
+    let log_handle = log.clone();
+    let canceled = window.request_animation_frame(move |_timestamp| {
+        log_handle.lock().unwrap().push("canceled".to_owned());
+    });
+    window.cancel_animation_frame(canceled);
+
+    assert!(log.lock().unwrap().is_empty());
+    sbox.tick_frame();
+    assert_eq!(*log.lock().unwrap(), vec!["first 16.7"]);
+    sbox.tick_frame();
+    assert_eq!(*log.lock().unwrap(), vec!["first 16.7", "next 33.3"]);
+}
+
+#[test]
+fn window_geometry_and_resize() {
+    let sbox = Sandbox::new(ScreenMetrics {
+        inner_width: 800,
+        inner_height: 600,
+        device_pixel_ratio: 2.0,
+        ..Default::default()
+    });
+    let window = sbox.window();
+    assert_eq!((window.inner_width(), window.inner_height()), (800, 600));
+    assert_eq!(window.outer_height(), 1080);
+    assert_eq!(window.screen().width(), 1920);
+    assert_eq!(window.device_pixel_ratio(), 2.0);
+
+    let resizes = Arc::new(Mutex::new(Vec::new()));
+    let (resizes_handle, window_handle) = (resizes.clone(), Arc::downgrade(&window));
+    let listener: EventListener = Arc::new(move |_event: &Event| {
+        let window = window_handle.upgrade().unwrap();
+        resizes_handle
+            .lock()
+            .unwrap()
+            .push((window.inner_width(), window.inner_height()));
+    });
+    window.add_event_listener("resize".to_owned(), listener, Default::default());
+
+    let mut metrics = window.screen_metrics();
+    metrics.device_pixel_ratio = 1.0;
+    sbox.resize(metrics.clone()).unwrap();
+    assert!(resizes.lock().unwrap().is_empty());
+    metrics.inner_width = 375;
+    sbox.resize(metrics).unwrap();
+    assert_eq!(*resizes.lock().unwrap(), vec![(375, 600)]);
+    assert_eq!(window.device_pixel_ratio(), 1.0);
+
+    let doc = window.document();
+    let log = record_events(&doc.into(), &["scroll"], false);
+    window.scroll_to(0.0, 250.0).unwrap();
+    window.scroll_to(0.0, 250.0).unwrap();
+    assert_eq!((window.scroll_x(), window.scroll_y()), (0.0, 250.0));
+    assert_eq!(*log.lock().unwrap(), vec!["scroll"]);
+
+    // A clone of the window is another handle to the same window
+    let copy = (*window).clone();
+    copy.scroll_to(0.0, 100.0).unwrap();
+    assert_eq!(window.scroll_y(), 100.0);
+}
+
+#[test]
+fn match_media_queries() {
+    let sbox = Sandbox::new(ScreenMetrics {
+        inner_width: 800,
+        inner_height: 600,
+        device_pixel_ratio: 2.0,
+        ..Default::default()
+    });
+    let window = sbox.window();
+    let matches = |query: &str| window.match_media(query).matches();
+
+    assert!(matches(""));
+    assert!(matches("screen"));
+    assert!(!matches("print"));
+    assert!(matches("not print"));
+    assert!(matches("only screen and (min-width: 600px)"));
+    assert!(!matches("(max-width: 40em)"));
+    assert!(matches("(width >= 800px) and (height < 601px)"));
+    assert!(matches("(400px <= width < 1000px)"));
+    assert!(!matches("(1000px > width > 800px)"));
+    assert!(matches("(orientation: landscape)"));
+    assert!(matches("(min-aspect-ratio: 4/3)"));
+    assert!(matches("(min-resolution: 192dpi)"));
+    assert!(matches("(resolution: 2x)"));
+    assert!(matches(
+        "(max-width: 100px) or (prefers-color-scheme: light)"
+    ));
+    assert!(matches("print, (prefers-reduced-motion: no-preference)"));
+    assert!(!matches("(prefers-reduced-motion)"));
+    assert!(!matches("not ((width < 500px) or (hover-nonsense: 1))"));
+    // Unknown features and syntax errors never match, even when negated
+    assert!(!matches("(unknown-feature)"));
+    assert!(!matches("not (unknown-feature)"));
+    assert!(!matches("screen and"));
+    assert!(matches("screen and, (min-width: 1px)"));
+}
+
+#[test]
+fn match_media_change_events() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let window = sbox.window();
+    let narrow = window.match_media("(max-width: 600px)");
+    let dark = window.match_media("(prefers-color-scheme: dark)");
+    assert!(!narrow.matches());
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    for list in [&narrow, &dark] {
+        let log_handle = log.clone();
+        let listener: EventListener = Arc::new(move |event: &Event| {
+            if let EventKind::MediaQueryList(data) = event.kind() {
+                log_handle
+                    .lock()
+                    .unwrap()
+                    .push(format!("{} {}", data.media, data.matches));
+            }
+        });
+        list.add_event_listener("change".to_owned(), listener, Default::default());
+    }
+
+    let mut metrics = window.screen_metrics();
+    metrics.inner_width = 500;
+    sbox.resize(metrics.clone()).unwrap();
+    metrics.inner_height = 400;
+    sbox.resize(metrics).unwrap();
+    sbox.set_user_preferences(UserPreferences {
+        color_scheme: ColorScheme::Dark,
+        ..Default::default()
+    })
+    .unwrap();
+
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            "(max-width: 600px) true",
+            "(prefers-color-scheme: dark) true"
+        ]
+    );
+    assert!(narrow.matches() && dark.matches());
+}
+
+#[test]
+fn sandbox_config() {
+    let sbox = Sandbox::new(
+        SandboxConfig::builder()
+            .url("https://example.com/app?tab=1".to_owned())
+            .referrer("https://example.org/".to_owned())
+            .user_agent("TestAgent/1.0".to_owned())
+            .languages(vec!["de-DE".to_owned(), "en".to_owned()])
+            .timezone("Europe/Berlin".to_owned())
+            .color_scheme(ColorScheme::Dark)
+            .online(false)
+            .seed(42)
+            .build()
+            .unwrap(),
+    );
+    let window = sbox.window();
+    let doc = window.document();
+    assert_eq!(doc.url(), "https://example.com/app?tab=1");
+    assert_eq!(doc.referrer(), "https://example.org/");
+    assert_eq!(sbox.config().timezone(), "Europe/Berlin");
+    assert!(window.match_media("(prefers-color-scheme: dark)").matches());
+    assert!(matches!(
+        SandboxConfig::builder().url("not a url".to_owned()).build(),
+        Err(DomError::Syntax)
+    ));
+
+    let navigator = window.navigator();
+    assert_eq!(navigator.user_agent(), "TestAgent/1.0");
+    assert_eq!(navigator.language().unwrap(), "de-DE");
+    assert!(navigator.cookie_enabled());
+    assert!(!navigator.on_line());
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let log_handle = log.clone();
+    let listener: EventListener = Arc::new(move |event: &Event| {
+        log_handle.lock().unwrap().push(event.event_type());
+    });
+    window.add_event_listener("online".to_owned(), listener, Default::default());
+    sbox.set_online(true).unwrap();
+    sbox.set_online(true).unwrap();
+    assert_eq!(*log.lock().unwrap(), vec!["online"]);
+    assert!(window.navigator().on_line());
+
+    // The random number generator is deterministic for a given seed
+    let same_seed = Sandbox::new(SandboxConfig::builder().seed(42).build().unwrap());
+    let numbers: Vec<f64> = (0..3).map(|_| window.random()).collect();
+    let same_numbers: Vec<f64> = (0..3).map(|_| same_seed.window().random()).collect();
+    assert_eq!(numbers, same_numbers);
+    assert!(numbers.iter().all(|n| (0.0..1.0).contains(n)));
+    assert!(numbers[0] != numbers[1]);
+}
+
+#[test]
+fn location_components() {
+    use crate::navigation::NavigationSource;
+
+    let sbox = Sandbox::new(
+        SandboxConfig::builder()
+            .url("https://user@example.com:8080/docs/page?q=1#intro".to_owned())
+            .build()
+            .unwrap(),
+    );
+    let location = sbox.window().location();
+    assert_eq!(
+        location.href(),
+        "https://user@example.com:8080/docs/page?q=1#intro"
+    );
+    assert_eq!(location.protocol(), "https:");
+    assert_eq!(location.host(), "example.com:8080");
+    assert_eq!(location.hostname(), "example.com");
+    assert_eq!(location.port(), "8080");
+    assert_eq!(location.pathname(), "/docs/page");
+    assert_eq!(location.search(), "?q=1");
+    assert_eq!(location.hash(), "#intro");
+    assert_eq!(location.origin(), "https://example.com:8080");
+
+    // Navigations to other documents are recorded, resolved, and do not change the location
+    location.assign("../about".to_owned()).unwrap();
+    location.set_search("?q=2".to_owned()).unwrap();
+    let requests = sbox.navigation_requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].url, "https://user@example.com:8080/about");
+    assert_eq!(requests[0].source, NavigationSource::Script);
+    assert_eq!(
+        requests[1].url,
+        "https://user@example.com:8080/docs/page?q=2#intro"
+    );
+    assert_eq!(location.pathname(), "/docs/page");
+}
+
+#[test]
+fn history_navigation() {
+    let sbox = Sandbox::new(
+        SandboxConfig::builder()
+            .url("https://example.com/".to_owned())
+            .build()
+            .unwrap(),
+    );
+    let window = sbox.window();
+    let history = window.history();
+    let log = Arc::new(Mutex::new(Vec::new()));
+    for event_type in ["popstate", "hashchange"] {
+        let log_handle = log.clone();
+        let listener: EventListener = Arc::new(move |event: &Event| {
+            let entry = match event.kind() {
+                EventKind::PopState(data) => format!("popstate {:?}", data.state),
+                EventKind::HashChange(data) => {
+                    format!("hashchange {} -> {}", data.old_url, data.new_url)
+                }
+                _ => unreachable!(),
+            };
+            log_handle.lock().unwrap().push(entry);
+        });
+        window.add_event_listener(event_type.to_owned(), listener, Default::default());
+    }
+
+    history
+        .push_state(Some("one".to_owned()), Some("/one".to_owned()))
+        .unwrap();
+    history
+        .push_state(Some("two".to_owned()), Some("/two#top".to_owned()))
+        .unwrap();
+    assert_eq!(history.length(), 3);
+    assert_eq!(window.location().pathname(), "/two");
+    assert!(matches!(
+        history.push_state(None, Some("https://example.org/".to_owned())),
+        Err(DomError::Security)
+    ));
+    assert!(log.lock().unwrap().is_empty());
+
+    // Traversal happens in a task
+    history.back().unwrap();
+    assert_eq!(history.state().as_deref(), Some("two"));
+    sbox.run_until_idle();
+    assert_eq!(history.state().as_deref(), Some("one"));
+    assert_eq!(window.document().url(), "https://example.com/one");
+
+    // Pushing drops the forward entries
+    history.replace_state(None, None).unwrap();
+    history.push_state(None, Some("/three".to_owned())).unwrap();
+    history.forward().unwrap();
+    sbox.run_until_idle();
+    assert_eq!(history.length(), 3);
+    assert_eq!(window.location().pathname(), "/three");
+
+    // Fragment navigation fires popstate at once, and hashchange in a task
+    window.location().set_hash("section".to_owned()).unwrap();
+    assert_eq!(window.location().hash(), "#section");
+    assert_eq!(history.length(), 4);
+    assert_eq!(sbox.navigation_requests().len(), 0);
+    sbox.run_until_idle();
+    history.go(-2).unwrap();
+    sbox.run_until_idle();
+
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            "popstate Some(\"one\")",
+            "hashchange https://example.com/two#top -> https://example.com/one",
+            "popstate None",
+            "hashchange https://example.com/three -> https://example.com/three#section",
+            "popstate None",
+            "hashchange https://example.com/three#section -> https://example.com/one",
+        ]
+    );
+}
+
+#[test]
+fn fragment_links() {
+    let sbox = Sandbox::new(
+        SandboxConfig::builder()
+            .url("https://example.com/page".to_owned())
+            .build()
+            .unwrap(),
+    );
+    let doc = sbox.window().document();
+    let link = doc.create_element("a".to_owned()).unwrap();
+    link.set_attribute("href".to_owned(), "#details".to_owned())
+        .unwrap();
+    doc.append_child(link.clone().into());
+
+    user_event::click(&link).unwrap();
+    assert_eq!(doc.url(), "https://example.com/page#details");
+    assert!(sbox.navigation_requests().is_empty());
+
+    link.set_attribute("href".to_owned(), "/other#details".to_owned())
+        .unwrap();
+    user_event::click(&link).unwrap();
+    assert_eq!(
+        sbox.navigation_requests()[0].url,
+        "https://example.com/other#details"
+    );
+}
+
+#[test]
+fn web_storage() {
+    use crate::storage::{MemoryStorageBackend, SharedStorage, StorageBackend};
+
+    let backend = Arc::new(MemoryStorageBackend::new());
+    backend.save(
+        "https://example.com",
+        &[("theme".to_owned(), "dark".to_owned())],
+    );
+    let shared = SharedStorage::new(backend.clone(), 16);
+    let config = SandboxConfig::builder()
+        .url("https://example.com/a".to_owned())
+        .local_storage(shared)
+        .build()
+        .unwrap();
+    let first = Sandbox::new(config.clone());
+    let second = Sandbox::new(config);
+
+    let storage = first.window().local_storage().unwrap();
+    assert_eq!(
+        storage.get_item("theme".to_owned()).as_deref(),
+        Some("dark")
+    );
+    storage
+        .set_item("lang".to_owned(), "de".to_owned())
+        .unwrap();
+    assert_eq!(storage.length(), 2);
+    assert_eq!(storage.key(1).as_deref(), Some("lang"));
+    assert!(matches!(
+        storage.set_item("long".to_owned(), "value".to_owned()),
+        Err(DomError::QuotaExceeded)
+    ));
+    assert_eq!(
+        backend.load("https://example.com"),
+        vec![
+            ("theme".to_owned(), "dark".to_owned()),
+            ("lang".to_owned(), "de".to_owned())
+        ]
+    );
+
+    // Other sandboxes with the same origin see the change, and get a storage event
+    let other_window = second.window();
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let log_handle = log.clone();
+    let listener: EventListener = Arc::new(move |event: &Event| {
+        if let EventKind::Storage(data) = event.kind() {
+            log_handle
+                .lock()
+                .unwrap()
+                .push(format!("{:?} {:?} {}", data.key, data.new_value, data.url));
+        }
+    });
+    other_window.add_event_listener("storage".to_owned(), listener, Default::default());
+    let other_storage = other_window.local_storage().unwrap();
+    assert_eq!(
+        other_storage.get_item("lang".to_owned()).as_deref(),
+        Some("de")
+    );
+
+    storage.remove_item("theme".to_owned());
+    storage.remove_item("theme".to_owned());
+    other_storage.clear();
+    first.run_until_idle();
+    second.run_until_idle();
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec!["Some(\"theme\") None https://example.com/a"]
+    );
+    assert_eq!(storage.length(), 0);
+
+    // Session storage is not shared, and opaque origins have no storage
+    let session = first.window().session_storage().unwrap();
+    session
+        .set_item("draft".to_owned(), "hello".to_owned())
+        .unwrap();
+    let other_session = other_window.session_storage().unwrap();
+    assert_eq!(other_session.get_item("draft".to_owned()), None);
+    let blank = Sandbox::new(SandboxConfig::default());
+    assert!(matches!(
+        blank.window().local_storage(),
+        Err(DomError::Security)
+    ));
+}
+
+#[test]
+fn json_file_storage() {
+    use crate::storage::{JsonFileStorageBackend, SharedStorage};
+
+    let path = std::env::temp_dir().join(format!("rdom-storage-{}.json", std::process::id()));
+    let config = || {
+        let backend = Arc::new(JsonFileStorageBackend::new(path.clone()));
+        SandboxConfig::builder()
+            .url("https://example.com/".to_owned())
+            .local_storage(SharedStorage::new(backend, SharedStorage::DEFAULT_QUOTA))
+            .build()
+            .unwrap()
+    };
+
+    let sbox = Sandbox::new(config());
+    let storage = sbox.window().local_storage().unwrap();
+    storage
+        .set_item("count".to_owned(), "1".to_owned())
+        .unwrap();
+    storage
+        .set_item("average".to_owned(), "2".to_owned())
+        .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        r#"{"https://example.com":[["count","1"],["average","2"]]}"#
+    );
+
+    // Items keep the order they were added in, rather than key order
+    let reloaded = Sandbox::new(config());
+    let storage = reloaded.window().local_storage().unwrap();
+    assert_eq!(storage.get_item("count".to_owned()).as_deref(), Some("1"));
+    assert_eq!(storage.length(), 2);
+    assert_eq!(storage.key(0).as_deref(), Some("count"));
+    assert_eq!(storage.key(1).as_deref(), Some("average"));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn document_cookies() {
+    use crate::cookie::{CookieJar, SameSite};
+    use std::time::UNIX_EPOCH;
+
+    let jar = CookieJar::new();
+    let sbox = Sandbox::new(
+        SandboxConfig::builder()
+            .url("https://www.example.com/shop/cart".to_owned())
+            .cookie_jar(jar.clone())
+            .build()
+            .unwrap(),
+    );
+    let doc = sbox.window().document();
+    doc.set_cookie("session=abc".to_owned());
+    doc.set_cookie("theme=dark; Path=/; Domain=example.com; SameSite=Strict".to_owned());
+    doc.set_cookie("short=lived; Max-Age=60".to_owned());
+    doc.set_cookie("old=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT".to_owned());
+    doc.set_cookie("other=1; Path=/admin".to_owned());
+    doc.set_cookie("loose=1; SameSite=None".to_owned());
+    doc.set_cookie("hidden=1; HttpOnly".to_owned());
+    assert_eq!(doc.cookie(), "session=abc; short=lived; theme=dark");
+
+    let theme = jar
+        .cookies()
+        .into_iter()
+        .find(|cookie| cookie.name == "theme")
+        .unwrap();
+    assert_eq!(theme.domain, "example.com");
+    assert!(!theme.host_only);
+    assert_eq!(theme.same_site, SameSite::Strict);
+    assert_eq!(jar.cookies()[0].path, "/shop");
+
+    // Expiry follows the virtual clock
+    sbox.advance_time(Duration::from_secs(61));
+    assert_eq!(doc.cookie(), "session=abc; theme=dark");
+
+    // Overwriting with a past date deletes
+    doc.set_cookie("session=; Expires=Thu, 01 Jan 1970 00:00:00 GMT".to_owned());
+    assert_eq!(doc.cookie(), "theme=dark");
+
+    // A Max-Age too large to parse keeps its sign, so a negative one still deletes
+    doc.set_cookie("a=b".to_owned());
+    doc.set_cookie("a=b; Max-Age=-99999999999999999999".to_owned());
+    assert_eq!(doc.cookie(), "theme=dark");
+
+    // Date tokens may start with multi-byte characters
+    doc.set_cookie("a=b; Expires=ab日 01 Jan 2030 00:00:00".to_owned());
+    assert_eq!(doc.cookie(), "a=b; theme=dark");
+    doc.set_cookie("a=b; Max-Age=0".to_owned());
+
+    // Scripts neither see nor replace HttpOnly cookies, which responses can set
+    let url = url::Url::parse("https://example.com/").unwrap();
+    jar.set_from_header(&url, "token=secret; HttpOnly; Secure", sbox.current_time());
+    let other = Sandbox::new(
+        SandboxConfig::builder()
+            .url("https://example.com/".to_owned())
+            .cookie_jar(jar.clone())
+            .build()
+            .unwrap(),
+    );
+    let other_doc = other.window().document();
+    other_doc.set_cookie("token=forged".to_owned());
+    assert_eq!(other_doc.cookie(), "theme=dark");
+    assert_eq!(
+        jar.header_for(&url, other.current_time()),
+        "theme=dark; token=secret"
+    );
+
+    let insecure = Sandbox::new(
+        SandboxConfig::builder()
+            .url("http://example.com/".to_owned())
+            .cookie_jar(jar.clone())
+            .build()
+            .unwrap(),
+    );
+    insecure
+        .window()
+        .document()
+        .set_cookie("id=1; Secure".to_owned());
+    assert_eq!(insecure.window().document().cookie(), "theme=dark");
+    // Dates are parsed leniently
+    doc.set_cookie("dated=1; expires=Friday, 01-Jan-21 00:10:00 GMT".to_owned());
+    let dated = jar.cookies().into_iter().last().unwrap();
+    assert_eq!(
+        dated.expires,
+        Some(UNIX_EPOCH + Duration::from_secs(1_609_459_200 + 600))
+    );
+}
+
+#[test]
+fn fetch_from_request_handler() {
+    use crate::cookie::CookieJar;
+    use crate::fetch::{Request, RequestInit, Response};
+
+    let jar = CookieJar::new();
+    let sbox = Sandbox::new(
+        SandboxConfig::builder()
+            .url("https://example.com/app/".to_owned())
+            .cookie_jar(jar.clone())
+            .build()
+            .unwrap(),
+    );
+    let window = sbox.window();
+    window.document().set_cookie("session=abc".to_owned());
+
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let requests_handle = requests.clone();
+    sbox.set_request_handler(Arc::new(move |request: &Request| {
+        requests_handle.lock().unwrap().push(request.clone());
+        match (request.method.as_ref(), request.url.as_ref()) {
+            ("GET", "https://example.com/app/items") => Response::new(200, r#"{"items": [1, 2]}"#)
+                .with_header("Content-Type", "application/json")
+                .with_header("Set-Cookie", "seen=1"),
+            ("POST", "https://example.com/api/items") => Response::new(201, "created"),
+            ("GET", "https://other.example/") => Response::error(),
+            _ => Response::new(404, "").with_status_text("Not Found"),
+        }
+    }));
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let fetch = |input: &str, init: RequestInit| {
+        let log = log.clone();
+        window
+            .fetch(input.to_owned(), init, move |result| {
+                let entry = match result {
+                    Ok(response) => format!("{} {}", response.status(), response.url()),
+                    Err(error) => format!("{:?}", error),
+                };
+                log.lock().unwrap().push(entry);
+            })
+            .unwrap();
+    };
+    fetch("items", Default::default());
+    fetch(
+        "/api/items",
+        RequestInit {
+            method: Some("post".to_owned()),
+            body: Some("name=x".to_owned()),
+            ..Default::default()
+        },
+    );
+    fetch("/missing", Default::default());
+    fetch("https://other.example/", Default::default());
+
+    // Responses arrive in tasks
+    assert!(log.lock().unwrap().is_empty());
+    sbox.run_until_idle();
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            "200 https://example.com/app/items",
+            "201 https://example.com/api/items",
+            "404 https://example.com/missing",
+            "Type",
+        ]
+    );
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0].header("Cookie"), Some("session=abc"));
+    assert_eq!(requests[1].method, "POST");
+    assert_eq!(requests[1].body.as_deref(), Some("name=x"));
+    assert_eq!(requests[3].header("cookie"), None);
+    assert_eq!(window.document().cookie(), "session=abc; seen=1");
+
+    let response = Response::new(200, r#"{"items": [1, 2]}"#);
+    assert_eq!(response.json().unwrap()["items"][1], 2);
+    assert!(matches!(
+        Response::new(200, "<html>").json(),
+        Err(DomError::Syntax)
+    ));
+}
+
+#[test]
+fn console_entries() {
+    use crate::console::{ConsoleLevel, ConsoleTable};
+
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let console = sbox.window().console();
+    console.log("starting".to_owned());
+    sbox.advance_time(Duration::from_millis(5));
+    console.group("loading".to_owned());
+    console.warn("slow response".to_owned());
+    console.table(
+        vec!["id".to_owned(), "name".to_owned()],
+        vec![
+            vec!["1".to_owned(), "apple".to_owned()],
+            vec!["20".to_owned()],
+        ],
+    );
+    console.group_end();
+    console.group_end();
+    console.error("failed".to_owned());
+
+    let entries = sbox.console_entries();
+    let summary: Vec<(ConsoleLevel, &str, usize)> = entries
+        .iter()
+        .map(|entry| (entry.level, entry.message.as_ref(), entry.group_depth))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (ConsoleLevel::Log, "starting", 0),
+            (ConsoleLevel::Log, "loading", 0),
+            (ConsoleLevel::Warn, "slow response", 1),
+            (ConsoleLevel::Log, "id | name\n1  | apple\n20 |", 1),
+            (ConsoleLevel::Error, "failed", 0),
+        ]
+    );
+    assert!(entries[1].group_start);
+    assert_eq!(entries[0].time, Duration::ZERO);
+    assert_eq!(entries[2].time, Duration::from_millis(5));
+    assert_eq!(
+        entries[3].table,
+        Some(ConsoleTable {
+            columns: vec!["id".to_owned(), "name".to_owned()],
+            rows: vec![
+                vec!["1".to_owned(), "apple".to_owned()],
+                vec!["20".to_owned(), "".to_owned()],
+            ],
+        })
+    );
+}
+
+#[test]
+fn owner_document_and_import_node() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let document = sbox.window().document();
+    assert!(document.owner_document().is_none());
+
+    let form = document.create_element("form".to_owned()).unwrap();
+    assert!(form.owner_document().unwrap() == document);
+    let built = sbox.builder::<ElementNodeArc>().build_body();
+    assert!(built.owner_document().unwrap() == document);
+
+    let input = document.create_element("input".to_owned()).unwrap();
+    input
+        .set_attribute("name".to_owned(), "q".to_owned())
+        .unwrap();
+    input.set_value("typed".to_owned()).unwrap();
+    form.append_child(input.into());
+
+    let other = document
+        .implementation()
+        .create_html_document(None)
+        .unwrap();
+    let copy = other.import_node(&form.clone().into(), true).unwrap();
+    assert!(copy.owner_document().unwrap() == other);
+    let copied_input = ElementNodeArc::try_from(copy.first_child().unwrap()).unwrap();
+    assert!(copied_input.owner_document().unwrap() == other);
+    assert_eq!(copied_input.get_attribute("name".to_owned()).unwrap(), "q");
+    assert_eq!(copied_input.value().unwrap(), "typed");
+
+    // The copy's state is independent of the original's
+    copied_input.set_value("changed".to_owned()).unwrap();
+    let original_input = ElementNodeArc::try_from(form.first_child().unwrap()).unwrap();
+    assert_eq!(original_input.value().unwrap(), "typed");
+
+    // Attributes keep their namespace
+    let link = document.create_element("a".to_owned()).unwrap();
+    let href = document
+        .create_attribute_ns(
+            Some("http://www.w3.org/1999/xlink".to_owned()),
+            "xlink:href".to_owned(),
+        )
+        .unwrap();
+    link.contents.set_attribute_node(href).unwrap();
+    let copied_link =
+        ElementNodeArc::try_from(document.import_node(&link.clone().into(), false).unwrap())
+            .unwrap();
+    let copied_href = copied_link.contents.attributes().pop().unwrap();
+    assert!(!Arc::ptr_eq(
+        &copied_href.contents,
+        &link.contents.attributes()[0].contents
+    ));
+    assert_eq!(copied_href.contents.name(), "xlink:href");
+    assert_eq!(
+        copied_href.contents.namespace_uri().unwrap(),
+        "http://www.w3.org/1999/xlink"
+    );
+
+    let shallow = other.import_node(&form.into(), false).unwrap();
+    assert!(shallow.first_child().is_none());
+    assert!(matches!(
+        other.import_node(&document.into(), true),
+        Err(DomError::NotSupported)
+    ));
+}
+
+#[test]
+fn adopt_node_across_sandboxes() {
+    let first = Sandbox::new(SandboxConfig::default());
+    let second = Sandbox::new(SandboxConfig::default());
+    let first_document = first.window().document();
+    let second_document = second.window().document();
+
+    let html = first_document.create_element("html".to_owned()).unwrap();
+    first_document.append_child(html.clone().into());
+    let list = first_document.create_element("ul".to_owned()).unwrap();
+    list.set_attribute("id".to_owned(), "list".to_owned())
+        .unwrap();
+    let item = first_document.create_element("li".to_owned()).unwrap();
+    list.append_child(item.clone().into());
+    html.append_child(list.clone().into());
+
+    let adopted = second_document.adopt_node(&list.clone().into()).unwrap();
+    assert!(adopted.common.node_graph.parent_node().is_none());
+    assert!(html.first_child().is_none());
+    for node in [list.clone().into(), AnyNodeArc::from(item)] {
+        assert!(Weak::ptr_eq(&node.get_context(), &Arc::downgrade(&second)));
+        assert!(node.owner_document().unwrap() == second_document);
+    }
+    let attribute = list.contents.attributes().remove(0);
+    assert!(Weak::ptr_eq(
+        &attribute.get_context(),
+        &Arc::downgrade(&second)
+    ));
+    assert!(attribute.owner_document().unwrap() == second_document);
+
+    assert!(matches!(
+        second_document.adopt_node(&first_document.into()),
+        Err(DomError::NotSupported)
+    ));
+}
+
+#[test]
+fn dom_implementation_documents() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let implementation = sbox.window().document().implementation();
+
+    let document = implementation
+        .create_html_document(Some("Report".to_owned()))
+        .unwrap();
+    assert_eq!(document.url(), "about:blank");
+    assert_eq!(document.content_type(), "text/html");
+    assert!(Weak::ptr_eq(
+        &document.get_context(),
+        &Arc::downgrade(&sbox)
+    ));
+    let children = document.common.node_graph.static_child_nodes();
+    assert_eq!(children.len(), 2);
+    assert!(children[0].contents.to_node_type() == NodeType::DocumentType);
+    let html = ElementNodeArc::try_from(children[1].clone()).unwrap();
+    let tag_names: Vec<String> = html
+        .common
+        .node_graph
+        .static_child_nodes()
+        .into_iter()
+        .map(|child| {
+            ElementNodeArc::try_from(child)
+                .unwrap()
+                .contents
+                .tag_name()
+                .to_ascii_lowercase()
+        })
+        .collect();
+    assert_eq!(tag_names, vec!["head", "body"]);
+    let title = html.first_child().unwrap().first_child().unwrap();
+    let text = TextNodeArc::try_from(title.first_child().unwrap()).unwrap();
+    assert_eq!(text.contents.data().unwrap(), "Report");
+
+    let svg = implementation
+        .create_document(
+            Some("http://www.w3.org/2000/svg".to_owned()),
+            "svg".to_owned(),
+        )
+        .unwrap();
+    assert_eq!(svg.content_type(), "image/svg+xml");
+    // The document element is in the given namespace
+    let root = ElementNodeArc::try_from(svg.first_child().unwrap()).unwrap();
+    assert_eq!(root.contents.tag_name(), "svg");
+    assert_eq!(
+        root.contents.namespace_uri().unwrap(),
+        "http://www.w3.org/2000/svg"
+    );
+    assert!(matches!(
+        implementation.create_document(None, "svg:svg".to_owned()),
+        Err(DomError::Namespace)
+    ));
+    assert!(matches!(
+        implementation.create_document(None, "xmlns".to_owned()),
+        Err(DomError::Namespace)
+    ));
+    let xml = implementation.create_document(None, String::new()).unwrap();
+    assert_eq!(xml.content_type(), "application/xml");
+    assert!(xml.first_child().is_none());
+    assert!(implementation.has_feature());
+}
+
+#[test]
+fn document_factories() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let document = sbox.window().document();
+
+    let comment = document.create_comment("note".to_owned()).unwrap();
+    assert_eq!(comment.contents.data().unwrap(), "note");
+    assert!(comment.owner_document().unwrap() == document);
+    let fragment = document.create_document_fragment().unwrap();
+    assert_eq!(fragment.node_type(), 9);
+
+    let attribute = document.create_attribute("Data-X".to_owned()).unwrap();
+    assert_eq!(attribute.contents.name(), "data-x");
+    assert!(matches!(
+        document.create_attribute("bad name".to_owned()),
+        Err(DomError::InvalidCharacter)
+    ));
+    let attribute = document
+        .create_attribute_ns(
+            Some("http://www.w3.org/1999/xlink".to_owned()),
+            "xlink:href".to_owned(),
+        )
+        .unwrap();
+    assert_eq!(attribute.contents.prefix().unwrap(), "xlink");
+    assert_eq!(attribute.contents.local_name(), "href");
+    assert_eq!(
+        attribute.contents.namespace_uri().unwrap(),
+        "http://www.w3.org/1999/xlink"
+    );
+    assert!(matches!(
+        document.create_attribute_ns(None, "xlink:href".to_owned()),
+        Err(DomError::Namespace)
+    ));
+    assert!(matches!(
+        document.create_attribute_ns(None, "xmlns".to_owned()),
+        Err(DomError::Namespace)
+    ));
+
+    assert!(matches!(
+        document.create_cdata_section("data".to_owned()),
+        Err(DomError::NotSupported)
+    ));
+    let xml = document
+        .implementation()
+        .create_document(None, "root".to_owned())
+        .unwrap();
+    let cdata = xml.create_cdata_section("a < b".to_owned()).unwrap();
+    assert_eq!(cdata.contents.data().unwrap(), "a < b");
+    let instruction = xml
+        .create_processing_instruction("xml-stylesheet".to_owned(), "href=\"a.css\"".to_owned())
+        .unwrap();
+    assert_eq!(instruction.contents.target(), "xml-stylesheet");
+    assert_eq!(instruction.contents.data().unwrap(), "href=\"a.css\"");
+    assert!(matches!(
+        xml.create_processing_instruction("pi".to_owned(), "?>".to_owned()),
+        Err(DomError::InvalidCharacter)
+    ));
+}
+
+#[test]
+fn created_events() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let document = sbox.window().document();
+    let body = sbox.builder::<ElementNodeArc>().build_body();
+    document.append_child(body.clone().into());
+
+    let mut event = document.create_event("MouseEvents".to_owned()).unwrap();
+    assert!(matches!(event.kind(), EventKind::Mouse(_)));
+    assert!(matches!(
+        body.dispatch_event(&event),
+        Err(DomError::InvalidState)
+    ));
+
+    let log = record_events(&document.clone().into(), &["ping"], false);
+    event.init_event("ping".to_owned(), true, true);
+    assert_eq!(event.event_type(), "ping");
+    assert!(body.dispatch_event(&event).unwrap());
+    assert_eq!(*log.lock().unwrap(), vec!["ping"]);
+
+    assert!(matches!(
+        document.create_event("NoSuchEvent".to_owned()),
+        Err(DomError::NotSupported)
+    ));
+}
+
+#[test]
+fn ranges() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let document = sbox.window().document();
+    let body = document.create_element("body".to_owned()).unwrap();
+    document.append_child(body.clone().into());
+    let first = document.create_text_node("héllo".to_owned()).unwrap();
+    let second = document.create_element("b".to_owned()).unwrap();
+    body.append_child(first.clone().into());
+    body.append_child(second.clone().into());
+    let body: AnyNodeArc = body.into();
+    let first: AnyNodeArc = first.into();
+
+    let range = document.create_range();
+    assert!(range.collapsed());
+    assert!(range.start_container().ptr_eq(&document.clone().into()));
+
+    range.set_start(&first, 1).unwrap();
+    assert!(range.collapsed());
+    range.set_end_after(&second.clone().into()).unwrap();
+    assert!(!range.collapsed());
+    assert!(range.end_container().ptr_eq(&body));
+    assert_eq!(range.end_offset(), 2);
+    assert!(range.common_ancestor_container().ptr_eq(&body));
+
+    // A start after the end collapses the range
+    range.set_start(&body, 2).unwrap();
+    assert!(range.collapsed());
+    range.select_node_contents(&first).unwrap();
+    assert_eq!(range.end_offset(), 5);
+    assert!(range.common_ancestor_container().ptr_eq(&first));
+    range.select_node(&first).unwrap();
+    assert_eq!((range.start_offset(), range.end_offset()), (0, 1));
+    range.collapse(false);
+    assert_eq!(range.start_offset(), 1);
+
+    assert!(matches!(range.set_end(&first, 6), Err(DomError::Range)));
+    assert!(matches!(
+        range.set_start_before(&document.into()),
+        Err(DomError::InvalidNodeType)
+    ));
+}
+
+#[test]
+fn tree_walker() {
+    use crate::tree_walker::{FilterResult, SHOW_ALL, SHOW_ELEMENT};
+
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let document = sbox.window().document();
+    let element = |name: &str| document.create_element(name.to_owned()).unwrap();
+    let root = element("div");
+    let list = element("ul");
+    let first = element("li");
+    let second = element("li");
+    let skipped = element("span");
+    let nested = element("em");
+    root.append_child(list.clone().into());
+    list.append_child(first.clone().into());
+    first.append_child(document.create_text_node("one".to_owned()).unwrap().into());
+    list.append_child(second.clone().into());
+    root.append_child(skipped.clone().into());
+    skipped.append_child(nested.clone().into());
+
+    let tag = |node: Option<AnyNodeArc>| {
+        ElementNodeArc::try_from(node.unwrap())
+            .unwrap()
+            .contents
+            .tag_name()
+            .to_ascii_lowercase()
+    };
+    let walker = document.create_tree_walker(
+        root.clone().into(),
+        SHOW_ELEMENT,
+        Some(Arc::new(|node: &AnyNodeArc| {
+            let element = ElementNodeArc::try_from(node.clone()).unwrap();
+            match element.contents.tag_name().to_ascii_lowercase().as_ref() {
+                "span" => FilterResult::Skip,
+                _ => FilterResult::Accept,
+            }
+        })),
+    );
+    assert_eq!(tag(walker.next_node()), "ul");
+    assert_eq!(tag(walker.next_node()), "li");
+    assert_eq!(tag(walker.next_node()), "li");
+    // The span is skipped, but its child is visited
+    assert_eq!(tag(walker.next_node()), "em");
+    assert!(walker.next_node().is_none());
+    assert_eq!(tag(walker.previous_node()), "li");
+    assert_eq!(tag(walker.previous_sibling()), "li");
+    assert_eq!(tag(walker.parent_node()), "ul");
+    assert_eq!(tag(walker.next_sibling()), "em");
+    assert_eq!(tag(walker.parent_node()), "div");
+    assert!(walker.parent_node().is_none());
+    assert_eq!(tag(walker.last_child()), "em");
+
+    let all = document.create_tree_walker(first.into(), SHOW_ALL, None);
+    assert_eq!(all.first_child().unwrap().node_type(), 3);
+}
+
+#[test]
+fn doctype_and_quirks_mode() {
+    use crate::node::quirks::QuirksMode;
+
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let standard = sbox
+        .window()
+        .document()
+        .implementation()
+        .create_html_document(None)
+        .unwrap();
+    let doctype = standard.doctype().unwrap();
+    assert_eq!(doctype.name(), "html");
+    assert_eq!(doctype.public_id(), "");
+    assert_eq!(standard.compat_mode(), "CSS1Compat");
+
+    // Gives the mode of a sandbox's document after inserting a doctype
+    let mode_for = |name: &str, public_id: &str, system_id: &str| {
+        let sbox = Sandbox::new(SandboxConfig::default());
+        let document = sbox.window().document();
+        let doctype = document
+            .implementation()
+            .create_document_type(name.to_owned(), public_id.to_owned(), system_id.to_owned())
+            .unwrap();
+        document.append_child(doctype.into());
+        document.mode()
+    };
+    assert_eq!(mode_for("html", "", ""), QuirksMode::NoQuirks);
+    assert_eq!(mode_for("svg", "", ""), QuirksMode::Quirks);
+    assert_eq!(
+        mode_for("html", "-//W3C//DTD HTML 3.2 Final//EN", ""),
+        QuirksMode::Quirks
+    );
+    assert_eq!(
+        mode_for("html", "-//W3C//DTD HTML 4.01 Transitional//EN", ""),
+        QuirksMode::Quirks
+    );
+    assert_eq!(
+        mode_for(
+            "html",
+            "-//W3C//DTD HTML 4.01 Transitional//EN",
+            "http://www.w3.org/TR/html4/loose.dtd"
+        ),
+        QuirksMode::LimitedQuirks
+    );
+    assert_eq!(
+        mode_for("HTML", "-//W3C//DTD XHTML 1.0 Transitional//EN", ""),
+        QuirksMode::LimitedQuirks
+    );
+
+    // Ids and classes match case-insensitively in quirks mode
+    let document = sbox.window().document();
+    let doctype = document
+        .implementation()
+        .create_document_type("html".to_owned(), "HTML".to_owned(), String::new())
+        .unwrap();
+    document.append_child(doctype.into());
+    assert_eq!(document.compat_mode(), "BackCompat");
+    let body = document.create_element("body".to_owned()).unwrap();
+    body.set_attribute("id".to_owned(), "Main".to_owned())
+        .unwrap();
+    body.set_attribute("class".to_owned(), "Page wide".to_owned())
+        .unwrap();
+    document.append_child(body.into());
+    let selector = Selector::try_from("body#main.page.WIDE").unwrap();
+    assert!(document.query_selector(&selector).unwrap().is_some());
+
+    let standard_body = standard.create_element("body".to_owned()).unwrap();
+    standard_body
+        .set_attribute("class".to_owned(), "Page".to_owned())
+        .unwrap();
+    standard.append_child(standard_body.into());
+    assert!(standard
+        .query_selector(&Selector::try_from(".page").unwrap())
+        .unwrap()
+        .is_none());
+    assert!(standard
+        .query_selector(&Selector::try_from(".Page").unwrap())
+        .unwrap()
+        .is_some());
+    assert!(Selector::try_from("div#").is_err());
+
+    // A doctype inserted together with the document element still sets the mode
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let document = sbox.window().document();
+    let doctype = document
+        .implementation()
+        .create_document_type("html".to_owned(), "HTML".to_owned(), String::new())
+        .unwrap();
+    let html = document.create_element("html".to_owned()).unwrap();
+    document
+        .replace_children(vec![doctype.into(), html.into()])
+        .unwrap();
+    assert_eq!(document.mode(), QuirksMode::Quirks);
+}
+
+#[test]
+fn document_accessors() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let document = sbox
+        .window()
+        .document()
+        .implementation()
+        .create_html_document(Some("  My \n page ".to_owned()))
+        .unwrap();
+    let html = document.document_element().unwrap();
+    assert_eq!(html.contents.tag_name(), "HTML");
+    assert_eq!(document.head().unwrap().contents.tag_name(), "HEAD");
+    let body = document.body().unwrap();
+    assert_eq!(body.contents.tag_name(), "BODY");
+
+    assert_eq!(document.title(), "My page");
+    // Setting the title replaces all the children of the title element at once
+    let title_element = document.title_element().unwrap();
+    title_element.append_child(document.create_text_node("!".to_owned()).unwrap().into());
+    let (observer, _) = record_mutations(&sbox);
+    observer
+        .observe(
+            &title_element.clone().into(),
+            MutationObserverInit {
+                child_list: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    document.set_title("Other".to_owned()).unwrap();
+    assert_eq!(document.title(), "Other");
+    let records = observer.take_records();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].removed_nodes().length(), 2);
+
+    let new_body = document.create_element("body".to_owned()).unwrap();
+    document.set_body(new_body.clone()).unwrap();
+    assert!(document.body().unwrap() == new_body);
+    assert!(body.common.node_graph.parent_node().is_none());
+    assert!(matches!(
+        document.set_body(document.create_element("div".to_owned()).unwrap()),
+        Err(DomError::HierarchyRequest)
+    ));
+
+    let forms = document.forms();
+    let links = document.links();
+    let named = document.get_elements_by_name("q".to_owned());
+    assert_eq!(forms.length(), 0);
+    let form = document.create_element("form".to_owned()).unwrap();
+    new_body.append_child(form.clone().into());
+    let input = document.create_element("input".to_owned()).unwrap();
+    input
+        .set_attribute("name".to_owned(), "q".to_owned())
+        .unwrap();
+    form.append_child(input.into());
+    let anchor = document.create_element("a".to_owned()).unwrap();
+    new_body.append_child(anchor.clone().into());
+    new_body.append_child(document.create_element("img".to_owned()).unwrap().into());
+    new_body.append_child(document.create_element("script".to_owned()).unwrap().into());
+
+    // The lists are live
+    assert_eq!(forms.length(), 1);
+    assert!(forms.item(0).unwrap().ptr_eq(&form.into()));
+    assert_eq!(named.length(), 1);
+    assert_eq!(links.length(), 0);
+    anchor
+        .set_attribute("href".to_owned(), "/next".to_owned())
+        .unwrap();
+    assert_eq!(links.length(), 1);
+    assert_eq!(document.images().length(), 1);
+    assert_eq!(document.scripts().length(), 1);
+
+    // Without a head, no title is created
+    let empty = sbox.window().document();
+    assert!(empty.document_element().is_none());
+    empty.set_title("Ignored".to_owned()).unwrap();
+    assert_eq!(empty.title(), "");
+}
+
+#[test]
+fn character_data() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let doc = sbox.window().document();
+    let text = doc.create_text_node("a😀b".to_owned()).unwrap();
+
+    // Offsets count UTF-16 code units, so the emoji is two long
+    assert_eq!(text.length(), 4);
+    assert_eq!(text.substring_data(1, 2).unwrap(), "😀");
+    assert_eq!(text.substring_data(3, 10).unwrap(), "b");
+    text.insert_data(3, "c".to_owned()).unwrap();
+    text.append_data("d".to_owned());
+    assert_eq!(text.data(), "a😀cbd");
+    text.replace_data(1, 2, "e".to_owned()).unwrap();
+    text.delete_data(3, 100).unwrap();
+    assert_eq!(text.data(), "aec");
+
+    assert!(matches!(text.substring_data(4, 1), Err(DomError::Range)));
+    assert!(matches!(
+        text.insert_data(4, "x".to_owned()),
+        Err(DomError::Range)
+    ));
+    assert_eq!(text.data(), "aec");
+
+    let comment = doc.create_comment("note".to_owned()).unwrap();
+    comment.replace_data(0, 1, "N".to_owned()).unwrap();
+    assert_eq!(comment.data(), "Note");
+    let instruction = doc
+        .create_processing_instruction("xml-stylesheet".to_owned(), "href=a".to_owned())
+        .unwrap();
+    instruction.append_data(".css".to_owned());
+    assert_eq!(instruction.target(), "xml-stylesheet");
+    assert_eq!(instruction.data(), "href=a.css");
+
+    // Clones have their own data
+    let copy = TextNodeArc::try_from(text.clone_node()).unwrap();
+    copy.set_data("changed".to_owned());
+    assert_eq!(text.data(), "aec");
+    assert!(copy.owner_document().unwrap() == doc);
+    let copy = CommentNodeArc::try_from(comment.clone_node()).unwrap();
+    copy.append_data("s".to_owned());
+    assert_eq!(comment.data(), "Note");
+
+    // So do clones of the stores
+    let store = TextStore::clone(&text.contents);
+    let copy = TextNodeArc::new(Arc::downgrade(&sbox), Arc::new(store));
+    copy.set_data("changed".to_owned());
+    assert_eq!(text.data(), "aec");
+}
+
+#[test]
+fn split_text_and_normalize() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let document = sbox.window().document();
+    let paragraph = document.create_element("p".to_owned()).unwrap();
+    let text = document.create_text_node("hello world".to_owned()).unwrap();
+    let bold = document.create_element("b".to_owned()).unwrap();
+    paragraph.append_child(text.clone().into());
+    paragraph.append_child(bold.clone().into());
+    let paragraph: AnyNodeArc = paragraph.into();
+
+    let caret = document.create_range();
+    caret.set_start(&text.clone().into(), 8).unwrap();
+    let after = document.create_range();
+    after.set_start(&paragraph, 1).unwrap();
+
+    // The new node follows the old one, and takes the ranges after the offset with it
+    let tail = text.split_text(5).unwrap();
+    assert_eq!(text.data(), "hello");
+    assert_eq!(tail.data(), " world");
+    assert!(text
+        .common
+        .node_graph
+        .next_sibling()
+        .unwrap()
+        .ptr_eq(&tail.clone().into()));
+    assert!(caret.start_container().ptr_eq(&tail.clone().into()));
+    assert_eq!(caret.start_offset(), 3);
+    assert_eq!(after.start_offset(), 2);
+    assert_eq!(text.whole_text(), "hello world");
+    assert!(matches!(text.split_text(6), Err(DomError::Range)));
+
+    // Removing the data before a point moves it back
+    text.delete_data(0, 2).unwrap();
+    let start = document.create_range();
+    start.set_start(&text.clone().into(), 3).unwrap();
+    let empty = document.create_text_node(String::new()).unwrap();
+    paragraph
+        .common
+        .node_graph
+        .insert_before(empty.clone().into(), Some(&bold.clone().into()));
+
+    paragraph.normalize();
+    assert_eq!(paragraph.common.node_graph.child_count(), 2);
+    assert_eq!(text.data(), "llo world");
+    assert!(tail.common.node_graph.parent_node().is_none());
+    assert!(empty.common.node_graph.parent_node().is_none());
+    assert!(caret.start_container().ptr_eq(&text.clone().into()));
+    assert_eq!(caret.start_offset(), 6);
+    assert_eq!(start.start_offset(), 3);
+    // The point before the empty node ends up at the end of the merged text
+    assert!(after.start_container().ptr_eq(&text.into()));
+    assert_eq!(after.start_offset(), 9);
+}
+
+#[test]
+fn node_text_and_names() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let document = sbox.window().document();
+    let element = |name: &str| document.create_element(name.to_owned()).unwrap();
+    let text =
+        |data: &str| -> AnyNodeArc { document.create_text_node(data.to_owned()).unwrap().into() };
+
+    let body = element("body");
+    let heading = element("h1");
+    heading.append_child(text("  Title "));
+    let paragraph = element("p");
+    paragraph.append_child(text("one\n  two"));
+    paragraph.append_child(element("br").into());
+    let bold = element("b");
+    bold.append_child(text(" three"));
+    paragraph.append_child(bold.into());
+    let script = element("script");
+    script.append_child(text("ignored()"));
+    body.append_child(heading.clone().into());
+    body.append_child(paragraph.clone().into());
+    body.append_child(script.clone().into());
+    body.append_child(text("end"));
+
+    assert_eq!(
+        body.text_content().unwrap(),
+        "  Title one\n  two threeignored()end"
+    );
+    assert_eq!(body.inner_text(), "Title\n\none two\nthree\n\nend");
+    assert_eq!(script.inner_text(), "ignored()");
+
+    assert_eq!(body.node_name(), "BODY");
+    assert_eq!(body.first_child().unwrap().node_name(), "H1");
+    // Only HTML elements are uppercased, even in an HTML document
+    let svg = document
+        .create_element_ns(
+            Some("http://www.w3.org/2000/svg".to_owned()),
+            "svg".to_owned(),
+        )
+        .unwrap();
+    assert_eq!(AnyNodeArc::from(svg).node_name(), "svg");
+    assert_eq!(text("x").node_name(), "#text");
+    assert_eq!(document.node_name(), "#document");
+    assert_eq!(
+        document.create_comment("c".to_owned()).unwrap().node_name(),
+        "#comment"
+    );
+    assert!(document.text_content().is_none());
+    assert!(body.node_value().is_none());
+
+    // CDATA sections are text nodes, so their data is part of the text content
+    let xml = document
+        .implementation()
+        .create_document(None, "root".to_owned())
+        .unwrap();
+    let root = xml.document_element().unwrap();
+    root.append_child(xml.create_text_node("a ".to_owned()).unwrap().into());
+    root.append_child(xml.create_cdata_section("< b".to_owned()).unwrap().into());
+    assert_eq!(root.text_content().unwrap(), "a < b");
+
+    // Setting text replaces the children with a single text node, in one mutation record
+    let (observer, log) = record_mutations(&sbox);
+    observer
+        .observe(
+            &body.clone().into(),
+            MutationObserverInit {
+                child_list: true,
+                character_data: Some(true),
+                attributes: Some(true),
+                subtree: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    heading.set_text_content("New".to_owned());
+    assert_eq!(heading.common.node_graph.child_count(), 1);
+    assert_eq!(heading.text_content().unwrap(), "New");
+    paragraph.set_text_content(String::new());
+    assert_eq!(paragraph.common.node_graph.child_count(), 0);
+
+    let node = heading.first_child().unwrap();
+    node.set_node_value("Newer".to_owned());
+    assert_eq!(node.text_content().unwrap(), "Newer");
+    heading
+        .set_attribute("id".to_owned(), "top".to_owned())
+        .unwrap();
+    let attribute = heading.contents.attributes()[0].clone();
+    assert_eq!(attribute.node_name(), "id");
+    attribute.set_text_content("main".to_owned());
+    assert_eq!(heading.get_attribute("id".to_owned()).unwrap(), "main");
+    assert_eq!(attribute.node_value().unwrap(), "main");
+
+    sbox.perform_microtask_checkpoint();
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            "ChildList 1 1+1- None",
+            "ChildList 1 0+3- None",
+            "CharacterData 3 0+0- None",
+            "Attributes 1 0+0- Some(\"id\")",
+            "Attributes 1 0+0- Some(\"id\")",
+        ]
+    );
+}
+
+#[test]
+fn node_identity_and_position() {
+    use crate::node::{
+        DOCUMENT_POSITION_CONTAINED_BY, DOCUMENT_POSITION_CONTAINS, DOCUMENT_POSITION_DISCONNECTED,
+        DOCUMENT_POSITION_FOLLOWING, DOCUMENT_POSITION_IMPLEMENTATION_SPECIFIC,
+        DOCUMENT_POSITION_PRECEDING,
+    };
+    use std::collections::HashMap;
+
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let document = sbox.window().document();
+    let build = || {
+        let list = document.create_element("ul".to_owned()).unwrap();
+        list.set_attribute("class".to_owned(), "menu".to_owned())
+            .unwrap();
+        let item = document.create_element("li".to_owned()).unwrap();
+        item.append_child(document.create_text_node("one".to_owned()).unwrap().into());
+        list.append_child(item.clone().into());
+        (AnyNodeArc::from(list), AnyNodeArc::from(item))
+    };
+    let (list, item) = build();
+    let (copy, copy_item) = build();
+
+    assert!(list.is_same_node(&list.clone()));
+    assert!(!list.is_same_node(&copy));
+    assert!(list.is_equal_node(&copy));
+    copy_item
+        .first_child()
+        .unwrap()
+        .set_node_value("two".to_owned());
+    assert!(!list.is_equal_node(&copy));
+    assert!(!item.is_equal_node(&list));
+    // Elements with the same name in different namespaces are not equal
+    let x = document
+        .create_element_ns(Some("urn:x".to_owned()), "foo".to_owned())
+        .unwrap();
+    let y = document
+        .create_element_ns(Some("urn:y".to_owned()), "foo".to_owned())
+        .unwrap();
+    assert!(!AnyNodeArc::from(x).is_equal_node(&y.into()));
+
+    // Nodes compare and hash by identity
+    #[allow(clippy::mutable_key_type)] // Changing a node's contents cannot change its hash
+    let mut names = HashMap::new();
+    names.insert(list.clone(), "list");
+    names.insert(copy.clone(), "copy");
+    assert_eq!(names[&list], "list");
+    assert_eq!(names.len(), 2);
+    assert!(list == list.clone() && list != copy);
+
+    let text = item.first_child().unwrap();
+    assert_eq!(list.compare_document_position(&list), 0);
+    assert_eq!(
+        list.compare_document_position(&text),
+        DOCUMENT_POSITION_CONTAINED_BY | DOCUMENT_POSITION_FOLLOWING
+    );
+    assert_eq!(
+        text.compare_document_position(&list),
+        DOCUMENT_POSITION_CONTAINS | DOCUMENT_POSITION_PRECEDING
+    );
+    let second = document.create_element("li".to_owned()).unwrap();
+    list.append_child(second.clone().into());
+    assert_eq!(
+        second.compare_document_position(&text),
+        DOCUMENT_POSITION_PRECEDING
+    );
+    assert_eq!(
+        text.compare_document_position(&second.clone().into()),
+        DOCUMENT_POSITION_FOLLOWING
+    );
+
+    let attribute: AnyNodeArc = ElementNodeArc::try_from(list.clone())
+        .unwrap()
+        .contents
+        .attributes()[0]
+        .clone()
+        .into();
+    assert_eq!(
+        attribute.compare_document_position(&list),
+        DOCUMENT_POSITION_CONTAINS | DOCUMENT_POSITION_PRECEDING
+    );
+    assert_eq!(
+        attribute.compare_document_position(&text),
+        DOCUMENT_POSITION_FOLLOWING
+    );
+
+    // Disconnected nodes are ordered consistently
+    let position = list.compare_document_position(&copy);
+    assert_ne!(position & DOCUMENT_POSITION_DISCONNECTED, 0);
+    assert_ne!(position & DOCUMENT_POSITION_IMPLEMENTATION_SPECIFIC, 0);
+    let reverse = copy.compare_document_position(&list);
+    assert_ne!(
+        position & DOCUMENT_POSITION_PRECEDING,
+        reverse & DOCUMENT_POSITION_PRECEDING
+    );
+    assert_eq!(list.compare_document_position(&copy_item), position);
+}
+
+#[test]
+fn child_node_methods() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let document = sbox.window().document();
+    let list = document.create_element("ul".to_owned()).unwrap();
+    let first = document.create_element("li".to_owned()).unwrap();
+    let second = document.create_element("li".to_owned()).unwrap();
+    list.append_child(first.clone().into());
+    list.append_child(second.clone().into());
+    let names = || -> Vec<String> {
+        list.common
+            .node_graph
+            .static_child_nodes()
+            .iter()
+            .map(|node| match node.node_name().as_ref() {
+                "#text" => node.node_value().unwrap(),
+                name => name.to_owned(),
+            })
+            .collect()
+    };
+
+    let (observer, log) = record_mutations(&sbox);
+    observer
+        .observe(
+            &list.clone().into(),
+            MutationObserverInit {
+                child_list: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    // Fragments are inserted as their children, in a single record
+    let fragment = document.create_document_fragment().unwrap();
+    fragment.append_child(document.create_element("b".to_owned()).unwrap().into());
+    fragment.append_child(document.create_element("i".to_owned()).unwrap().into());
+    first
+        .before(vec!["start".into(), fragment.clone().into()])
+        .unwrap();
+    assert_eq!(names(), vec!["start", "B", "I", "LI", "LI"]);
+    assert_eq!(fragment.common.node_graph.child_count(), 0);
+
+    // A node next to the reference point may be one of the arguments
+    second
+        .after(vec![first.clone().into(), "end".into()])
+        .unwrap();
+    assert_eq!(names(), vec!["start", "B", "I", "LI", "LI", "end"]);
+    assert!(list.last_child().unwrap().node_value().unwrap() == "end");
+    assert!(second
+        .common
+        .node_graph
+        .next_sibling()
+        .unwrap()
+        .ptr_eq(&first.clone().into()));
+
+    let text = TextNodeArc::try_from(list.first_child().unwrap()).unwrap();
+    text.replace_with(vec!["new".into()]).unwrap();
+    assert_eq!(names()[0], "new");
+    first.remove().unwrap();
+    assert!(first.common.node_graph.parent_node().is_none());
+    // Nodes without a parent are left alone
+    first.remove().unwrap();
+    first.after(vec!["ignored".into()]).unwrap();
+
+    assert!(matches!(
+        second.before(vec![list.clone().into()]),
+        Err(DomError::HierarchyRequest)
+    ));
+
+    sbox.perform_microtask_checkpoint();
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            "ChildList 1 3+0- None",
+            "ChildList 1 0+1- None",
+            "ChildList 1 2+0- None",
+            "ChildList 1 1+1- None",
+            "ChildList 1 0+1- None",
+        ]
+    );
+}
+
+#[test]
+fn child_node_methods_in_document() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let document = sbox
+        .window()
+        .document()
+        .implementation()
+        .create_html_document(None)
+        .unwrap();
+    let doctype = document.doctype().unwrap();
+    let html = document.document_element().unwrap();
+    let element = document.create_element("html".to_owned()).unwrap();
+    let comment = document.create_comment("note".to_owned()).unwrap();
+
+    // A document keeps a single element, after its doctype
+    assert!(matches!(
+        html.after(vec![element.clone().into()]),
+        Err(DomError::HierarchyRequest)
+    ));
+    assert!(matches!(
+        doctype.before(vec![comment.clone().into(), element.clone().into()]),
+        Err(DomError::HierarchyRequest)
+    ));
+    assert!(matches!(
+        html.before(vec![doctype.clone().into(), element.clone().into()]),
+        Err(DomError::HierarchyRequest)
+    ));
+    let other_doctype = document
+        .implementation()
+        .create_document_type("html".to_owned(), String::new(), String::new())
+        .unwrap();
+    assert!(matches!(
+        html.after(vec![other_doctype.clone().into()]),
+        Err(DomError::HierarchyRequest)
+    ));
+    assert!(matches!(
+        document.prepend(vec![element.clone().into()]),
+        Err(DomError::HierarchyRequest)
+    ));
+    assert_eq!(document.common.node_graph.child_count(), 2);
+
+    // Replacing the element or the doctype with one of the same kind is fine
+    html.replace_with(vec![comment.clone().into(), element.clone().into()])
+        .unwrap();
+    assert!(document.document_element().unwrap() == element);
+    doctype
+        .replace_with(vec![other_doctype.clone().into()])
+        .unwrap();
+    assert!(document.doctype().unwrap() == other_doctype);
+    assert!(matches!(
+        comment.replace_with(vec![doctype.clone().into()]),
+        Err(DomError::HierarchyRequest)
+    ));
+    assert_eq!(document.common.node_graph.child_count(), 3);
+}
+
+#[test]
+fn parent_node_methods() {
+    let sbox = Sandbox::new(SandboxConfig::default());
+    let document = sbox.window().document();
+    let list = document.create_element("ul".to_owned()).unwrap();
+    let first = document.create_element("li".to_owned()).unwrap();
+    let second = document.create_element("li".to_owned()).unwrap();
+    let children = list.children().unwrap();
+    assert_eq!(children.length(), 0);
+    assert!(list.first_element_child().unwrap().is_none());
+
+    list.append(vec!["a".into(), second.clone().into()])
+        .unwrap();
+    list.prepend(vec![first.clone().into(), "b".into()])
+        .unwrap();
+    assert_eq!(list.text_content().unwrap(), "ba");
+    assert_eq!(list.common.node_graph.child_count(), 4);
+    // The list of children is live, and only has elements
+    assert_eq!(children.length(), 2);
+    assert!(children.item(1).unwrap().ptr_eq(&second.clone().into()));
+    assert!(list.first_element_child().unwrap().unwrap() == first);
+    assert!(list.last_element_child().unwrap().unwrap() == second);
+
+    assert!(first.next_element_sibling().unwrap().unwrap() == second);
+    assert!(second.previous_element_sibling().unwrap().unwrap() == first);
+    assert!(first.previous_element_sibling().unwrap().is_none());
+    let text = TextNodeArc::try_from(first.common.node_graph.next_sibling().unwrap()).unwrap();
+    assert!(text.next_element_sibling().unwrap().unwrap() == second);
+
+    let fragment = document.create_document_fragment().unwrap();
+    fragment
+        .append(vec!["c".into(), first.clone().into()])
+        .unwrap();
+    assert!(fragment.first_element_child().unwrap().unwrap() == first);
+    assert_eq!(children.length(), 1);
+
+    let (observer, log) = record_mutations(&sbox);
+    observer
+        .observe(
+            &list.clone().into(),
+            MutationObserverInit {
+                child_list: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    list.replace_children(vec![fragment.clone().into(), "d".into()])
+        .unwrap();
+    assert_eq!(list.text_content().unwrap(), "cd");
+    assert!(list.first_element_child().unwrap().unwrap() == first);
+    assert_eq!(fragment.common.node_graph.child_count(), 0);
+    assert!(second.common.node_graph.parent_node().is_none());
+
+    assert!(matches!(
+        first.append(vec![list.clone().into()]),
+        Err(DomError::HierarchyRequest)
+    ));
+    assert!(matches!(
+        document.append(vec!["text".into()]),
+        Err(DomError::HierarchyRequest)
+    ));
+
+    sbox.perform_microtask_checkpoint();
+    assert_eq!(*log.lock().unwrap(), vec!["ChildList 1 3+3- None"]);
+}
@@ -0,0 +1,341 @@
+//! [TreeWalker](https://developer.mozilla.org/en-US/docs/Web/API/TreeWalker), which moves
+//! through the subtree of a node, visiting the nodes a [NodeFilter] accepts.
+
+use crate::internal_prelude::*;
+use crate::node::contents::NodeType;
+
+use std::sync::RwLock;
+
+/// [NodeFilter.SHOW_ALL](https://developer.mozilla.org/en-US/docs/Web/API/TreeWalker/whatToShow)
+pub const SHOW_ALL: u32 = 0xFFFF_FFFF;
+/// Shows element nodes
+pub const SHOW_ELEMENT: u32 = 0x1;
+/// Shows attribute nodes
+pub const SHOW_ATTRIBUTE: u32 = 0x2;
+/// Shows text nodes
+pub const SHOW_TEXT: u32 = 0x4;
+/// Shows CDATA section nodes
+pub const SHOW_CDATA_SECTION: u32 = 0x8;
+/// Shows processing instruction nodes
+pub const SHOW_PROCESSING_INSTRUCTION: u32 = 0x40;
+/// Shows comment nodes
+pub const SHOW_COMMENT: u32 = 0x80;
+/// Shows document nodes
+pub const SHOW_DOCUMENT: u32 = 0x100;
+/// Shows doctype nodes
+pub const SHOW_DOCUMENT_TYPE: u32 = 0x200;
+/// Shows document fragment nodes
+pub const SHOW_DOCUMENT_FRAGMENT: u32 = 0x400;
+
+/// The bit of `what_to_show` which shows nodes of the given type
+fn show_bit(node_type: NodeType) -> u32 {
+    match node_type {
+        NodeType::Element => SHOW_ELEMENT,
+        NodeType::Attribute => SHOW_ATTRIBUTE,
+        NodeType::Text => SHOW_TEXT,
+        NodeType::CDataSection => SHOW_CDATA_SECTION,
+        NodeType::ProcessingInstruction => SHOW_PROCESSING_INSTRUCTION,
+        NodeType::Comment => SHOW_COMMENT,
+        NodeType::Document => SHOW_DOCUMENT,
+        NodeType::DocumentType => SHOW_DOCUMENT_TYPE,
+        NodeType::DocumentFragment => SHOW_DOCUMENT_FRAGMENT,
+    }
+}
+
+/// The result of filtering a node
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FilterResult {
+    /// Visit the node
+    Accept,
+    /// Skip the node and its descendants
+    Reject,
+    /// Skip the node, but visit its descendants
+    Skip,
+}
+
+/// A [NodeFilter](https://developer.mozilla.org/en-US/docs/Web/API/NodeFilter), which decides
+/// which nodes a tree walker visits
+pub trait NodeFilter: Send + Sync {
+    /// Decides whether to visit `node`
+    fn accept_node(&self, node: &AnyNodeArc) -> FilterResult;
+}
+
+impl<F> NodeFilter for F
+where
+    F: Fn(&AnyNodeArc) -> FilterResult + Send + Sync,
+{
+    fn accept_node(&self, node: &AnyNodeArc) -> FilterResult {
+        self(node)
+    }
+}
+
+/// A [TreeWalker](https://developer.mozilla.org/en-US/docs/Web/API/TreeWalker), as created by
+/// `Document::create_tree_walker`
+#[sourcegen::sourcegen(generator = "behave", script = "SandboxMember context")]
+// Generated. All manual edits to the block annotated with #[sourcegen...] will be discarded.
+pub struct TreeWalker {
+    /// SandboxMember implementation
+    context: Weak<Sandbox>,
+    /// The node whose subtree is walked
+    root: AnyNodeArc,
+    /// The node types to visit, as a bitmask of the `SHOW_` constants
+    what_to_show: u32,
+    /// Decides which of the shown nodes to visit
+    filter: Option<Arc<dyn NodeFilter>>,
+    /// The node the walker is at
+    current_node: RwLock<AnyNodeArc>,
+}
+
+#[sourcegen::generated]
+impl TreeWalker {
+    /// gets `Weak<Sandbox>` to the `Sandbox` that it is in
+    pub fn get_context(&self) -> Weak<Sandbox> {
+        self.context.clone()
+    }
+}
+
+#[sourcegen::generated]
+impl SandboxMemberBehavior for TreeWalker {
+    fn get_context(&self) -> Weak<Sandbox> {
+        self.get_context()
+    }
+}
+
+/// The direction to look for children or siblings in
+#[derive(Clone, Copy)]
+enum Direction {
+    Forward,
+    Backward,
+}
+
+impl Direction {
+    fn first_child(self, node: &AnyNodeArc) -> Option<AnyNodeArc> {
+        match self {
+            Direction::Forward => node.common.node_graph.first_child(),
+            Direction::Backward => node.common.node_graph.last_child(),
+        }
+    }
+
+    fn next_sibling(self, node: &AnyNodeArc) -> Option<AnyNodeArc> {
+        match self {
+            Direction::Forward => node.common.node_graph.next_sibling(),
+            Direction::Backward => node.common.node_graph.previous_sibling(),
+        }
+    }
+}
+
+impl TreeWalker {
+    pub(crate) fn new(
+        context: Weak<Sandbox>,
+        root: AnyNodeArc,
+        what_to_show: u32,
+        filter: Option<Arc<dyn NodeFilter>>,
+    ) -> TreeWalker {
+        TreeWalker {
+            context,
+            current_node: RwLock::new(root.clone()),
+            root,
+            what_to_show,
+            filter,
+        }
+    }
+
+    /// [TreeWalker.root](https://developer.mozilla.org/en-US/docs/Web/API/TreeWalker/root)
+    pub fn root(&self) -> AnyNodeArc {
+        self.root.clone()
+    }
+
+    /// [TreeWalker.whatToShow](https://developer.mozilla.org/en-US/docs/Web/API/TreeWalker/whatToShow)
+    pub fn what_to_show(&self) -> u32 {
+        self.what_to_show
+    }
+
+    /// [TreeWalker.currentNode](https://developer.mozilla.org/en-US/docs/Web/API/TreeWalker/currentNode)
+    pub fn current_node(&self) -> AnyNodeArc {
+        self.current_node.read().unwrap().clone()
+    }
+
+    /// Sets [TreeWalker.currentNode](https://developer.mozilla.org/en-US/docs/Web/API/TreeWalker/currentNode)
+    pub fn set_current_node(&self, node: AnyNodeArc) {
+        *self.current_node.write().unwrap() = node;
+    }
+
+    /// [Filters](https://dom.spec.whatwg.org/#concept-node-filter) `node`
+    fn filter(&self, node: &AnyNodeArc) -> FilterResult {
+        if self.what_to_show & show_bit(node.contents.to_node_type()) == 0 {
+            return FilterResult::Skip;
+        }
+        match &self.filter {
+            Some(filter) => filter.accept_node(node),
+            None => FilterResult::Accept,
+        }
+    }
+
+    /// Moves to `node`, and gives it
+    fn visit(&self, node: AnyNodeArc) -> Option<AnyNodeArc> {
+        self.set_current_node(node.clone());
+        Some(node)
+    }
+
+    /// [TreeWalker.parentNode](https://developer.mozilla.org/en-US/docs/Web/API/TreeWalker/parentNode):
+    /// moves to the closest accepted ancestor within the root
+    pub fn parent_node(&self) -> Option<AnyNodeArc> {
+        let mut node = self.current_node();
+        while !node.ptr_eq(&self.root) {
+            node = node.common.node_graph.parent_node()?;
+            if self.filter(&node) == FilterResult::Accept {
+                return self.visit(node);
+            }
+        }
+        None
+    }
+
+    /// [Traverses children](https://dom.spec.whatwg.org/#concept-traverse-children)
+    fn traverse_children(&self, direction: Direction) -> Option<AnyNodeArc> {
+        let current = self.current_node();
+        let mut node = direction.first_child(&current);
+        'nodes: while let Some(candidate) = node {
+            match self.filter(&candidate) {
+                FilterResult::Accept => return self.visit(candidate),
+                FilterResult::Skip => {
+                    if let Some(child) = direction.first_child(&candidate) {
+                        node = Some(child);
+                        continue;
+                    }
+                }
+                FilterResult::Reject => {}
+            }
+
+            let mut candidate = candidate;
+            loop {
+                if let Some(sibling) = direction.next_sibling(&candidate) {
+                    node = Some(sibling);
+                    continue 'nodes;
+                }
+                let parent = candidate.common.node_graph.parent_node()?;
+                if parent.ptr_eq(&self.root) || parent.ptr_eq(&current) {
+                    return None;
+                }
+                candidate = parent;
+            }
+        }
+        None
+    }
+
+    /// [TreeWalker.firstChild](https://developer.mozilla.org/en-US/docs/Web/API/TreeWalker/firstChild)
+    pub fn first_child(&self) -> Option<AnyNodeArc> {
+        self.traverse_children(Direction::Forward)
+    }
+
+    /// [TreeWalker.lastChild](https://developer.mozilla.org/en-US/docs/Web/API/TreeWalker/lastChild)
+    pub fn last_child(&self) -> Option<AnyNodeArc> {
+        self.traverse_children(Direction::Backward)
+    }
+
+    /// [Traverses siblings](https://dom.spec.whatwg.org/#concept-traverse-siblings)
+    fn traverse_siblings(&self, direction: Direction) -> Option<AnyNodeArc> {
+        let mut node = self.current_node();
+        if node.ptr_eq(&self.root) {
+            return None;
+        }
+        loop {
+            let mut sibling = direction.next_sibling(&node);
+            while let Some(candidate) = sibling {
+                node = candidate;
+                let result = self.filter(&node);
+                if result == FilterResult::Accept {
+                    return self.visit(node);
+                }
+                sibling = direction.first_child(&node);
+                if result == FilterResult::Reject || sibling.is_none() {
+                    sibling = direction.next_sibling(&node);
+                }
+            }
+            node = node.common.node_graph.parent_node()?;
+            if node.ptr_eq(&self.root) || self.filter(&node) == FilterResult::Accept {
+                return None;
+            }
+        }
+    }
+
+    /// [TreeWalker.nextSibling](https://developer.mozilla.org/en-US/docs/Web/API/TreeWalker/nextSibling)
+    pub fn next_sibling(&self) -> Option<AnyNodeArc> {
+        self.traverse_siblings(Direction::Forward)
+    }
+
+    /// [TreeWalker.previousSibling](https://developer.mozilla.org/en-US/docs/Web/API/TreeWalker/previousSibling)
+    pub fn previous_sibling(&self) -> Option<AnyNodeArc> {
+        self.traverse_siblings(Direction::Backward)
+    }
+
+    /// [TreeWalker.previousNode](https://developer.mozilla.org/en-US/docs/Web/API/TreeWalker/previousNode):
+    /// moves to the previous accepted node in tree order
+    pub fn previous_node(&self) -> Option<AnyNodeArc> {
+        let mut node = self.current_node();
+        while !node.ptr_eq(&self.root) {
+            let mut sibling = node.common.node_graph.previous_sibling();
+            while let Some(candidate) = sibling {
+                node = candidate;
+                let mut result = self.filter(&node);
+                while result != FilterResult::Reject {
+                    match node.common.node_graph.last_child() {
+                        Some(child) => {
+                            node = child;
+                            result = self.filter(&node);
+                        }
+                        None => break,
+                    }
+                }
+                if result == FilterResult::Accept {
+                    return self.visit(node);
+                }
+                sibling = node.common.node_graph.previous_sibling();
+            }
+            if node.ptr_eq(&self.root) {
+                return None;
+            }
+            node = node.common.node_graph.parent_node()?;
+            if self.filter(&node) == FilterResult::Accept {
+                return self.visit(node);
+            }
+        }
+        None
+    }
+
+    /// [TreeWalker.nextNode](https://developer.mozilla.org/en-US/docs/Web/API/TreeWalker/nextNode):
+    /// moves to the next accepted node in tree order
+    pub fn next_node(&self) -> Option<AnyNodeArc> {
+        let mut node = self.current_node();
+        let mut result = FilterResult::Accept;
+        loop {
+            while result != FilterResult::Reject {
+                match node.common.node_graph.first_child() {
+                    Some(child) => {
+                        node = child;
+                        result = self.filter(&node);
+                        if result == FilterResult::Accept {
+                            return self.visit(node);
+                        }
+                    }
+                    None => break,
+                }
+            }
+
+            // Move to the next node which is not a descendant, without leaving the root
+            let mut ancestor = node.clone();
+            node = loop {
+                if ancestor.ptr_eq(&self.root) {
+                    return None;
+                }
+                if let Some(sibling) = ancestor.common.node_graph.next_sibling() {
+                    break sibling;
+                }
+                ancestor = ancestor.common.node_graph.parent_node()?;
+            };
+            result = self.filter(&node);
+            if result == FilterResult::Accept {
+                return self.visit(node);
+            }
+        }
+    }
+}
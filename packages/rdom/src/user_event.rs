@@ -0,0 +1,356 @@
+//! Simulation of user interaction, in the spirit of testing-library's `user-event`. Rather than
+//! dispatching a single synthetic event, each function here fires the whole sequence of events a
+//! browser would fire for the interaction, in the same order, and performs the default actions
+//! (such as updating an input's value) unless a listener cancels them.
+
+use crate::event::{
+    Event, EventInit, EventKind, InputEventData, KeyboardEventData, ModifierState, MouseEventData,
+    PointerEventData,
+};
+use crate::internal_prelude::*;
+use crate::node::concrete::{DocumentNodeArc, ElementNodeArc};
+use crate::node::element::HtmlElementStore;
+use crate::node::focus::{document_of, focusable_inclusive_ancestor, set_focus};
+use crate::node::form::default_button;
+
+/// Clicks an element with the main mouse button: fires pointerdown, mousedown, focus, pointerup,
+/// mouseup and click. Disabled form controls only receive the pointer events, and canceling
+/// pointerdown suppresses mousedown, the focus change and mouseup.
+pub fn click(element: &ElementNodeArc) -> Result<(), DomError> {
+    let disabled = element.disabled();
+
+    let pointerdown_allowed = fire(element, "pointerdown", true, true, pointer_kind(1))?;
+    let mouse_events = !disabled && pointerdown_allowed;
+    if mouse_events && fire(element, "mousedown", true, true, mouse_kind(1, 0))? {
+        if let Ok(document) = document_of(element) {
+            set_focus(&document, focusable_inclusive_ancestor(element))?;
+        }
+    }
+    fire(element, "pointerup", true, true, pointer_kind(0))?;
+    if mouse_events {
+        fire(element, "mouseup", true, true, mouse_kind(0, 0))?;
+    }
+    if !disabled {
+        fire(element, "click", true, true, mouse_kind(0, 1))?;
+    }
+    Ok(())
+}
+
+/// Focuses the element (if it is focusable) and types `text` into it one character at a
+/// time. Each character fires keydown, keypress, beforeinput, input and keyup, and is appended to
+/// the value of editable elements unless one of the events is canceled.
+pub fn type_text(element: &ElementNodeArc, text: &str) -> Result<(), DomError> {
+    if element.disabled() {
+        return Ok(());
+    }
+    element.focus()?;
+
+    for c in text.chars() {
+        press_key(element, &c.to_string())?;
+    }
+    Ok(())
+}
+
+/// Presses and releases a single key on the element. `key` is a
+/// [key value](https://developer.mozilla.org/en-US/docs/Web/API/UI_Events/Keyboard_event_key_values),
+/// either a single character or a named key such as "Enter" or "Backspace". Enter activates
+/// buttons and links and submits the form of a text field; Space activates buttons, checkboxes
+/// and radio buttons when it is released.
+pub fn press_key(element: &ElementNodeArc, key: &str) -> Result<(), DomError> {
+    let kind = keyboard_kind(key);
+
+    let keydown_allowed = fire(element, "keydown", true, true, kind.clone())?;
+    if keydown_allowed {
+        let produces_character = key.chars().count() == 1 || key == "Enter";
+        if !produces_character || fire(element, "keypress", true, true, kind.clone())? {
+            match key {
+                "Backspace" => edit_value(element, "deleteContentBackward", None)?,
+                "Enter" => match element.contents.html_element() {
+                    Some(HtmlElementStore::HtmlTextArea(_)) => {
+                        edit_value(element, "insertLineBreak", Some("\n"))?
+                    }
+                    Some(HtmlElementStore::HtmlInput(_)) if is_editable(element) => {
+                        implicitly_submit(element)?
+                    }
+                    _ if activates_on_enter(element) => synthetic_click(element)?,
+                    _ => {}
+                },
+                _ if key.chars().count() == 1 => edit_value(element, "insertText", Some(key))?,
+                _ => {}
+            }
+        }
+    }
+
+    let keyup_allowed = fire(element, "keyup", true, true, kind)?;
+    if key == " " && keydown_allowed && keyup_allowed && activates_on_space(element) {
+        synthetic_click(element)?;
+    }
+    Ok(())
+}
+
+/// Presses the Tab key (Shift+Tab if `shift` is true), moving focus to the next (or previous)
+/// focusable element of the document in tree order.
+pub fn tab(document: &DocumentNodeArc, shift: bool) -> Result<(), DomError> {
+    let modifiers = ModifierState {
+        shift_key: shift,
+        ..Default::default()
+    };
+    let kind = EventKind::Keyboard(KeyboardEventData {
+        key: "Tab".to_owned(),
+        code: "Tab".to_owned(),
+        modifiers,
+        ..Default::default()
+    });
+
+    let keydown_allowed = match document.focused_element() {
+        Some(focused) => fire(&focused, "keydown", true, true, kind.clone())?,
+        None => fire_on_document(document, "keydown", kind.clone())?,
+    };
+
+    if keydown_allowed {
+        let focusable = document.sequential_navigation_order();
+        let current = document
+            .focused_element()
+            .and_then(|focused| focusable.iter().position(|element| *element == focused));
+        let next = match (current, shift) {
+            (None, false) => focusable.first(),
+            (None, true) => focusable.last(),
+            (Some(index), false) => focusable.get(index + 1),
+            (Some(index), true) => index.checked_sub(1).and_then(|index| focusable.get(index)),
+        };
+        set_focus(document, next.cloned())?;
+    }
+
+    match document.focused_element() {
+        Some(focused) => fire(&focused, "keyup", true, true, kind)?,
+        None => fire_on_document(document, "keyup", kind)?,
+    };
+    Ok(())
+}
+
+/// Selects the options of a select element whose values are in `values`, firing input and change
+/// on the select for each of them. Unless the select has the `multiple` attribute, only the last
+/// matching option remains selected.
+pub fn select_option(select: &ElementNodeArc, values: &[&str]) -> Result<(), DomError> {
+    match select.contents.html_element() {
+        Some(HtmlElementStore::HtmlSelect(_)) if !select.disabled() => {}
+        _ => return Err(DomError::InvalidState),
+    }
+    click(select)?;
+
+    let multiple = select.has_attribute("multiple".to_owned());
+    let options = select.options();
+    for option in options.iter() {
+        let value = option.value()?;
+        if option.disabled() || !values.contains(&value.as_str()) {
+            continue;
+        }
+
+        if !multiple {
+            for other in options.iter() {
+                other.set_selected(false)?;
+            }
+        }
+        option.set_selected(true)?;
+
+        fire(select, "input", true, false, EventKind::Basic)?;
+        fire(select, "change", true, false, EventKind::Basic)?;
+    }
+    Ok(())
+}
+
+fn activates_on_enter(element: &ElementNodeArc) -> bool {
+    match element.contents.html_element() {
+        Some(HtmlElementStore::HtmlButton(_)) => !element.disabled(),
+        Some(HtmlElementStore::HtmlAnchor(_)) => element.has_attribute("href".to_owned()),
+        Some(HtmlElementStore::HtmlInput(_)) => {
+            !element.disabled()
+                && matches!(
+                    element.input_type().as_str(),
+                    "submit" | "image" | "reset" | "button"
+                )
+        }
+        _ => false,
+    }
+}
+
+fn activates_on_space(element: &ElementNodeArc) -> bool {
+    match element.contents.html_element() {
+        Some(HtmlElementStore::HtmlButton(_)) => !element.disabled(),
+        Some(HtmlElementStore::HtmlInput(_)) => {
+            !element.disabled()
+                && matches!(
+                    element.input_type().as_str(),
+                    "checkbox" | "radio" | "submit" | "image" | "reset" | "button"
+                )
+        }
+        _ => false,
+    }
+}
+
+/// Fires a click which does not come from a pointing device, as happens on keyboard activation
+fn synthetic_click(element: &ElementNodeArc) -> Result<(), DomError> {
+    fire(element, "click", true, true, mouse_kind(0, 0)).map(|_| ())
+}
+
+/// [Implicit submission](https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#implicit-submission):
+/// pressing Enter in a text field clicks the form's default button, or submits the form directly
+/// if it has none.
+fn implicitly_submit(element: &ElementNodeArc) -> Result<(), DomError> {
+    let form = match element.form() {
+        Some(form) => form,
+        None => return Ok(()),
+    };
+    match default_button(&form) {
+        Some(button) if button.disabled() => Ok(()),
+        Some(button) => synthetic_click(&button),
+        None => form.request_submit(None),
+    }
+}
+
+fn fire(
+    target: &ElementNodeArc,
+    event_type: &str,
+    bubbles: bool,
+    cancelable: bool,
+    kind: EventKind,
+) -> Result<bool, DomError> {
+    let event = Event::new(
+        event_type.to_owned(),
+        EventInit {
+            bubbles,
+            cancelable,
+            kind,
+        },
+    );
+    let not_canceled = target.dispatch_event(&event)?;
+    perform_microtask_checkpoint(target)?;
+    Ok(not_canceled)
+}
+
+fn fire_on_document(
+    document: &DocumentNodeArc,
+    event_type: &str,
+    kind: EventKind,
+) -> Result<bool, DomError> {
+    let event = Event::new(
+        event_type.to_owned(),
+        EventInit {
+            bubbles: true,
+            cancelable: true,
+            kind,
+        },
+    );
+    let not_canceled = document.dispatch_event(&event)?;
+    perform_microtask_checkpoint(document)?;
+    Ok(not_canceled)
+}
+
+/// Each user input event is dispatched by its own task, so microtasks queued by its listeners
+/// run before the next event is fired
+fn perform_microtask_checkpoint(target: &impl SandboxMemberBehavior) -> Result<(), DomError> {
+    target
+        .get_context()
+        .upgrade()
+        .ok_or(DomError::SandboxDropped)?
+        .perform_microtask_checkpoint();
+    Ok(())
+}
+
+fn mouse_kind(buttons: u16, detail: i32) -> EventKind {
+    EventKind::Mouse(MouseEventData {
+        buttons,
+        detail,
+        ..Default::default()
+    })
+}
+
+fn pointer_kind(buttons: u16) -> EventKind {
+    EventKind::Pointer(PointerEventData {
+        mouse: MouseEventData {
+            buttons,
+            ..Default::default()
+        },
+        pointer_id: 1,
+        pointer_type: "mouse".to_owned(),
+        is_primary: true,
+    })
+}
+
+fn keyboard_kind(key: &str) -> EventKind {
+    let mut chars = key.chars();
+    let (code, shift_key) = match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphabetic() => (
+            format!("Key{}", c.to_ascii_uppercase()),
+            c.is_ascii_uppercase(),
+        ),
+        (Some(c), None) if c.is_ascii_digit() => (format!("Digit{}", c), false),
+        (Some(' '), None) => ("Space".to_owned(), false),
+        (Some(_), None) => ("".to_owned(), false),
+        _ => (key.to_owned(), false),
+    };
+    EventKind::Keyboard(KeyboardEventData {
+        key: key.to_owned(),
+        code,
+        modifiers: ModifierState {
+            shift_key,
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+}
+
+/// Whether typing into the element changes its value
+fn is_editable(element: &ElementNodeArc) -> bool {
+    if element.disabled() || element.has_attribute("readonly".to_owned()) {
+        return false;
+    }
+    match element.contents.html_element() {
+        Some(HtmlElementStore::HtmlTextArea(_)) => true,
+        Some(HtmlElementStore::HtmlInput(_)) => !matches!(
+            element.input_type().as_str(),
+            "button"
+                | "checkbox"
+                | "color"
+                | "file"
+                | "hidden"
+                | "image"
+                | "radio"
+                | "range"
+                | "reset"
+                | "submit"
+        ),
+        _ => false,
+    }
+}
+
+/// Fires beforeinput and, unless it is canceled, changes the value and fires input
+fn edit_value(
+    element: &ElementNodeArc,
+    input_type: &str,
+    data: Option<&str>,
+) -> Result<(), DomError> {
+    if !is_editable(element) {
+        return Ok(());
+    }
+
+    let kind = EventKind::Input(InputEventData {
+        input_type: input_type.to_owned(),
+        data: data.map(str::to_owned),
+    });
+    if !fire(element, "beforeinput", true, true, kind.clone())? {
+        return Ok(());
+    }
+
+    let mut value = element.value()?;
+    match data {
+        Some(data) => value.push_str(data),
+        None => {
+            value.pop();
+        }
+    }
+    element.set_value(value)?;
+
+    fire(element, "input", true, false, kind)?;
+    Ok(())
+}
@@ -1,10 +1,45 @@
 //! A virtual browser window.
 
 use crate::{
+    config::{SandboxConfig, ScreenMetrics, UserPreferences},
+    console::Console,
+    event::{Event, EventInit, EventKind, MediaQueryListEventData},
+    event_loop::TaskHandle,
+    fetch::{RequestInit, Response},
+    history::History,
+    impl_event_target,
     internal_prelude::*,
+    location::Location,
+    media_query::MediaQueryList,
     node::{concrete::DocumentNodeArc, contents::DocumentStore},
+    proxy_event_target_behavior,
+    storage::{SharedStorage, Storage},
 };
 
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI32, AtomicU32, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+
+/// A timer callback, shared so that an interval can call it repeatedly
+type TimerHandler = Arc<dyn Fn() + Send + Sync>;
+
+/// A [DOMHighResTimeStamp](https://developer.mozilla.org/en-US/docs/Web/API/DOMHighResTimeStamp):
+/// milliseconds since the sandbox was created, on its virtual clock
+pub type DomHighResTimeStamp = f64;
+
+/// A callback passed to `request_animation_frame`
+type FrameRequestCallback = Box<dyn FnOnce(DomHighResTimeStamp) + Send>;
+
+/// Timers nested more deeply than this are clamped to [MIN_NESTED_TIMEOUT]
+const MAX_TIMER_NESTING_LEVEL: u32 = 5;
+
+/// The shortest timeout of a deeply nested timer, per the
+/// [spec](https://html.spec.whatwg.org/multipage/timers-and-user-prompts.html#timer-initialisation-steps)
+const MIN_NESTED_TIMEOUT: Duration = Duration::from_millis(4);
+
+crate::use_behaviors!(event_target);
+
 /// A simulated window for static rendering
 #[sourcegen::sourcegen(generator = "behave", script = "SandboxMember context;")]
 // Generated. All manual edits to the block annotated with #[sourcegen...] will be discarded.
@@ -12,6 +47,32 @@ use crate::{
 pub struct Window {
     document: DocumentNodeArc,
     context: Weak<Sandbox>,
+    self_weak: Weak<Window>,
+    pub(crate) event_target_behavior: EventTargetBehaviorStorage,
+    /// The map of active timers, from timer id to the task which will run it
+    active_timers: Arc<RwLock<HashMap<i32, TaskHandle>>>,
+    next_timer_id: Arc<AtomicI32>,
+    /// The nesting level of the timer currently running, or 0 outside of timers
+    timer_nesting_level: Arc<AtomicU32>,
+    /// The map of animation frame callbacks, in the order they were requested
+    animation_frame_callbacks: Arc<Mutex<Vec<(i32, FrameRequestCallback)>>>,
+    next_animation_frame_id: Arc<AtomicI32>,
+    /// The configuration of the sandbox, as changed since it was created
+    config: Arc<RwLock<SandboxConfig>>,
+    /// The scroll position of the viewport, as (x, y)
+    scroll_position: Arc<RwLock<(f64, f64)>>,
+    /// State of the random number generator
+    random_state: Arc<Mutex<u64>>,
+    /// Media query lists which fire change events, in the order they were created
+    media_query_lists: Arc<RwLock<Vec<Weak<MediaQueryList>>>>,
+    history: Arc<History>,
+    location: Arc<Location>,
+    console: Arc<Console>,
+    /// The window's storage objects, created when first used
+    local_storage: Arc<Mutex<Option<Arc<Storage>>>>,
+    session_storage: Arc<Mutex<Option<Arc<Storage>>>>,
+    /// The storage behind `session_storage`, which lasts as long as the window
+    session_storage_area: SharedStorage,
 }
 
 #[sourcegen::generated]
@@ -30,21 +91,543 @@ impl SandboxMemberBehavior for Window {
 }
 
 impl Window {
-    pub(crate) fn new(context: Weak<Sandbox>) -> Arc<Window> {
+    pub(crate) fn new(context: Weak<Sandbox>, config: SandboxConfig) -> Arc<Window> {
         Arc::new_cyclic(|win_weak| {
             let document = DocumentNodeArc::new(
                 context.clone(),
                 Arc::new(DocumentStore {
                     default_view: win_weak.clone(),
+                    url: Arc::new(RwLock::new(config.url())),
+                    referrer: config.referrer(),
+                    ..Default::default()
                 }),
             );
-            Window { context, document }
+            let history = History::new(context.clone(), win_weak.clone(), config.url());
+            let location = Location::new(context.clone(), Arc::downgrade(&history));
+            let console = Console::new(context.clone());
+            Window {
+                context,
+                document,
+                self_weak: win_weak.clone(),
+                event_target_behavior: EventTargetBehaviorStorage::new(),
+                active_timers: Default::default(),
+                next_timer_id: Arc::new(AtomicI32::new(1)),
+                timer_nesting_level: Arc::new(AtomicU32::new(0)),
+                animation_frame_callbacks: Default::default(),
+                next_animation_frame_id: Arc::new(AtomicI32::new(1)),
+                random_state: Arc::new(Mutex::new(config.seed())),
+                config: Arc::new(RwLock::new(config)),
+                scroll_position: Arc::new(RwLock::new((0.0, 0.0))),
+                media_query_lists: Default::default(),
+                history,
+                location,
+                console,
+                local_storage: Default::default(),
+                session_storage: Default::default(),
+                session_storage_area: Default::default(),
+            }
         })
     }
 
+    pub(crate) fn self_arc(&self) -> Result<Arc<Window>, DomError> {
+        self.self_weak.upgrade().ok_or(DomError::SandboxDropped)
+    }
+
+    pub(crate) fn self_weak(&self) -> Weak<Window> {
+        self.self_weak.clone()
+    }
+
+    proxy_event_target_behavior!();
+
     /// Gets the window's document
     // would be nice to have DocumentNode
     pub fn document(&self) -> DocumentNodeArc {
         self.document.clone()
     }
+
+    /// [Window.location](https://developer.mozilla.org/en-US/docs/Web/API/Window/location)
+    pub fn location(&self) -> Arc<Location> {
+        self.location.clone()
+    }
+
+    /// [Window.history](https://developer.mozilla.org/en-US/docs/Web/API/Window/history)
+    pub fn history(&self) -> Arc<History> {
+        self.history.clone()
+    }
+
+    /// [Window.console](https://developer.mozilla.org/en-US/docs/Web/API/console)
+    pub fn console(&self) -> Arc<Console> {
+        self.console.clone()
+    }
+
+    /// [Window.localStorage](https://developer.mozilla.org/en-US/docs/Web/API/Window/localStorage):
+    /// the items of the document's origin in the configured local storage. Fails with
+    /// `DomError::Security` if the origin is opaque, e.g. for about:blank.
+    pub fn local_storage(&self) -> Result<Arc<Storage>, DomError> {
+        let shared = self.config.read().unwrap().local_storage();
+        self.storage(&self.local_storage, shared)
+    }
+
+    /// [Window.sessionStorage](https://developer.mozilla.org/en-US/docs/Web/API/Window/sessionStorage):
+    /// like `local_storage`, but kept only for the lifetime of the window
+    pub fn session_storage(&self) -> Result<Arc<Storage>, DomError> {
+        self.storage(&self.session_storage, self.session_storage_area.clone())
+    }
+
+    fn storage(
+        &self,
+        slot: &Mutex<Option<Arc<Storage>>>,
+        shared: SharedStorage,
+    ) -> Result<Arc<Storage>, DomError> {
+        let mut slot = slot.lock().unwrap();
+        if let Some(storage) = slot.as_ref() {
+            return Ok(storage.clone());
+        }
+
+        let origin = self.history.document_url()?.origin();
+        if !origin.is_tuple() {
+            return Err(DomError::Security);
+        }
+        let storage = Storage::new(
+            self.context.clone(),
+            self.self_weak.clone(),
+            origin.ascii_serialization(),
+            shared,
+        );
+        *slot = Some(storage.clone());
+        Ok(storage)
+    }
+
+    /// [Window.innerWidth](https://developer.mozilla.org/en-US/docs/Web/API/Window/innerWidth)
+    pub fn inner_width(&self) -> u16 {
+        self.config.read().unwrap().screen_metrics.inner_width
+    }
+
+    /// [Window.innerHeight](https://developer.mozilla.org/en-US/docs/Web/API/Window/innerHeight)
+    pub fn inner_height(&self) -> u16 {
+        self.config.read().unwrap().screen_metrics.inner_height
+    }
+
+    /// [Window.outerWidth](https://developer.mozilla.org/en-US/docs/Web/API/Window/outerWidth)
+    pub fn outer_width(&self) -> u16 {
+        self.config.read().unwrap().screen_metrics.outer_width
+    }
+
+    /// [Window.outerHeight](https://developer.mozilla.org/en-US/docs/Web/API/Window/outerHeight)
+    pub fn outer_height(&self) -> u16 {
+        self.config.read().unwrap().screen_metrics.outer_height
+    }
+
+    /// [Window.devicePixelRatio](https://developer.mozilla.org/en-US/docs/Web/API/Window/devicePixelRatio)
+    pub fn device_pixel_ratio(&self) -> f64 {
+        self.config
+            .read()
+            .unwrap()
+            .screen_metrics
+            .device_pixel_ratio
+    }
+
+    /// [Window.screen](https://developer.mozilla.org/en-US/docs/Web/API/Window/screen)
+    pub fn screen(&self) -> Screen {
+        let screen_metrics = &self.config.read().unwrap().screen_metrics;
+        Screen {
+            width: screen_metrics.screen_width,
+            height: screen_metrics.screen_height,
+        }
+    }
+
+    /// Gives the sandbox's configuration, including any changes made since it was created
+    pub fn config(&self) -> SandboxConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    /// [Window.navigator](https://developer.mozilla.org/en-US/docs/Web/API/Window/navigator)
+    pub fn navigator(&self) -> Navigator {
+        let config = self.config.read().unwrap();
+        Navigator {
+            user_agent: config.user_agent(),
+            languages: config.languages(),
+            cookie_enabled: config.cookie_enabled(),
+            on_line: config.online(),
+        }
+    }
+
+    pub(crate) fn set_online(&self, online: bool) -> Result<(), DomError> {
+        {
+            let mut config = self.config.write().unwrap();
+            if config.online() == online {
+                return Ok(());
+            }
+            config.set_online(online);
+        }
+        let event_type = if online { "online" } else { "offline" };
+        self.dispatch_event(&Event::new(event_type.to_owned(), EventInit::default()))
+            .map(|_| ())
+    }
+
+    /// [Math.random](https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/Math/random):
+    /// a number in [0, 1) from the sandbox's generator, which is seeded by the configuration so
+    /// that every run gives the same sequence
+    pub fn random(&self) -> f64 {
+        // splitmix64
+        let mut state = self.random_state.lock().unwrap();
+        *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        // The top 53 bits fill the mantissa of an f64
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Gives the current screen metrics
+    pub fn screen_metrics(&self) -> ScreenMetrics {
+        self.config.read().unwrap().screen_metrics.clone()
+    }
+
+    pub(crate) fn set_screen_metrics(&self, screen_metrics: ScreenMetrics) -> Result<(), DomError> {
+        let resized = {
+            let current = &mut self.config.write().unwrap().screen_metrics;
+            let resized = current.inner_width != screen_metrics.inner_width
+                || current.inner_height != screen_metrics.inner_height;
+            *current = screen_metrics;
+            resized
+        };
+
+        if resized {
+            self.dispatch_event(&Event::new("resize".to_owned(), EventInit::default()))?;
+        }
+        self.evaluate_media_queries()
+    }
+
+    /// Gives the preferences of the simulated user
+    pub fn user_preferences(&self) -> UserPreferences {
+        self.config.read().unwrap().user_preferences.clone()
+    }
+
+    pub(crate) fn set_user_preferences(
+        &self,
+        user_preferences: UserPreferences,
+    ) -> Result<(), DomError> {
+        self.config.write().unwrap().user_preferences = user_preferences;
+        self.evaluate_media_queries()
+    }
+
+    /// [Window.matchMedia](https://developer.mozilla.org/en-US/docs/Web/API/Window/matchMedia):
+    /// parses a media query list, e.g. `"screen and (min-width: 600px)"`
+    pub fn match_media(&self, query: &str) -> Arc<MediaQueryList> {
+        let list = MediaQueryList::new(self.context.clone(), self, query);
+        self.media_query_lists
+            .write()
+            .unwrap()
+            .push(Arc::downgrade(&list));
+        list
+    }
+
+    /// [Evaluates media queries and reports changes](https://drafts.csswg.org/cssom-view/#evaluate-media-queries-and-report-changes),
+    /// firing a change event at each media query list whose result changed
+    fn evaluate_media_queries(&self) -> Result<(), DomError> {
+        let lists: Vec<Arc<MediaQueryList>> = {
+            let mut lists = self.media_query_lists.write().unwrap();
+            lists.retain(|list| list.strong_count() > 0);
+            lists.iter().filter_map(Weak::upgrade).collect()
+        };
+
+        for list in lists {
+            if let Some(matches) = list.update() {
+                let event = Event::new(
+                    "change".to_owned(),
+                    EventInit {
+                        kind: EventKind::MediaQueryList(MediaQueryListEventData {
+                            media: list.media(),
+                            matches,
+                        }),
+                        ..Default::default()
+                    },
+                );
+                list.dispatch_event(&event)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// [Window.scrollX](https://developer.mozilla.org/en-US/docs/Web/API/Window/scrollX)
+    pub fn scroll_x(&self) -> f64 {
+        self.scroll_position.read().unwrap().0
+    }
+
+    /// [Window.scrollY](https://developer.mozilla.org/en-US/docs/Web/API/Window/scrollY)
+    pub fn scroll_y(&self) -> f64 {
+        self.scroll_position.read().unwrap().1
+    }
+
+    /// [Window.scrollTo](https://developer.mozilla.org/en-US/docs/Web/API/Window/scrollTo).
+    /// There is no layout, so the position is only kept from going negative. Fires a scroll
+    /// event at the document if the position changed.
+    pub fn scroll_to(&self, x: f64, y: f64) -> Result<(), DomError> {
+        let position = (x.max(0.0), y.max(0.0));
+        let changed = {
+            let mut current = self.scroll_position.write().unwrap();
+            let changed = *current != position;
+            *current = position;
+            changed
+        };
+
+        if changed {
+            let event = Event::new(
+                "scroll".to_owned(),
+                EventInit {
+                    bubbles: true,
+                    ..Default::default()
+                },
+            );
+            self.document.dispatch_event(&event)?;
+        }
+        Ok(())
+    }
+
+    /// [fetch](https://developer.mozilla.org/en-US/docs/Web/API/fetch): requests `input`,
+    /// resolved against the document's URL, from the sandbox's request handler. `callback` is
+    /// called in a task with the response, or with `DomError::Type` if the request fails.
+    /// Responses with error statuses such as 404 are not failures.
+    pub fn fetch(
+        &self,
+        input: String,
+        init: RequestInit,
+        callback: impl FnOnce(Result<Response, DomError>) + Send + 'static,
+    ) -> Result<(), DomError> {
+        let context = self.context.upgrade().ok_or(DomError::SandboxDropped)?;
+        crate::fetch::fetch(&context, input, init, Box::new(callback));
+        Ok(())
+    }
+
+    /// [setTimeout](https://developer.mozilla.org/en-US/docs/Web/API/setTimeout): calls
+    /// `handler` once `timeout` has elapsed on the sandbox's virtual clock. Returns the id of
+    /// the timer.
+    pub fn set_timeout(
+        &self,
+        handler: impl FnOnce() + Send + 'static,
+        timeout: Duration,
+    ) -> Result<i32, DomError> {
+        let handler = Mutex::new(Some(handler));
+        let handler: TimerHandler = Arc::new(move || {
+            if let Some(handler) = handler.lock().unwrap().take() {
+                handler();
+            }
+        });
+        self.initialize_timer(handler, timeout, false, None)
+    }
+
+    /// [setInterval](https://developer.mozilla.org/en-US/docs/Web/API/setInterval): calls
+    /// `handler` every time `timeout` elapses on the sandbox's virtual clock, until the
+    /// interval is cleared. Returns the id of the timer.
+    pub fn set_interval(
+        &self,
+        handler: impl Fn() + Send + Sync + 'static,
+        timeout: Duration,
+    ) -> Result<i32, DomError> {
+        self.initialize_timer(Arc::new(handler), timeout, true, None)
+    }
+
+    /// [clearTimeout](https://developer.mozilla.org/en-US/docs/Web/API/clearTimeout)
+    pub fn clear_timeout(&self, id: i32) {
+        if let Some(handle) = self.active_timers.write().unwrap().remove(&id) {
+            if let Some(context) = self.context.upgrade() {
+                context.cancel_task(handle);
+            }
+        }
+    }
+
+    /// [clearInterval](https://developer.mozilla.org/en-US/docs/Web/API/clearInterval).
+    /// Timeouts and intervals share their ids, so this is the same as `clear_timeout`.
+    pub fn clear_interval(&self, id: i32) {
+        self.clear_timeout(id)
+    }
+
+    /// [requestAnimationFrame](https://developer.mozilla.org/en-US/docs/Web/API/window/requestAnimationFrame):
+    /// calls `callback` with the frame's timestamp the next time the sandbox renders a frame
+    /// (see `Sandbox::tick_frame`). Returns the id of the request.
+    pub fn request_animation_frame(
+        &self,
+        callback: impl FnOnce(DomHighResTimeStamp) + Send + 'static,
+    ) -> i32 {
+        let id = self.next_animation_frame_id.fetch_add(1, Ordering::SeqCst);
+        self.animation_frame_callbacks
+            .lock()
+            .unwrap()
+            .push((id, Box::new(callback)));
+        id
+    }
+
+    /// [cancelAnimationFrame](https://developer.mozilla.org/en-US/docs/Web/API/Window/cancelAnimationFrame)
+    pub fn cancel_animation_frame(&self, id: i32) {
+        self.animation_frame_callbacks
+            .lock()
+            .unwrap()
+            .retain(|(callback_id, _)| *callback_id != id);
+    }
+
+    /// [Runs the animation frame callbacks](https://html.spec.whatwg.org/multipage/imagebitmap-and-animations.html#run-the-animation-frame-callbacks)
+    /// which were requested before the frame started, in order. Callbacks requested while they
+    /// run wait for the next frame.
+    pub(crate) fn run_animation_frame_callbacks(
+        &self,
+        context: &Sandbox,
+        now: DomHighResTimeStamp,
+    ) {
+        let ids: Vec<i32> = self
+            .animation_frame_callbacks
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in ids {
+            // An earlier callback may have canceled this one
+            let callback = {
+                let mut callbacks = self.animation_frame_callbacks.lock().unwrap();
+                match callbacks
+                    .iter()
+                    .position(|(callback_id, _)| *callback_id == id)
+                {
+                    Some(index) => callbacks.remove(index).1,
+                    None => continue,
+                }
+            };
+            callback(now);
+            context.perform_microtask_checkpoint();
+        }
+    }
+
+    /// The [timer initialization steps](https://html.spec.whatwg.org/multipage/timers-and-user-prompts.html#timer-initialisation-steps).
+    /// An interval reschedules itself with its `previous_id`.
+    fn initialize_timer(
+        &self,
+        handler: TimerHandler,
+        timeout: Duration,
+        repeat: bool,
+        previous_id: Option<i32>,
+    ) -> Result<i32, DomError> {
+        let context = self.context.upgrade().ok_or(DomError::SandboxDropped)?;
+        let id = previous_id.unwrap_or_else(|| self.next_timer_id.fetch_add(1, Ordering::SeqCst));
+
+        let nesting_level = self.timer_nesting_level.load(Ordering::SeqCst);
+        let clamped_timeout = if nesting_level > MAX_TIMER_NESTING_LEVEL {
+            timeout.max(MIN_NESTED_TIMEOUT)
+        } else {
+            timeout
+        };
+
+        let window = self.self_weak.clone();
+        let handle = context.schedule_task(
+            clamped_timeout,
+            Box::new(move || {
+                if let Some(window) = window.upgrade() {
+                    window.run_timer(id, handler, timeout, repeat, nesting_level + 1);
+                }
+            }),
+        );
+        self.active_timers.write().unwrap().insert(id, handle);
+        Ok(id)
+    }
+
+    fn run_timer(
+        &self,
+        id: i32,
+        handler: TimerHandler,
+        timeout: Duration,
+        repeat: bool,
+        nesting_level: u32,
+    ) {
+        if !self.active_timers.read().unwrap().contains_key(&id) {
+            return;
+        }
+
+        let outer_nesting_level = self
+            .timer_nesting_level
+            .swap(nesting_level, Ordering::SeqCst);
+        handler();
+        // The handler may have cleared its own timer
+        if self.active_timers.read().unwrap().contains_key(&id) {
+            if repeat {
+                let _ = self.initialize_timer(handler, timeout, true, Some(id));
+            } else {
+                self.active_timers.write().unwrap().remove(&id);
+            }
+        }
+        self.timer_nesting_level
+            .store(outer_nesting_level, Ordering::SeqCst);
+    }
+}
+
+impl_event_target!(Window, event_target_behavior);
+
+/// The [Screen](https://developer.mozilla.org/en-US/docs/Web/API/Screen) the window is shown on
+#[derive(Clone, Debug, PartialEq)]
+pub struct Screen {
+    width: u16,
+    height: u16,
+}
+
+impl Screen {
+    /// [Screen.width](https://developer.mozilla.org/en-US/docs/Web/API/Screen/width)
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    /// [Screen.height](https://developer.mozilla.org/en-US/docs/Web/API/Screen/height)
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// [Screen.availWidth](https://developer.mozilla.org/en-US/docs/Web/API/Screen/availWidth):
+    /// the simulated screen has no taskbars, so this is the full width
+    pub fn avail_width(&self) -> u16 {
+        self.width
+    }
+
+    /// [Screen.availHeight](https://developer.mozilla.org/en-US/docs/Web/API/Screen/availHeight):
+    /// the simulated screen has no taskbars, so this is the full height
+    pub fn avail_height(&self) -> u16 {
+        self.height
+    }
+}
+
+/// The [Navigator](https://developer.mozilla.org/en-US/docs/Web/API/Navigator): the identity
+/// and state of the simulated browser
+#[derive(Clone, Debug, PartialEq)]
+pub struct Navigator {
+    user_agent: String,
+    languages: Vec<String>,
+    cookie_enabled: bool,
+    on_line: bool,
+}
+
+impl Navigator {
+    /// [Navigator.userAgent](https://developer.mozilla.org/en-US/docs/Web/API/Navigator/userAgent)
+    pub fn user_agent(&self) -> String {
+        self.user_agent.clone()
+    }
+
+    /// [Navigator.language](https://developer.mozilla.org/en-US/docs/Web/API/Navigator/language):
+    /// the most preferred language
+    pub fn language(&self) -> Option<String> {
+        self.languages.first().cloned()
+    }
+
+    /// [Navigator.languages](https://developer.mozilla.org/en-US/docs/Web/API/Navigator/languages)
+    pub fn languages(&self) -> Vec<String> {
+        self.languages.clone()
+    }
+
+    /// [Navigator.cookieEnabled](https://developer.mozilla.org/en-US/docs/Web/API/Navigator/cookieEnabled)
+    pub fn cookie_enabled(&self) -> bool {
+        self.cookie_enabled
+    }
+
+    /// [Navigator.onLine](https://developer.mozilla.org/en-US/docs/Web/API/Navigator/onLine)
+    pub fn on_line(&self) -> bool {
+        self.on_line
+    }
 }